@@ -0,0 +1,56 @@
+use std::time::{Duration, Instant};
+
+use rust_droid::common::relative_rect::RelativeRect;
+use rust_droid::models::CaptureStrategy;
+use rust_droid::{Droid, DroidConfig};
+
+/// Number of captures measured per strategy.
+const SAMPLES: usize = 10;
+
+fn main() -> anyhow::Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    println!(
+        "{:<24} {:>10} {:>10} {:>10}",
+        "strategy", "mean", "min", "max"
+    );
+
+    for (name, strategy) in [("png", CaptureStrategy::Png), ("raw", CaptureStrategy::Raw)] {
+        let mut droid = Droid::new(DroidConfig::default().capture_strategy(strategy))?;
+        report(name, || droid.screenshot().map(|_| ()))?;
+    }
+
+    // The top tenth of the screen, e.g. the status bar and a toolbar.
+    let mut droid = Droid::new(DroidConfig::default().capture_strategy(CaptureStrategy::Raw))?;
+    let region = RelativeRect::new(0.0, 0.0, 1.0, 0.1);
+    report("raw region (10%)", || {
+        droid.screenshot_region(region).map(|_| ())
+    })?;
+
+    Ok(())
+}
+
+fn report(
+    name: &str,
+    mut capture: impl FnMut() -> rust_droid::error::Result<()>,
+) -> anyhow::Result<()> {
+    // Warm up: the first capture also learns the raw layout.
+    capture()?;
+
+    let mut samples = Vec::with_capacity(SAMPLES);
+    for _ in 0..SAMPLES {
+        let start = Instant::now();
+        capture()?;
+        samples.push(start.elapsed());
+    }
+
+    let total: Duration = samples.iter().sum();
+    println!(
+        "{:<24} {:>10.1?} {:>10.1?} {:>10.1?}",
+        name,
+        total / SAMPLES as u32,
+        samples.iter().min().unwrap(),
+        samples.iter().max().unwrap()
+    );
+    Ok(())
+}
@@ -0,0 +1,28 @@
+use rust_droid::DroidConfig;
+use rust_droid::models::KeyCode;
+use rust_droid::pool::DevicePool;
+
+fn main() -> anyhow::Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    // Run a small smoke test on every connected device, four at a time.
+    let runs = DevicePool::new(DroidConfig::default())
+        .max_concurrency(4)
+        .run(|droid, log| {
+            let model = droid.getprop("ro.product.model")?;
+            log.info(format!("running on {}", model));
+
+            droid.keyevent(KeyCode::Home).execute()?;
+            droid.snapshot(format!("home_{}.png", droid.serial()))?;
+            Ok(model)
+        })?;
+
+    for run in &runs {
+        match &run.result {
+            Ok(model) => println!("{} ({}) passed in {:?}", run.serial, model, run.elapsed),
+            Err(e) => println!("{} failed: {}", run.serial, e),
+        }
+    }
+
+    Ok(())
+}
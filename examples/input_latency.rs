@@ -0,0 +1,63 @@
+use std::time::{Duration, Instant};
+
+use rust_droid::common::point::Point;
+use rust_droid::models::InputInjection;
+use rust_droid::{Droid, DroidConfig, Target};
+
+/// Number of taps measured per configuration.
+const SAMPLES: usize = 20;
+
+fn main() -> anyhow::Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    // Tap somewhere harmless, e.g. an empty area of the home screen.
+    let point = Point::new(10, 10);
+
+    let configurations = [
+        ("one-off shell + input", DroidConfig::default()),
+        (
+            "persistent shell + input",
+            DroidConfig::default().persistent_shell(true),
+        ),
+        (
+            "persistent shell + sendevent",
+            DroidConfig::default()
+                .persistent_shell(true)
+                .input_injection(InputInjection::Sendevent),
+        ),
+    ];
+
+    println!(
+        "{:<30} {:>10} {:>10} {:>10} {:>9}",
+        "path", "mean", "min", "max", "speedup"
+    );
+    // The first configuration is the path every command took before the
+    // persistent shell and direct injection existed.
+    let mut baseline: Option<Duration> = None;
+    for (name, config) in configurations {
+        let mut droid = Droid::new(config)?;
+        // Warm up: opens the session and probes the touchscreen.
+        droid.touch(Target::Point(point)).execute()?;
+
+        let mut samples = Vec::with_capacity(SAMPLES);
+        for _ in 0..SAMPLES {
+            let start = Instant::now();
+            droid.touch(Target::Point(point)).execute()?;
+            samples.push(start.elapsed());
+        }
+
+        let total: Duration = samples.iter().sum();
+        let mean = total / SAMPLES as u32;
+        let baseline = *baseline.get_or_insert(mean);
+        println!(
+            "{:<30} {:>10.1?} {:>10.1?} {:>10.1?} {:>8.1}x",
+            name,
+            mean,
+            samples.iter().min().unwrap(),
+            samples.iter().max().unwrap(),
+            baseline.as_secs_f64() / mean.as_secs_f64()
+        );
+    }
+
+    Ok(())
+}
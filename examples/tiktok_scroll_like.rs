@@ -1,8 +1,10 @@
 use core::time::Duration;
 
-use image::GenericImageView;
 use rand::Rng;
-use rust_droid::{AppPackages, Droid, DroidConfig, TIKTOK_LIKE_POINT, Target, error::DroidError};
+use rust_droid::{
+    AppPackages, Droid, DroidConfig, TIKTOK_LIKE_POINT, Target, error::DroidError,
+    models::Direction,
+};
 
 fn main() -> anyhow::Result<()> {
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
@@ -22,14 +24,6 @@ fn main() -> anyhow::Result<()> {
 
     droid.sleep(Duration::from_secs(3));
 
-    let (w, h) = droid.screenshot()?.dimensions();
-    let center_x = w / 2;
-    let start_y = (h as f32 * 0.80) as u32;
-    let end_y = (h as f32 * 0.20) as u32;
-
-    let start = rust_droid::common::point::Point::new(center_x, start_y);
-    let end = rust_droid::common::point::Point::new(center_x, end_y);
-
     let mut rng = rand::thread_rng();
 
     loop {
@@ -37,7 +31,8 @@ fn main() -> anyhow::Result<()> {
         droid.sleep(Duration::from_secs(delay_secs));
 
         droid
-            .swipe(Target::Point(start), Target::Point(end))
+            .scroll(Direction::Up)
+            .distance(0.6)
             .duration(Duration::from_millis(350))
             .execute()?;
 
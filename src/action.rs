@@ -1,4 +1,9 @@
+pub mod drag;
+pub mod expect;
 pub mod keyevent;
+pub mod path;
+pub mod scroll;
+pub mod scroll_to;
 pub mod swipe;
 pub mod text;
 pub mod touch;
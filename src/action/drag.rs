@@ -0,0 +1,137 @@
+use crate::action::path::{self, MotionProfile};
+use crate::common::easing::Easing;
+use crate::common::relative_rect::RelativeRect;
+use crate::{Droid, Result, Target};
+use std::time::Duration;
+
+/// Builds and executes a drag-and-drop action.
+///
+/// The item under `from` is long-pressed until it is picked up, moved to
+/// `to`, held there briefly and released. This struct is created by the
+/// `Droid::drag()` method.
+pub struct DragBuilder<'a> {
+    droid: &'a mut Droid,
+    from: Target,
+    to: Target,
+    pick_up: Duration,
+    duration: Duration,
+    drop_hold: Duration,
+    threshold: Option<f32>,
+    from_search_rect: Option<RelativeRect>,
+    to_search_rect: Option<RelativeRect>,
+}
+
+impl<'a> DragBuilder<'a> {
+    pub fn new(droid: &'a mut Droid, from: Target, to: Target) -> Self {
+        Self {
+            droid,
+            from,
+            to,
+            pick_up: Duration::from_millis(800),
+            duration: Duration::from_millis(600),
+            drop_hold: Duration::from_millis(300),
+            threshold: None,
+            from_search_rect: None,
+            to_search_rect: None,
+        }
+    }
+
+    /// Sets how long the item is pressed before it starts moving.
+    ///
+    /// Must exceed the device's long-press timeout for the item to be picked up.
+    /// Default is `800ms`.
+    pub fn pick_up(mut self, duration: Duration) -> Self {
+        self.pick_up = duration;
+        self
+    }
+
+    /// Sets how long the move from source to destination takes.
+    ///
+    /// Default is `600ms`.
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Sets how long the item is held over the destination before release.
+    ///
+    /// Default is `300ms`.
+    pub fn drop_hold(mut self, duration: Duration) -> Self {
+        self.drop_hold = duration;
+        self
+    }
+
+    pub fn threshold(mut self, value: f32) -> Self {
+        self.threshold = Some(value);
+        self
+    }
+
+    pub fn search_from_in(mut self, rect: RelativeRect) -> Self {
+        self.from_search_rect = Some(rect);
+        self
+    }
+
+    pub fn search_to_in(mut self, rect: RelativeRect) -> Self {
+        self.to_search_rect = Some(rect);
+        self
+    }
+
+    /// Executes the drag-and-drop.
+    ///
+    /// Devices that support motion events get the configured hold times
+    /// exactly. Older devices fall back to `input draganddrop`, where the
+    /// pick-up time is chosen by the device, or to a long `input swipe`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either target cannot be found or if the
+    /// underlying ADB command fails.
+    pub fn execute(self) -> Result<()> {
+        let threshold = self
+            .threshold
+            .unwrap_or(self.droid.config.default_confidence);
+        let from_point = self
+            .droid
+            .resolve_target(&self.from, threshold, self.from_search_rect)?;
+        let to_point = self
+            .droid
+            .resolve_target(&self.to, threshold, self.to_search_rect)?;
+
+        log::info!(
+            "Executing drag from {:?} to {:?} (pick-up {:?}, move {:?}, drop hold {:?})",
+            from_point,
+            to_point,
+            self.pick_up,
+            self.duration,
+            self.drop_hold
+        );
+
+        let controller = &mut self.droid.controller;
+        if controller.supports_motion_events()? {
+            let profile = MotionProfile {
+                duration: self.duration,
+                easing: Easing::EaseInOut,
+                curve: 0.0,
+                steps: 10,
+            };
+            path::perform_path(
+                controller,
+                &[from_point, to_point],
+                &[self.pick_up, self.drop_hold],
+                &profile,
+            )?;
+        } else if controller.supports_drag_and_drop()? {
+            log::debug!("Using `input draganddrop`; hold times are chosen by the device.");
+            controller.drag_and_drop(from_point, to_point, self.duration)?;
+        } else {
+            log::warn!("Device supports neither motion events nor drag-and-drop; using a swipe.");
+            controller.swipe(
+                from_point,
+                to_point,
+                self.pick_up + self.duration + self.drop_hold,
+            )?;
+        }
+        // A failed drag returns early; a crash it caused is reported by the next check.
+        self.droid.check_crash()
+    }
+}
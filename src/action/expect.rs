@@ -0,0 +1,446 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use image::DynamicImage;
+
+use crate::retry::{ActionOutcome, RetryPolicy};
+use crate::{Droid, DroidError, Result, Target, vision};
+
+/// Fraction of the screen that must differ for `expect_screen_change` to pass.
+const SCREEN_CHANGE_TOLERANCE: f32 = 0.002;
+
+/// What an action is expected to cause on screen.
+#[derive(Debug, Clone)]
+pub(crate) enum Condition {
+    Appears(Target),
+    Gone(Target),
+    ScreenChange,
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Condition::Appears(target) => write!(f, "{:?} appears", target),
+            Condition::Gone(target) => write!(f, "{:?} is gone", target),
+            Condition::ScreenChange => write!(f, "the screen changes"),
+        }
+    }
+}
+
+/// A post-condition checked after an action, which re-issues the action
+/// while the condition is not met.
+#[derive(Debug, Clone)]
+pub(crate) struct Expectation {
+    pub condition: Option<Condition>,
+    /// How long to wait for the condition after each issue.
+    pub timeout: Duration,
+    /// How many more times the action is issued if the condition is not met.
+    pub reissues: u32,
+}
+
+impl Default for Expectation {
+    fn default() -> Self {
+        Self {
+            condition: None,
+            timeout: Duration::from_secs(3),
+            reissues: 2,
+        }
+    }
+}
+
+/// Generates the `retry` and `expect_*` setters of an action builder with
+/// `retry: Option<RetryPolicy>` and `expectation: Expectation` fields.
+/// `$action` names the action in the docs, e.g. `"touch"`.
+macro_rules! setters {
+    ($action:literal) => {
+        /// Overrides the retry policy from `DroidConfig` for this action.
+        pub fn retry(mut self, policy: $crate::retry::RetryPolicy) -> Self {
+            self.retry = Some(policy);
+            self
+        }
+
+        #[doc = concat!("Re-issues the ", $action, " until `target` is on screen.")]
+        ///
+        /// `target` must be an image; a point target fails the action with
+        /// `DroidError::InvalidTarget`.
+        pub fn expect_appears(mut self, target: $crate::Target) -> Self {
+            self.expectation.condition = Some($crate::action::expect::Condition::Appears(target));
+            self
+        }
+
+        #[doc = concat!("Re-issues the ", $action, " until `target` is no longer on screen.")]
+        ///
+        /// `target` must be an image, as for `expect_appears`.
+        pub fn expect_gone(mut self, target: $crate::Target) -> Self {
+            self.expectation.condition = Some($crate::action::expect::Condition::Gone(target));
+            self
+        }
+
+        #[doc = concat!("Re-issues the ", $action, " until the screen content changes.")]
+        pub fn expect_screen_change(mut self) -> Self {
+            self.expectation.condition = Some($crate::action::expect::Condition::ScreenChange);
+            self
+        }
+
+        /// Sets how long to wait for the expectation after each issue.
+        ///
+        /// Default is `3s`.
+        pub fn expect_within(mut self, timeout: std::time::Duration) -> Self {
+            self.expectation.timeout = timeout;
+            self
+        }
+
+        #[doc = concat!("Sets how many more times the ", $action, " is issued if the expectation is not met.")]
+        ///
+        /// Default is `2`.
+        pub fn reissue(mut self, times: u32) -> Self {
+            self.expectation.reissues = times;
+            self
+        }
+    };
+}
+pub(crate) use setters;
+
+/// An action builder whose action can be performed repeatedly.
+pub(crate) trait Action {
+    fn droid(&mut self) -> &mut Droid;
+
+    /// Performs the action once.
+    fn perform(&mut self) -> Result<()>;
+}
+
+/// Performs `action` under `retry`, then checks `expectation`, re-issuing the
+/// action until it is met or the re-issues are used up.
+pub(crate) fn execute<A: Action>(
+    action: &mut A,
+    name: &str,
+    retry: &RetryPolicy,
+    expectation: &Expectation,
+) -> Result<ActionOutcome> {
+    action.droid().check_crash()?;
+    let Some(condition) = &expectation.condition else {
+        let ((), outcome) = retry.run(name, || perform_checked(action))?;
+        return Ok(outcome);
+    };
+    condition.validate()?;
+
+    // The screen before the latest issue, for `Condition::ScreenChange`.
+    let mut state = (action, None::<DynamicImage>);
+    reissue_until(
+        &mut state,
+        name,
+        condition,
+        expectation.reissues,
+        |(action, before)| {
+            *before = match condition {
+                Condition::ScreenChange => Some(action.droid().capture()?.0),
+                _ => None,
+            };
+            let ((), outcome) = retry.run(name, || perform_checked(*action))?;
+            Ok(outcome.attempts)
+        },
+        |(action, before)| {
+            wait_for(
+                action.droid(),
+                condition,
+                before.as_ref(),
+                expectation.timeout,
+            )
+        },
+    )
+}
+
+impl Condition {
+    /// Rejects point targets, which are always "on screen" and so would make
+    /// `Appears` pass and `Gone` fail without looking.
+    fn validate(&self) -> Result<()> {
+        match self {
+            Condition::Appears(Target::Point(p)) | Condition::Gone(Target::Point(p)) => {
+                Err(DroidError::InvalidTarget(format!(
+                    "cannot expect the point {:?} to appear or disappear; use an image target",
+                    p
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Issues the action with `issue`, which returns how many attempts it took,
+/// until `met` reports the condition or `reissues` more issues are used up.
+fn reissue_until<S>(
+    state: &mut S,
+    name: &str,
+    condition: &Condition,
+    reissues: u32,
+    mut issue: impl FnMut(&mut S) -> Result<u32>,
+    mut met: impl FnMut(&mut S) -> Result<bool>,
+) -> Result<ActionOutcome> {
+    let mut attempts = 0;
+    for reissue in 0..=reissues {
+        attempts += issue(state)?;
+        if met(state)? {
+            return Ok(ActionOutcome {
+                attempts,
+                reissues: reissue,
+            });
+        }
+        if reissue < reissues {
+            log::warn!(
+                "{} did not have the expected effect ({}), issuing it again",
+                name,
+                condition
+            );
+        }
+    }
+
+    Err(DroidError::ExpectationFailed {
+        action: name.to_string(),
+        expectation: condition.to_string(),
+        attempts,
+    })
+}
+
+/// Performs `action` once and fails with `DroidError::AppCrashed` if the
+/// app crashed meanwhile, so that a crash is neither retried nor waited out.
+fn perform_checked<A: Action>(action: &mut A) -> Result<()> {
+    let result = action.perform();
+    action.droid().check_crash()?;
+    result
+}
+
+/// Waits up to `timeout` for `condition`, returning whether it was met.
+fn wait_for(
+    droid: &mut Droid,
+    condition: &Condition,
+    before: Option<&DynamicImage>,
+    timeout: Duration,
+) -> Result<bool> {
+    let threshold = droid.config.default_confidence;
+    let interval = droid.config.default_interval;
+
+    poll(
+        droid,
+        timeout,
+        |droid| {
+            let (screen, scale) = droid.capture()?;
+            let met = match condition {
+                Condition::Appears(target) | Condition::Gone(target) => {
+                    let resolved = droid.resolve_target_on(target, threshold, None, &screen, scale);
+                    let found = match resolved {
+                        Ok(_) => true,
+                        Err(DroidError::ImageNotFound(_)) => false,
+                        Err(e) => return Err(e),
+                    };
+                    if !found && matches!(condition, Condition::Appears(_)) {
+                        droid.handle_interruptions(&screen, scale)?;
+                    }
+                    found == matches!(condition, Condition::Appears(_))
+                }
+                Condition::ScreenChange => {
+                    before.is_none_or(|before| screen_changed(before, &screen))
+                }
+            };
+            if met {
+                log::debug!("Expectation met: {}", condition);
+            }
+            Ok(met)
+        },
+        |droid, remaining| droid.wait_for_change(interval.min(remaining), remaining),
+    )
+}
+
+/// Whether `screen` differs from `before` by more than `SCREEN_CHANGE_TOLERANCE`.
+fn screen_changed(before: &DynamicImage, screen: &DynamicImage) -> bool {
+    vision::image_difference(before, screen, None) > SCREEN_CHANGE_TOLERANCE
+}
+
+/// Calls `check` until it returns `true` or `timeout` elapses, calling
+/// `pause` with the remaining time in between. `check` runs at least once.
+fn poll<S>(
+    state: &mut S,
+    timeout: Duration,
+    mut check: impl FnMut(&mut S) -> Result<bool>,
+    mut pause: impl FnMut(&mut S, Duration),
+) -> Result<bool> {
+    let start = Instant::now();
+    loop {
+        if check(state)? {
+            return Ok(true);
+        }
+        let remaining = timeout.saturating_sub(start.elapsed());
+        if remaining.is_zero() {
+            return Ok(false);
+        }
+        pause(state, remaining);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    /// Issues taking `attempts[i]` attempts; the condition is met on `met_on`.
+    fn run(
+        attempts: &[u32],
+        met_on: Option<usize>,
+        reissues: u32,
+    ) -> (Result<ActionOutcome>, usize) {
+        let mut issues = 0;
+        let result = reissue_until(
+            &mut issues,
+            "touch",
+            &Condition::ScreenChange,
+            reissues,
+            |issues| {
+                *issues += 1;
+                Ok(attempts[*issues - 1])
+            },
+            |issues| Ok(Some(*issues) == met_on),
+        );
+        (result, issues)
+    }
+
+    #[test]
+    fn met_on_first_issue() {
+        let (result, issues) = run(&[1], Some(1), 2);
+        assert_eq!(issues, 1);
+        assert_eq!(
+            result.unwrap(),
+            ActionOutcome {
+                attempts: 1,
+                reissues: 0
+            }
+        );
+    }
+
+    #[test]
+    fn reissues_until_met_and_counts_retries() {
+        // The first issue needed two attempts, the re-issue one.
+        let (result, issues) = run(&[2, 1], Some(2), 2);
+        let outcome = result.unwrap();
+        assert_eq!(issues, 2);
+        assert_eq!(
+            outcome,
+            ActionOutcome {
+                attempts: 3,
+                reissues: 1
+            }
+        );
+        assert_eq!(outcome.retries(), 1);
+    }
+
+    #[test]
+    fn fails_once_reissues_are_used_up() {
+        let (result, issues) = run(&[1, 3, 1], None, 2);
+        assert_eq!(issues, 3);
+        match result {
+            Err(DroidError::ExpectationFailed {
+                action,
+                expectation,
+                attempts,
+            }) => {
+                assert_eq!(action, "touch");
+                assert_eq!(expectation, "the screen changes");
+                assert_eq!(attempts, 5);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn issue_errors_are_not_reissued() {
+        let mut issues = 0;
+        let result = reissue_until(
+            &mut issues,
+            "touch",
+            &Condition::ScreenChange,
+            2,
+            |issues| {
+                *issues += 1;
+                Err(DroidError::Timeout(Duration::ZERO))
+            },
+            |_| Ok(true),
+        );
+        assert_eq!(issues, 1);
+        assert!(matches!(result, Err(DroidError::Timeout(_))));
+    }
+
+    #[test]
+    fn poll_times_out_when_never_met() {
+        let mut checks = 0;
+        let timeout = Duration::from_millis(30);
+        let start = Instant::now();
+        let met = poll(
+            &mut checks,
+            timeout,
+            |checks| {
+                *checks += 1;
+                Ok(false)
+            },
+            |_, remaining| std::thread::sleep(remaining.min(Duration::from_millis(10))),
+        )
+        .unwrap();
+        assert!(!met);
+        assert!(start.elapsed() >= timeout);
+        assert!(checks >= 2);
+    }
+
+    #[test]
+    fn poll_stops_once_met() {
+        let mut checks = 0;
+        let met = poll(
+            &mut checks,
+            Duration::from_secs(5),
+            |checks| {
+                *checks += 1;
+                Ok(*checks == 3)
+            },
+            |_, _| {},
+        )
+        .unwrap();
+        assert!(met);
+        assert_eq!(checks, 3);
+    }
+
+    #[test]
+    fn poll_checks_once_without_timeout() {
+        let mut checks = 0;
+        let met = poll(
+            &mut checks,
+            Duration::ZERO,
+            |checks| {
+                *checks += 1;
+                Ok(false)
+            },
+            |_, _| panic!("no pause without time left"),
+        )
+        .unwrap();
+        assert!(!met);
+        assert_eq!(checks, 1);
+    }
+
+    #[test]
+    fn screen_change_ignores_tiny_differences() {
+        let before = DynamicImage::ImageRgb8(RgbImage::from_pixel(100, 100, Rgb([0, 0, 0])));
+        let mut cursor = RgbImage::from_pixel(100, 100, Rgb([0, 0, 0]));
+        cursor.put_pixel(0, 0, Rgb([255, 255, 255]));
+        assert!(!screen_changed(&before, &DynamicImage::ImageRgb8(cursor)));
+
+        let after = DynamicImage::ImageRgb8(RgbImage::from_pixel(100, 100, Rgb([255, 255, 255])));
+        assert!(screen_changed(&before, &after));
+    }
+
+    #[test]
+    fn point_targets_cannot_be_expected() {
+        let point = Target::Point(crate::common::point::Point::new(1, 2));
+        assert!(matches!(
+            Condition::Appears(point.clone()).validate(),
+            Err(DroidError::InvalidTarget(_))
+        ));
+        assert!(Condition::Gone(point).validate().is_err());
+        assert!(Condition::Appears(Target::from("a.png")).validate().is_ok());
+        assert!(Condition::ScreenChange.validate().is_ok());
+    }
+}
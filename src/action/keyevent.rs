@@ -1,3 +1,5 @@
+use crate::action::expect::{self, Action, Expectation};
+use crate::retry::{ActionOutcome, RetryPolicy};
 use crate::{Droid, Result, models::KeyCode};
 use std::time::Duration;
 
@@ -5,6 +7,10 @@ pub struct KeyeventBuilder<'a> {
     droid: &'a mut Droid,
     key_code: KeyCode,
     times: u32,
+    /// Presses of the current burst already sent, so a retry sends only the rest.
+    sent: u32,
+    retry: Option<RetryPolicy>,
+    expectation: Expectation,
 }
 
 impl<'a> KeyeventBuilder<'a> {
@@ -13,29 +19,56 @@ impl<'a> KeyeventBuilder<'a> {
             droid,
             key_code,
             times: 1,
+            sent: 0,
+            retry: None,
+            expectation: Expectation::default(),
         }
     }
 
+    /// Sets the number of key presses.
+    ///
+    /// A retry after an error sends only the presses that were not sent yet.
+    /// Default is `1`.
     pub fn times(mut self, count: u32) -> Self {
         self.times = count;
         self
     }
 
-    pub fn execute(self) -> Result<()> {
+    expect::setters!("key event");
+
+    pub fn execute(mut self) -> Result<ActionOutcome> {
+        let policy = self
+            .retry
+            .take()
+            .unwrap_or_else(|| self.droid.config.retry.clone());
+        let expectation = std::mem::take(&mut self.expectation);
+        expect::execute(&mut self, "keyevent", &policy, &expectation)
+    }
+}
+
+impl Action for KeyeventBuilder<'_> {
+    fn droid(&mut self) -> &mut Droid {
+        self.droid
+    }
+
+    fn perform(&mut self) -> Result<()> {
         log::info!(
             "Executing keyevent {:?} for {} times",
             self.key_code,
-            self.times
+            self.times - self.sent
         );
 
-        for i in 0..self.times {
+        for i in self.sent..self.times {
             self.droid.controller.input_keyevent(self.key_code as i32)?;
+            self.sent += 1;
 
             if self.times > 1 && i < self.times - 1 {
                 // 多次按键之间稍作停顿
                 std::thread::sleep(Duration::from_millis(50));
             }
         }
+        // A completed burst is sent in full again when the action is re-issued.
+        self.sent = 0;
         Ok(())
     }
 }
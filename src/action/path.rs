@@ -0,0 +1,287 @@
+use crate::common::easing::Easing;
+use crate::common::point::Point;
+use crate::device::DeviceController;
+use crate::models::{MotionAction, MotionEvent};
+use crate::{Droid, Result, Target};
+use std::time::Duration;
+
+/// How the pointer travels between consecutive waypoints of a gesture.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct MotionProfile {
+    /// Time spent moving along each segment.
+    pub duration: Duration,
+    /// Velocity profile applied within each segment.
+    pub easing: Easing,
+    /// Sideways bend of each segment, as a fraction of its length.
+    pub curve: f32,
+    /// Number of intermediate MOVE events per segment.
+    pub steps: u32,
+}
+
+struct Waypoint {
+    target: Target,
+    hold: Duration,
+}
+
+/// Builds and executes a drag along a path of waypoints.
+///
+/// The pointer goes down on the first waypoint, moves through every following
+/// waypoint without lifting, and goes up on the last one. Any waypoint can be
+/// an image target. This struct is created by the `Droid::path()` method.
+pub struct PathBuilder<'a> {
+    droid: &'a mut Droid,
+    waypoints: Vec<Waypoint>,
+    duration: Duration,
+    easing: Easing,
+    curve: f32,
+    steps: u32,
+    threshold: Option<f32>,
+}
+
+impl<'a> PathBuilder<'a> {
+    pub fn new(droid: &'a mut Droid, start: Target) -> Self {
+        Self {
+            droid,
+            waypoints: vec![Waypoint {
+                target: start,
+                hold: Duration::ZERO,
+            }],
+            duration: Duration::from_millis(300),
+            easing: Easing::Linear,
+            curve: 0.0,
+            steps: 10,
+            threshold: None,
+        }
+    }
+
+    /// Appends a waypoint the pointer moves to after the previous one.
+    pub fn to(mut self, target: Target) -> Self {
+        self.waypoints.push(Waypoint {
+            target,
+            hold: Duration::ZERO,
+        });
+        self
+    }
+
+    /// Keeps the pointer still on the most recently added waypoint.
+    ///
+    /// Calling it right after `Droid::path()` holds on the start point before
+    /// moving, which is what long-press-to-drag interactions expect.
+    pub fn hold(mut self, duration: Duration) -> Self {
+        if let Some(waypoint) = self.waypoints.last_mut() {
+            waypoint.hold += duration;
+        }
+        self
+    }
+
+    /// Sets the time spent moving along each segment between two waypoints.
+    ///
+    /// Default is `300ms`.
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Sets the velocity profile used within each segment.
+    ///
+    /// Default is `Easing::Linear`.
+    pub fn easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    /// Bends each segment into an arc.
+    ///
+    /// The value is the sideways offset of the arc's control point as a fraction
+    /// of the segment length; negative values bend to the other side.
+    /// Default is `0.0` (straight lines).
+    pub fn curve(mut self, bend: f32) -> Self {
+        self.curve = bend;
+        self
+    }
+
+    /// Sets the number of intermediate move events sent per segment.
+    ///
+    /// More steps give smoother motion at the cost of a longer gesture, since
+    /// every event starts an `input` process on the device, which takes a
+    /// couple of hundred milliseconds. Unless `InputInjection::Sendevent` is
+    /// available, a segment therefore lasts at least that long per step, and
+    /// the easing is only approximated. Default is `10`.
+    pub fn steps(mut self, steps: u32) -> Self {
+        self.steps = steps.max(1);
+        self
+    }
+
+    /// Sets the confidence threshold used for image waypoints.
+    ///
+    /// If not set, the default confidence from `DroidConfig` is used.
+    pub fn threshold(mut self, value: f32) -> Self {
+        self.threshold = Some(value);
+        self
+    }
+
+    /// Resolves every waypoint and performs the gesture.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any image waypoint cannot be found or if the
+    /// underlying ADB command fails.
+    pub fn execute(self) -> Result<()> {
+        let threshold = self
+            .threshold
+            .unwrap_or(self.droid.config.default_confidence);
+
+        let mut points = Vec::with_capacity(self.waypoints.len());
+        for waypoint in &self.waypoints {
+            points.push(
+                self.droid
+                    .resolve_target(&waypoint.target, threshold, None)?,
+            );
+        }
+        let holds: Vec<Duration> = self.waypoints.iter().map(|w| w.hold).collect();
+
+        log::info!(
+            "Executing path through {:?} with {:?} per segment",
+            points,
+            self.duration
+        );
+
+        let profile = MotionProfile {
+            duration: self.duration,
+            easing: self.easing,
+            curve: self.curve,
+            steps: self.steps,
+        };
+        // A crash recorded meanwhile stays pending if the gesture itself failed.
+        perform_path(&mut self.droid.controller, &points, &holds, &profile)?;
+        self.droid.check_crash()
+    }
+}
+
+/// Drives the pointer through `points`, holding on each for the matching entry of `holds`.
+///
+/// Uses motion events when the device supports them and falls back to a
+/// series of `input swipe` commands otherwise. The fallback lifts the pointer
+/// between segments, so it cannot reproduce continuous drags.
+pub(crate) fn perform_path(
+    controller: &mut DeviceController,
+    points: &[Point],
+    holds: &[Duration],
+    profile: &MotionProfile,
+) -> Result<()> {
+    if controller.supports_motion_events()? {
+        let events = build_motion_events(points, holds, profile);
+        log::debug!("Sending {} motion events", events.len());
+        return controller.motion_events(&events);
+    }
+
+    log::warn!("Device does not support `input motionevent`; falling back to swipes per segment.");
+    for (i, point) in points.iter().enumerate() {
+        if !holds[i].is_zero() {
+            controller.swipe(*point, *point, holds[i])?;
+        }
+        if let Some(next) = points.get(i + 1) {
+            controller.swipe(*point, *next, profile.duration)?;
+        }
+    }
+    Ok(())
+}
+
+/// Expands waypoints into a DOWN/MOVE.../UP event sequence following `profile`.
+pub(crate) fn build_motion_events(
+    points: &[Point],
+    holds: &[Duration],
+    profile: &MotionProfile,
+) -> Vec<MotionEvent> {
+    let (Some(&first), Some(&last)) = (points.first(), points.last()) else {
+        return Vec::new();
+    };
+    let steps = profile.steps.max(1);
+    let step_delay = profile.duration / steps;
+
+    let mut events = vec![MotionEvent::new(MotionAction::Down, first, holds[0])];
+    for (i, segment) in points.windows(2).enumerate() {
+        for step in 1..=steps {
+            let t = profile.easing.apply(step as f32 / steps as f32);
+            let point = curve_point(segment[0], segment[1], profile.curve, t);
+            events.push(MotionEvent::new(MotionAction::Move, point, step_delay));
+        }
+        if let Some(arrival) = events.last_mut() {
+            arrival.delay += holds[i + 1];
+        }
+    }
+
+    events.push(MotionEvent::new(MotionAction::Up, last, Duration::ZERO));
+    events
+}
+
+/// Returns the point at parameter `t` (0.0 to 1.0) of a quadratic Bézier
+/// from `from` to `to`. On a straight segment `t` is the fraction of the
+/// distance travelled; on a bent one it only approximates it.
+///
+/// The control point sits on the perpendicular bisector of the segment,
+/// `bend` segment lengths away from its midpoint.
+fn curve_point(from: Point, to: Point, bend: f32, t: f32) -> Point {
+    let (x0, y0) = (from.x as f32, from.y as f32);
+    let (x2, y2) = (to.x as f32, to.y as f32);
+    let (dx, dy) = (x2 - x0, y2 - y0);
+    let cx = (x0 + x2) / 2.0 - dy * bend;
+    let cy = (y0 + y2) / 2.0 + dx * bend;
+
+    let u = 1.0 - t;
+    let x = u * u * x0 + 2.0 * u * t * cx + t * t * x2;
+    let y = u * u * y0 + 2.0 * u * t * cy + t * t * y2;
+    Point::new(x.round().max(0.0) as u32, y.round().max(0.0) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(steps: u32, curve: f32) -> MotionProfile {
+        MotionProfile {
+            duration: Duration::from_millis(300),
+            easing: Easing::Linear,
+            curve,
+            steps,
+        }
+    }
+
+    #[test]
+    fn motion_events_through_waypoints() {
+        let points = [Point::new(0, 0), Point::new(100, 0), Point::new(100, 100)];
+        let holds = [
+            Duration::from_millis(500),
+            Duration::from_millis(200),
+            Duration::ZERO,
+        ];
+        let events = build_motion_events(&points, &holds, &profile(3, 0.0));
+
+        // DOWN, three moves per segment, UP.
+        assert_eq!(events.len(), 1 + 2 * 3 + 1);
+        assert_eq!(events[0].action, MotionAction::Down);
+        assert_eq!(events[0].delay, Duration::from_millis(500));
+        assert_eq!(events[3].point, Point::new(100, 0));
+        // Arriving on the middle waypoint adds its hold to the step delay.
+        assert_eq!(events[3].delay, Duration::from_millis(300));
+        assert_eq!(events[4].delay, Duration::from_millis(100));
+        let up = events.last().unwrap();
+        assert_eq!(
+            (up.action, up.point),
+            (MotionAction::Up, Point::new(100, 100))
+        );
+        assert!(build_motion_events(&[], &[], &profile(3, 0.0)).is_empty());
+    }
+
+    #[test]
+    fn curve_point_bends_sideways() {
+        let (from, to) = (Point::new(0, 0), Point::new(100, 0));
+        assert_eq!(curve_point(from, to, 0.0, 0.5), Point::new(50, 0));
+        assert_eq!(curve_point(from, to, 0.5, 0.0), from);
+        assert_eq!(curve_point(from, to, 0.5, 1.0), to);
+        // The control point is 50 pixels off the segment, the curve's apex half that.
+        assert_eq!(curve_point(from, to, 0.5, 0.5), Point::new(50, 25));
+        // Points that would leave the screen are clamped to it.
+        assert_eq!(curve_point(from, to, -0.5, 0.5), Point::new(50, 0));
+    }
+}
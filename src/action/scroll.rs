@@ -0,0 +1,227 @@
+use crate::action::swipe::SwipeBuilder;
+use crate::common::point::Point;
+use crate::common::rect::Rect;
+use crate::common::relative_rect::RelativeRect;
+use crate::models::Direction;
+use crate::{Droid, Result};
+use std::time::Duration;
+
+/// Builds and executes a scroll gesture in a given direction.
+///
+/// The start and end points are derived from the cached screen size and the
+/// current rotation, so no screenshot is needed. The gesture itself is performed by a `SwipeBuilder`.
+/// This struct is created by the `Droid::scroll()` method.
+pub struct ScrollBuilder<'a> {
+    droid: &'a mut Droid,
+    direction: Direction,
+    container: Option<RelativeRect>,
+    distance: f32,
+    duration: Duration,
+    top_margin: f32,
+    bottom_margin: f32,
+}
+
+impl<'a> ScrollBuilder<'a> {
+    pub fn new(droid: &'a mut Droid, direction: Direction) -> Self {
+        Self {
+            droid,
+            direction,
+            container: None,
+            distance: 0.6,
+            duration: Duration::from_millis(400),
+            top_margin: 0.05,
+            bottom_margin: 0.08,
+        }
+    }
+
+    /// Restricts the gesture to a region of the screen, such as a list view.
+    ///
+    /// Default is the whole screen.
+    pub fn within(mut self, rect: RelativeRect) -> Self {
+        self.container = Some(rect);
+        self
+    }
+
+    /// Sets the length of the gesture as a fraction of the container's extent
+    /// along the scroll axis. The value will be clamped between 0.0 and 1.0.
+    ///
+    /// Default is `0.6`.
+    pub fn distance(mut self, fraction: f32) -> Self {
+        self.distance = fraction.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets how long the finger takes to travel the distance.
+    ///
+    /// Longer durations scroll slowly and stop where the finger lifts.
+    /// Default is `400ms`.
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Performs a fast flick so the content keeps moving after the finger lifts.
+    pub fn fling(mut self) -> Self {
+        self.duration = Duration::from_millis(80);
+        self
+    }
+
+    /// Sets the screen fractions kept clear at both ends of the scroll axis.
+    /// The values will be clamped between 0.0 and 0.5.
+    ///
+    /// Vertical scrolls keep `top` clear of the status bar and `bottom` clear
+    /// of the navigation bar. Horizontal scrolls keep `top` at the left edge
+    /// and `bottom` at the right edge, where swipes start system gestures.
+    ///
+    /// Default is `0.05` at the top and `0.08` at the bottom.
+    pub fn margins(mut self, top: f32, bottom: f32) -> Self {
+        self.top_margin = top.clamp(0.0, 0.5);
+        self.bottom_margin = bottom.clamp(0.0, 0.5);
+        self
+    }
+
+    /// Executes the scroll.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the screen size cannot be queried or if the
+    /// underlying ADB command fails.
+    pub fn execute(self) -> Result<()> {
+        let (width, height) = self.droid.controller.screen_size()?;
+        let container = self
+            .container
+            .unwrap_or(RelativeRect::new(0.0, 0.0, 1.0, 1.0))
+            .to_absolute(width, height);
+        let area = scroll_area(
+            container,
+            self.direction,
+            (self.top_margin, self.bottom_margin),
+            (width, height),
+        );
+        let (start, end) = endpoints(area, self.direction, self.distance);
+
+        log::info!(
+            "Scrolling {:?} within {:?} over {:?}",
+            self.direction,
+            area,
+            self.duration
+        );
+
+        SwipeBuilder::new(self.droid, start.into(), end.into())
+            .duration(self.duration)
+            .execute()?;
+        Ok(())
+    }
+}
+
+fn is_vertical(direction: Direction) -> bool {
+    matches!(direction, Direction::Up | Direction::Down)
+}
+
+/// Trims `container` by the `(leading, trailing)` margins along the scroll
+/// axis of `direction`, given as fractions of the `screen` extent on that axis.
+fn scroll_area(
+    container: Rect,
+    direction: Direction,
+    (leading, trailing): (f32, f32),
+    screen: (u32, u32),
+) -> Rect {
+    let vertical = is_vertical(direction);
+    let (start, length, extent) = if vertical {
+        (container.y, container.height, screen.1)
+    } else {
+        (container.x, container.width, screen.0)
+    };
+
+    let safe_start = (leading * extent as f32) as u32;
+    let safe_end = extent - (trailing * extent as f32) as u32;
+    let first = start.max(safe_start);
+    let last = (start + length).min(safe_end);
+
+    if first >= last {
+        log::warn!("Scroll container lies within the margins; ignoring margins.");
+        return container;
+    }
+    if vertical {
+        Rect::new(container.x, first, container.width, last - first)
+    } else {
+        Rect::new(first, container.y, last - first, container.height)
+    }
+}
+
+/// Returns where the finger goes down and lifts to scroll `area` in
+/// `direction`, covering `distance` of its extent along the scroll axis.
+fn endpoints(area: Rect, direction: Direction, distance: f32) -> (Point, Point) {
+    let center = area.center();
+    let half_x = (area.width as f32 * distance / 2.0) as u32;
+    let half_y = (area.height as f32 * distance / 2.0) as u32;
+
+    let (left, right) = (center.x - half_x, center.x + half_x);
+    let (top, bottom) = (center.y - half_y, center.y + half_y);
+
+    match direction {
+        Direction::Up => (Point::new(center.x, bottom), Point::new(center.x, top)),
+        Direction::Down => (Point::new(center.x, top), Point::new(center.x, bottom)),
+        Direction::Left => (Point::new(right, center.y), Point::new(left, center.y)),
+        Direction::Right => (Point::new(left, center.y), Point::new(right, center.y)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PORTRAIT: (u32, u32) = (1000, 2000);
+    const LANDSCAPE: (u32, u32) = (2000, 1000);
+    const MARGINS: (f32, f32) = (0.05, 0.1);
+
+    fn full(screen: (u32, u32)) -> Rect {
+        Rect::new(0, 0, screen.0, screen.1)
+    }
+
+    #[test]
+    fn vertical_scroll_in_both_orientations() {
+        let area = scroll_area(full(PORTRAIT), Direction::Up, MARGINS, PORTRAIT);
+        assert_eq!(area, Rect::new(0, 100, 1000, 1700));
+        assert_eq!(
+            endpoints(area, Direction::Up, 0.5),
+            (Point::new(500, 1375), Point::new(500, 525))
+        );
+
+        let area = scroll_area(full(LANDSCAPE), Direction::Down, MARGINS, LANDSCAPE);
+        assert_eq!(area, Rect::new(0, 50, 2000, 850));
+        assert_eq!(
+            endpoints(area, Direction::Down, 0.5),
+            (Point::new(1000, 263), Point::new(1000, 687))
+        );
+    }
+
+    #[test]
+    fn horizontal_scroll_in_both_orientations() {
+        let area = scroll_area(full(PORTRAIT), Direction::Left, MARGINS, PORTRAIT);
+        assert_eq!(area, Rect::new(50, 0, 850, 2000));
+        assert_eq!(
+            endpoints(area, Direction::Left, 0.5),
+            (Point::new(687, 1000), Point::new(263, 1000))
+        );
+
+        let area = scroll_area(full(LANDSCAPE), Direction::Right, MARGINS, LANDSCAPE);
+        assert_eq!(area, Rect::new(100, 0, 1700, 1000));
+        assert_eq!(
+            endpoints(area, Direction::Right, 0.5),
+            (Point::new(525, 500), Point::new(1375, 500))
+        );
+    }
+
+    #[test]
+    fn container_inside_margins_is_kept() {
+        let container = Rect::new(0, 0, 1000, 50);
+        let area = scroll_area(container, Direction::Up, MARGINS, PORTRAIT);
+        assert_eq!(area, container);
+
+        // A container away from the edges is not trimmed.
+        let container = Rect::new(200, 500, 600, 400);
+        let area = scroll_area(container, Direction::Left, MARGINS, PORTRAIT);
+        assert_eq!(area, container);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::action::scroll::ScrollBuilder;
+use crate::common::point::Point;
+use crate::common::relative_rect::RelativeRect;
+use crate::models::Direction;
+use crate::{Droid, DroidError, Result, Target, vision};
+use image::GenericImageView;
+use std::time::Duration;
+
+/// Builds and executes a "scroll until visible" operation.
+///
+/// The builder scrolls repeatedly until the target can be resolved, and
+/// returns the `Point` where it was found. This struct is created by the
+/// `Droid::scroll_to()` method.
+pub struct ScrollToBuilder<'a> {
+    droid: &'a mut Droid,
+    target: Target,
+    direction: Direction,
+    container: Option<RelativeRect>,
+    distance: f32,
+    max_swipes: u32,
+    settle: Duration,
+    threshold: Option<f32>,
+    change_tolerance: f32,
+}
+
+impl<'a> ScrollToBuilder<'a> {
+    pub fn new(droid: &'a mut Droid, target: Target) -> Self {
+        Self {
+            droid,
+            target,
+            direction: Direction::Up,
+            container: None,
+            distance: 0.5,
+            max_swipes: 10,
+            settle: Duration::from_millis(500),
+            threshold: None,
+            change_tolerance: 0.002,
+        }
+    }
+
+    /// Sets the finger direction of each scroll.
+    ///
+    /// Default is `Direction::Up`, which moves down a vertical list.
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// Restricts both the scroll gesture and the target search to a region.
+    pub fn within(mut self, rect: RelativeRect) -> Self {
+        self.container = Some(rect);
+        self
+    }
+
+    /// Sets the length of each scroll as a fraction of the region.
+    ///
+    /// Default is `0.5`, which keeps some overlap between consecutive pages.
+    pub fn distance(mut self, fraction: f32) -> Self {
+        self.distance = fraction;
+        self
+    }
+
+    /// Sets the maximum number of scrolls before giving up.
+    ///
+    /// Default is `10`.
+    pub fn max_swipes(mut self, count: u32) -> Self {
+        self.max_swipes = count;
+        self
+    }
+
+    /// Sets how long to wait after each scroll for the content to come to rest.
+    ///
+    /// Default is `500ms`.
+    pub fn settle(mut self, duration: Duration) -> Self {
+        self.settle = duration;
+        self
+    }
+
+    /// Sets the confidence threshold for image matching.
+    ///
+    /// If not set, the default confidence from `DroidConfig` is used.
+    pub fn threshold(mut self, value: f32) -> Self {
+        self.threshold = Some(value);
+        self
+    }
+
+    /// Sets the largest screen difference (0.0 to 1.0) still considered
+    /// "unchanged" when detecting the end of the list.
+    ///
+    /// Default is `0.002`, which tolerates a ticking clock or a blinking cursor.
+    pub fn change_tolerance(mut self, value: f32) -> Self {
+        self.change_tolerance = value;
+        self
+    }
+
+    /// Scrolls until the target is visible and returns its position.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DroidError::EndOfScroll` if the screen stops changing before the
+    /// target appears, `DroidError::ScrollLimitReached` if it is still missing
+    /// after `max_swipes` scrolls, or any error from the underlying commands.
+    pub fn execute(self) -> Result<Point> {
+        let threshold = self
+            .threshold
+            .unwrap_or(self.droid.config.default_confidence);
+        log::info!(
+            "Scrolling {:?} until {:?} is visible, at most {} swipes",
+            self.direction,
+            self.target,
+            self.max_swipes
+        );
+
+        let mut previous = None;
+        let mut swipes = 0;
+        loop {
+            // Dialogs and popups covering the list are dismissed like for any other search.
+            let (result, screen) = self.droid.locate(&self.target, threshold, self.container)?;
+            match result {
+                Ok(point) => {
+                    log::info!("Target found at {:?} after {} swipes.", point, swipes);
+                    return Ok(point);
+                }
+                Err(DroidError::ImageNotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+
+            if let Some(previous) = &previous {
+                let region = self.container.map(|rect| {
+                    let (w, h) = screen.dimensions();
+                    rect.to_absolute(w, h)
+                });
+                if vision::image_difference(previous, &screen, region) <= self.change_tolerance {
+                    log::warn!("Screen stopped changing after {} swipes.", swipes);
+                    return Err(DroidError::EndOfScroll {
+                        target: format!("{:?}", self.target),
+                        swipes,
+                    });
+                }
+            }
+
+            if swipes >= self.max_swipes {
+                log::warn!("Target not found after {} swipes.", swipes);
+                return Err(DroidError::ScrollLimitReached {
+                    target: format!("{:?}", self.target),
+                    swipes,
+                });
+            }
+
+            let mut scroll = ScrollBuilder::new(self.droid, self.direction).distance(self.distance);
+            if let Some(rect) = self.container {
+                scroll = scroll.within(rect);
+            }
+            scroll.execute()?;
+            swipes += 1;
+
+            previous = Some(screen);
+            std::thread::sleep(self.settle);
+        }
+    }
+}
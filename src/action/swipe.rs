@@ -1,4 +1,8 @@
+use crate::action::expect::{self, Action, Expectation};
+use crate::action::path::{self, MotionProfile};
+use crate::common::easing::Easing;
 use crate::common::relative_rect::RelativeRect;
+use crate::retry::{ActionOutcome, RetryPolicy};
 use crate::{Droid, Result, Target};
 use std::time::Duration;
 
@@ -10,6 +14,10 @@ pub struct SwipeBuilder<'a> {
     threshold: Option<f32>,
     start_search_rect: Option<RelativeRect>,
     end_search_rect: Option<RelativeRect>,
+    easing: Option<Easing>,
+    curve: f32,
+    retry: Option<RetryPolicy>,
+    expectation: Expectation,
 }
 
 impl<'a> SwipeBuilder<'a> {
@@ -22,6 +30,10 @@ impl<'a> SwipeBuilder<'a> {
             threshold: None,
             start_search_rect: None,
             end_search_rect: None,
+            easing: None,
+            curve: 0.0,
+            retry: None,
+            expectation: Expectation::default(),
         }
     }
 
@@ -45,7 +57,39 @@ impl<'a> SwipeBuilder<'a> {
         self
     }
 
-    pub fn execute(self) -> Result<()> {
+    /// Applies a velocity profile to the swipe.
+    ///
+    /// Eased and curved swipes are sent as motion events instead of a single
+    /// `input swipe`.
+    pub fn easing(mut self, easing: Easing) -> Self {
+        self.easing = Some(easing);
+        self
+    }
+
+    /// Bends the swipe into an arc; see `PathBuilder::curve`.
+    pub fn curve(mut self, bend: f32) -> Self {
+        self.curve = bend;
+        self
+    }
+
+    expect::setters!("swipe");
+
+    pub fn execute(mut self) -> Result<ActionOutcome> {
+        let policy = self
+            .retry
+            .take()
+            .unwrap_or_else(|| self.droid.config.retry.clone());
+        let expectation = std::mem::take(&mut self.expectation);
+        expect::execute(&mut self, "swipe", &policy, &expectation)
+    }
+}
+
+impl Action for SwipeBuilder<'_> {
+    fn droid(&mut self) -> &mut Droid {
+        self.droid
+    }
+
+    fn perform(&mut self) -> Result<()> {
         let threshold = self
             .threshold
             .unwrap_or(self.droid.config.default_confidence);
@@ -63,10 +107,24 @@ impl<'a> SwipeBuilder<'a> {
             self.duration
         );
 
-        self.droid
-            .controller
-            .swipe(start_point, end_point, self.duration)?;
+        if self.easing.is_none() && self.curve == 0.0 {
+            return self
+                .droid
+                .controller
+                .swipe(start_point, end_point, self.duration);
+        }
 
-        Ok(())
+        let profile = MotionProfile {
+            duration: self.duration,
+            easing: self.easing.unwrap_or_default(),
+            curve: self.curve,
+            steps: 10,
+        };
+        path::perform_path(
+            &mut self.droid.controller,
+            &[start_point, end_point],
+            &[Duration::ZERO, Duration::ZERO],
+            &profile,
+        )
     }
 }
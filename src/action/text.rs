@@ -1,8 +1,12 @@
+use crate::action::expect::{self, Action, Expectation};
+use crate::retry::{ActionOutcome, RetryPolicy};
 use crate::{Droid, Result};
 
 pub struct TextBuilder<'a> {
     droid: &'a mut Droid,
     text: String,
+    retry: Option<RetryPolicy>,
+    expectation: Expectation,
 }
 
 impl<'a> TextBuilder<'a> {
@@ -10,10 +14,29 @@ impl<'a> TextBuilder<'a> {
         Self {
             droid,
             text: text.to_string(),
+            retry: None,
+            expectation: Expectation::default(),
         }
     }
 
-    pub fn execute(self) -> Result<()> {
+    expect::setters!("text input");
+
+    pub fn execute(mut self) -> Result<ActionOutcome> {
+        let policy = self
+            .retry
+            .take()
+            .unwrap_or_else(|| self.droid.config.retry.clone());
+        let expectation = std::mem::take(&mut self.expectation);
+        expect::execute(&mut self, "text", &policy, &expectation)
+    }
+}
+
+impl Action for TextBuilder<'_> {
+    fn droid(&mut self) -> &mut Droid {
+        self.droid
+    }
+
+    fn perform(&mut self) -> Result<()> {
         log::info!("Executing text input: '{}'", self.text);
         self.droid.controller.input_text(&self.text)
     }
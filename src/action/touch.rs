@@ -1,4 +1,6 @@
+use crate::action::expect::{self, Action, Expectation};
 use crate::common::relative_rect::RelativeRect;
+use crate::retry::{ActionOutcome, RetryPolicy};
 use crate::{Droid, Result, Target};
 use std::time::Duration;
 
@@ -11,8 +13,12 @@ pub struct TouchBuilder<'a> {
     target: Target,
     duration: Duration,
     times: u32,
+    /// Touches of the current burst already sent, so a retry sends only the rest.
+    sent: u32,
     threshold: Option<f32>,
     search_rect: Option<RelativeRect>,
+    retry: Option<RetryPolicy>,
+    expectation: Expectation,
 }
 
 impl<'a> TouchBuilder<'a> {
@@ -22,13 +28,17 @@ impl<'a> TouchBuilder<'a> {
             target,
             duration: Duration::from_millis(100),
             times: 1,
+            sent: 0,
             threshold: None,
             search_rect: None,
+            retry: None,
+            expectation: Expectation::default(),
         }
     }
 
     /// Sets the number of times to perform the touch action.
     ///
+    /// A retry after an error sends only the touches that were not sent yet.
     /// Default is `1`.
     pub fn times(mut self, count: u32) -> Self {
         self.times = count;
@@ -65,6 +75,8 @@ impl<'a> TouchBuilder<'a> {
         self
     }
 
+    expect::setters!("touch");
+
     /// Executes the configured touch action.
     ///
     /// It resolves the `target` to a screen coordinate and then performs
@@ -73,8 +85,24 @@ impl<'a> TouchBuilder<'a> {
     /// # Errors
     ///
     /// Returns an error if the target cannot be found (for image targets) or
-    /// if the underlying ADB command fails.
-    pub fn execute(self) -> Result<()> {
+    /// if the underlying ADB command fails, after any retries allowed by the
+    /// retry policy.
+    pub fn execute(mut self) -> Result<ActionOutcome> {
+        let policy = self
+            .retry
+            .take()
+            .unwrap_or_else(|| self.droid.config.retry.clone());
+        let expectation = std::mem::take(&mut self.expectation);
+        expect::execute(&mut self, "touch", &policy, &expectation)
+    }
+}
+
+impl Action for TouchBuilder<'_> {
+    fn droid(&mut self) -> &mut Droid {
+        self.droid
+    }
+
+    fn perform(&mut self) -> Result<()> {
         let threshold = self
             .threshold
             .unwrap_or(self.droid.config.default_confidence);
@@ -84,20 +112,23 @@ impl<'a> TouchBuilder<'a> {
         log::info!(
             "Executing touch action at {:?} for {} times",
             point,
-            self.times
+            self.times - self.sent
         );
 
-        for i in 0..self.times {
+        for i in self.sent..self.times {
             if self.duration <= Duration::from_millis(200) {
                 self.droid.controller.tap(point)?;
             } else {
                 self.droid.controller.swipe(point, point, self.duration)?;
             }
+            self.sent += 1;
 
             if self.times > 1 && i < self.times - 1 {
                 std::thread::sleep(Duration::from_millis(50));
             }
         }
+        // A completed burst is sent in full again when the action is re-issued.
+        self.sent = 0;
         Ok(())
     }
 }
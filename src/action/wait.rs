@@ -73,7 +73,7 @@ impl<'a> WaitBuilder<'a> {
                     return Ok(point);
                 }
                 Err(DroidError::ImageNotFound(_)) => {
-                    log::trace!("Target not found yet, retrying after {:?}", self.interval);
+                    log::trace!("Target not found yet, retrying on next screen change");
                 }
                 Err(e) => {
                     log::error!("An unrecoverable error occurred while waiting: {:?}", e);
@@ -81,7 +81,8 @@ impl<'a> WaitBuilder<'a> {
                 }
             }
 
-            std::thread::sleep(self.interval);
+            let remaining = self.timeout.saturating_sub(start_time.elapsed());
+            self.droid.wait_for_change(self.interval, remaining);
         }
     }
 }
@@ -0,0 +1,227 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::Droid;
+use crate::error::{DroidError, Result};
+
+/// The installed version of an app.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppVersion {
+    /// The user-visible version, e.g. `"33.1.2"`.
+    pub name: String,
+    /// The internal version number, which increases with every release.
+    pub code: u64,
+}
+
+impl fmt::Display for AppVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.code)
+    }
+}
+
+/// Manages one installed app.
+///
+/// This struct is created by the `Droid::app()` method, which accepts a
+/// package name or an `AppPackages` variant.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rust_droid::{AppPackages, Droid, DroidConfig};
+/// let mut droid = Droid::new(DroidConfig::default())?;
+/// let mut chrome = droid.app(AppPackages::Chrome);
+/// println!("Chrome {}", chrome.version()?);
+/// chrome.clear_data()?;
+/// chrome.restart()?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct App<'a> {
+    droid: &'a mut Droid,
+    package: String,
+}
+
+impl<'a> App<'a> {
+    pub fn new(droid: &'a mut Droid, package: &str) -> Self {
+        Self {
+            droid,
+            package: package.to_string(),
+        }
+    }
+
+    pub fn package(&self) -> &str {
+        &self.package
+    }
+
+    pub fn is_installed(&mut self) -> Result<bool> {
+        self.droid.controller.is_installed(&self.package)
+    }
+
+    /// Returns the installed version.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DroidError::PackageNotFound` if the app is not installed.
+    pub fn version(&mut self) -> Result<AppVersion> {
+        self.droid
+            .controller
+            .package_version(&self.package)?
+            .ok_or_else(|| DroidError::PackageNotFound(self.package.clone()))
+    }
+
+    /// Whether any process of the app is running.
+    pub fn is_running(&mut self) -> Result<bool> {
+        Ok(!self.pids()?.is_empty())
+    }
+
+    /// Returns the ids of the app's running processes.
+    pub fn pids(&mut self) -> Result<Vec<u32>> {
+        self.droid.controller.pidof(&self.package)
+    }
+
+    /// Starts the app's launcher activity.
+    pub fn launch(&mut self) -> Result<()> {
+        if !self.is_installed()? {
+            return Err(DroidError::PackageNotFound(self.package.clone()));
+        }
+        self.droid.controller.launch_app(&self.package)
+    }
+
+    /// Stops every process of the app.
+    pub fn force_stop(&mut self) -> Result<()> {
+        log::info!("Force-stopping {}", self.package);
+        self.droid.controller.force_stop(&self.package)
+    }
+
+    /// Deletes all of the app's data, which also stops it.
+    pub fn clear_data(&mut self) -> Result<()> {
+        log::info!("Clearing data of {}", self.package);
+        self.droid.controller.clear_data(&self.package)
+    }
+
+    /// Removes the app together with its data.
+    pub fn uninstall(&mut self) -> Result<()> {
+        log::info!("Uninstalling {}", self.package);
+        self.droid.controller.uninstall(&self.package, false)
+    }
+
+    /// Removes the app but keeps its data and cache, so a later install of
+    /// the same app picks them up again.
+    pub fn uninstall_keep_data(&mut self) -> Result<()> {
+        log::info!("Uninstalling {}, keeping its data", self.package);
+        self.droid.controller.uninstall(&self.package, true)
+    }
+
+    /// Waits up to `timeout` until a process of the app exists.
+    pub fn wait_until_running(&mut self, timeout: Duration) -> Result<()> {
+        let start = Instant::now();
+        loop {
+            if self.is_running()? {
+                return Ok(());
+            }
+            if start.elapsed() > timeout {
+                return Err(DroidError::Timeout(timeout));
+            }
+            std::thread::sleep(self.droid.config.default_interval);
+        }
+    }
+
+    /// Stops the app, launches it again and waits until its process exists,
+    /// using the default timeout from `DroidConfig`.
+    pub fn restart(&mut self) -> Result<()> {
+        self.force_stop()?;
+        self.launch()?;
+        self.wait_until_running(self.droid.config.default_timeout)
+    }
+}
+
+/// Builds and executes the installation of an APK from the host.
+///
+/// The APK is pushed to the device and installed with `pm install`. Failures
+/// are reported as `DroidError::InstallFailed` with the reason parsed from the
+/// package manager's failure code.
+///
+/// This struct is created by the `Droid::install()` method.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rust_droid::{Droid, DroidConfig, error::{DroidError, InstallFailure}};
+/// let mut droid = Droid::new(DroidConfig::default())?;
+/// let result = droid
+///     .install("build/app-release.apk")
+///     .split("build/app-arm64-v8a.apk")
+///     .reinstall(true)
+///     .grant_all_permissions(true)
+///     .execute();
+/// if let Err(DroidError::InstallFailed { reason: InstallFailure::VersionDowngrade, .. }) = result {
+///     eprintln!("A newer build is installed already");
+/// }
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct InstallBuilder<'a> {
+    droid: &'a mut Droid,
+    apks: Vec<PathBuf>,
+    reinstall: bool,
+    downgrade: bool,
+    grant_all_permissions: bool,
+    allow_test: bool,
+}
+
+impl<'a> InstallBuilder<'a> {
+    pub fn new(droid: &'a mut Droid, apk: PathBuf) -> Self {
+        Self {
+            droid,
+            apks: vec![apk],
+            reinstall: false,
+            downgrade: false,
+            grant_all_permissions: false,
+            allow_test: false,
+        }
+    }
+
+    /// Adds a split APK of the same app, e.g. a per-ABI or per-density split.
+    pub fn split(mut self, apk: impl Into<PathBuf>) -> Self {
+        self.apks.push(apk.into());
+        self
+    }
+
+    /// Replaces the app if it is installed already, keeping its data (`-r`).
+    pub fn reinstall(mut self, enabled: bool) -> Self {
+        self.reinstall = enabled;
+        self
+    }
+
+    /// Allows installing an older version over a newer one (`-d`).
+    pub fn downgrade(mut self, enabled: bool) -> Self {
+        self.downgrade = enabled;
+        self
+    }
+
+    /// Grants every runtime permission in the manifest on install (`-g`).
+    pub fn grant_all_permissions(mut self, enabled: bool) -> Self {
+        self.grant_all_permissions = enabled;
+        self
+    }
+
+    /// Allows APKs marked `testOnly`, as built by Android Studio (`-t`).
+    pub fn allow_test(mut self, enabled: bool) -> Self {
+        self.allow_test = enabled;
+        self
+    }
+
+    pub fn execute(self) -> Result<()> {
+        let flags: Vec<&str> = [
+            (self.reinstall, "-r"),
+            (self.downgrade, "-d"),
+            (self.grant_all_permissions, "-g"),
+            (self.allow_test, "-t"),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, flag)| enabled.then_some(flag))
+        .collect();
+
+        log::info!("Installing {:?} with flags {:?}", self.apks, flags);
+        self.droid.controller.install(&self.apks, &flags.join(" "))
+    }
+}
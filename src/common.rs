@@ -1,3 +1,5 @@
+pub mod easing;
 pub mod point;
 pub mod rect;
 pub mod relative_rect;
+pub(crate) mod stats;
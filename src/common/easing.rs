@@ -0,0 +1,61 @@
+/// A velocity profile applied to the movement between two gesture waypoints.
+///
+/// Easing maps the elapsed fraction of a segment's duration to the fraction
+/// of the distance travelled, so the same path can be traversed at a constant
+/// speed or with realistic acceleration and deceleration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Easing {
+    /// Constant speed from start to end.
+    #[default]
+    Linear,
+    /// Starts slowly and accelerates towards the end.
+    EaseIn,
+    /// Starts quickly and decelerates towards the end.
+    EaseOut,
+    /// Accelerates through the first half and decelerates through the second.
+    EaseInOut,
+}
+
+impl Easing {
+    /// Maps a time fraction `t` (0.0 to 1.0) to a distance fraction (0.0 to 1.0).
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_span_the_whole_distance() {
+        for easing in [
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+        ] {
+            assert_eq!(easing.apply(0.0), 0.0);
+            assert_eq!(easing.apply(1.0), 1.0);
+            assert_eq!(easing.apply(-1.0), 0.0);
+            assert_eq!(easing.apply(2.0), 1.0);
+            let samples: Vec<f32> = (0..=10).map(|i| easing.apply(i as f32 / 10.0)).collect();
+            assert!(samples.windows(2).all(|pair| pair[0] <= pair[1]));
+        }
+        assert_eq!(Easing::EaseIn.apply(0.5), 0.125);
+        assert_eq!(Easing::EaseOut.apply(0.5), 0.875);
+        assert_eq!(Easing::EaseInOut.apply(0.5), 0.5);
+    }
+}
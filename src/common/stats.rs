@@ -0,0 +1,37 @@
+use std::time::Duration;
+
+/// Returns the `percent` percentile of `sorted` by the nearest-rank method:
+/// the smallest value that at least `percent`% of the values do not exceed.
+///
+/// `sorted` must be in ascending order. Returns `None` if it is empty.
+pub(crate) fn percentile(sorted: &[Duration], percent: usize) -> Option<Duration> {
+    let rank = (sorted.len() * percent.min(100)).div_ceil(100).max(1);
+    sorted.get(rank - 1).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(values: &[u64]) -> Vec<Duration> {
+        values.iter().copied().map(Duration::from_millis).collect()
+    }
+
+    #[test]
+    fn nearest_rank() {
+        let sorted = millis(&[10, 20, 30, 40, 50, 60, 70, 80, 90, 100]);
+        assert_eq!(percentile(&sorted, 50), Some(Duration::from_millis(50)));
+        assert_eq!(percentile(&sorted, 90), Some(Duration::from_millis(90)));
+        assert_eq!(percentile(&sorted, 95), Some(Duration::from_millis(100)));
+        assert_eq!(percentile(&sorted, 0), Some(Duration::from_millis(10)));
+        assert_eq!(percentile(&sorted, 100), Some(Duration::from_millis(100)));
+    }
+
+    #[test]
+    fn single_sample_and_empty_input() {
+        let sorted = millis(&[42]);
+        assert_eq!(percentile(&sorted, 1), Some(Duration::from_millis(42)));
+        assert_eq!(percentile(&sorted, 99), Some(Duration::from_millis(42)));
+        assert_eq!(percentile(&[], 50), None);
+    }
+}
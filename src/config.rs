@@ -1,3 +1,6 @@
+use crate::models::{CaptureStrategy, InputInjection, ReconnectPolicy};
+use crate::retry::RetryPolicy;
+use crate::selector::DeviceSelector;
 use std::net::{Ipv4Addr, SocketAddrV4};
 use std::time::Duration;
 
@@ -7,30 +10,54 @@ pub struct DroidConfig {
     /// The address and port of the ADB server.
     pub adb_server_addr: SocketAddrV4,
     /// The serial number of the specific device to connect to.
-    /// If `None`, the first available device will be used.
+    /// If `None`, the first device matching `device_selector` will be used.
     pub device_serial: Option<String>,
+    /// Criteria for choosing a device by its properties.
+    pub device_selector: DeviceSelector,
     /// The default timeout duration for operations like `wait_for`.
     pub default_timeout: Duration,
     /// The default polling interval for `wait_for` operations.
     pub default_interval: Duration,
     /// The default confidence threshold for image template matching (0.0 to 1.0).
     pub default_confidence: f32,
+    /// Whether shell commands are pipelined through one long-lived shell
+    /// session instead of opening a new ADB connection per command.
+    pub persistent_shell: bool,
+    /// How taps, swipes and other touch gestures are injected.
+    pub input_injection: InputInjection,
+    /// How screenshots are captured.
+    pub capture_strategy: CaptureStrategy,
+    /// How long to wait for the device to come back after it is lost.
+    pub reconnect: ReconnectPolicy,
+    /// How touch, swipe, text and keyevent actions are retried.
+    pub retry: RetryPolicy,
 }
 
 impl Default for DroidConfig {
     /// Provides a reasonable default configuration.
     /// - ADB Server: `127.0.0.1:5037`
-    /// - Device: Auto-select first available
+    /// - Device: Auto-select first online device, in serial order
     /// - Timeout: 20 seconds
     /// - Interval: 0.5 seconds
     /// - Confidence: 0.8
+    /// - Persistent shell: disabled
+    /// - Input injection: `input` tool
+    /// - Capture strategy: PNG
+    /// - Reconnect: wait up to 30 seconds
+    /// - Retry: none, every action is attempted once
     fn default() -> Self {
         Self {
             adb_server_addr: SocketAddrV4::new(Ipv4Addr::LOCALHOST, 5037),
             device_serial: None,
+            device_selector: DeviceSelector::default(),
             default_timeout: Duration::from_secs(20),
             default_interval: Duration::from_millis(500),
             default_confidence: 0.8,
+            persistent_shell: false,
+            input_injection: InputInjection::Input,
+            capture_strategy: CaptureStrategy::Png,
+            reconnect: ReconnectPolicy::default(),
+            retry: RetryPolicy::default(),
         }
     }
 }
@@ -48,6 +75,12 @@ impl DroidConfig {
         self
     }
 
+    /// Chooses the device by its properties, e.g. model or SDK level.
+    pub fn select(mut self, selector: DeviceSelector) -> Self {
+        self.device_selector = selector;
+        self
+    }
+
     /// Sets the default timeout duration.
     pub fn timeout(mut self, timeout: Duration) -> Self {
         self.default_timeout = timeout;
@@ -60,4 +93,34 @@ impl DroidConfig {
         self.default_confidence = confidence.clamp(0.0, 1.0);
         self
     }
+
+    /// Enables or disables the persistent shell session.
+    pub fn persistent_shell(mut self, enabled: bool) -> Self {
+        self.persistent_shell = enabled;
+        self
+    }
+
+    /// Sets how touch gestures are injected.
+    pub fn input_injection(mut self, injection: InputInjection) -> Self {
+        self.input_injection = injection;
+        self
+    }
+
+    /// Sets how screenshots are captured.
+    pub fn capture_strategy(mut self, strategy: CaptureStrategy) -> Self {
+        self.capture_strategy = strategy;
+        self
+    }
+
+    /// Sets what to do when the device is lost.
+    pub fn reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect = policy;
+        self
+    }
+
+    /// Sets the default retry policy for actions.
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
 }
@@ -0,0 +1,604 @@
+use std::fmt;
+use std::fs::File;
+use std::net::SocketAddrV4;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+use adb_client::{ADBDeviceExt, ADBServer, ADBServerDevice};
+
+use crate::error::{DroidError, Result};
+use crate::logcat::{LogEntry, LogFilter, LogLevel, LogcatStream};
+
+/// How long the log has to be quiet before a partially collected crash is reported.
+const QUIET: Duration = Duration::from_millis(500);
+/// How often the focused window is checked for a crash dialog.
+const DIALOG_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// A crash dialog within this time of a logged crash belongs to that crash.
+const DIALOG_GRACE: Duration = Duration::from_secs(10);
+
+/// How an app failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrashKind {
+    /// An uncaught Java or Kotlin exception (`FATAL EXCEPTION`).
+    Java,
+    /// A crash in native code, which leaves a tombstone.
+    Native,
+    /// The app did not respond to input or finish a broadcast in time.
+    Anr,
+    /// The system's "has stopped" or "isn't responding" dialog was shown,
+    /// but no crash was logged, e.g. because the monitor started too late.
+    Dialog,
+}
+
+impl fmt::Display for CrashKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CrashKind::Java => write!(f, "uncaught exception"),
+            CrashKind::Native => write!(f, "native crash"),
+            CrashKind::Anr => write!(f, "ANR"),
+            CrashKind::Dialog => write!(f, "crash dialog"),
+        }
+    }
+}
+
+/// A crash detected by the crash monitor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrashReport {
+    pub package: String,
+    /// The name of the process that crashed, e.g. `com.example.shop:sync`.
+    pub process: String,
+    pub kind: CrashKind,
+    pub pid: Option<u32>,
+    /// When the crash was detected on the host.
+    pub time: SystemTime,
+    /// The exception, signal or ANR reason, e.g.
+    /// `java.lang.IllegalStateException: cart is empty`.
+    pub summary: String,
+    /// The logged stack trace or ANR details, one log line per line.
+    pub stack_trace: String,
+    /// The tombstone or ANR trace on the device, if the log named one.
+    pub remote_trace: Option<String>,
+    /// The pulled copy of `remote_trace`, if `CrashOptions::pull_traces_to` is set.
+    pub trace_file: Option<PathBuf>,
+}
+
+impl fmt::Display for CrashReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} in {}", self.kind, self.process)?;
+        if let Some(pid) = self.pid {
+            write!(f, " (pid {})", pid)?;
+        }
+        if !self.summary.is_empty() {
+            write!(f, ": {}", self.summary)?;
+        }
+        Ok(())
+    }
+}
+
+/// Options for a crash monitor started with `Droid::start_crash_monitor()`.
+#[derive(Debug, Clone, Default)]
+pub struct CrashOptions {
+    /// A host directory to pull tombstones and ANR traces into. Reading them
+    /// needs a rooted device or a debug build of Android.
+    pub pull_traces_to: Option<PathBuf>,
+}
+
+impl CrashOptions {
+    pub fn pull_traces_to<P: Into<PathBuf>>(mut self, directory: P) -> Self {
+        self.pull_traces_to = Some(directory.into());
+        self
+    }
+}
+
+/// Watches the device log and screen for crashes of one app.
+///
+/// While a monitor runs, actions and waits fail with `DroidError::AppCrashed`
+/// as soon as a crash of the app was detected, instead of timing out. Each
+/// crash is reported once.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rust_droid::{Droid, DroidConfig, Target, crash::CrashOptions, error::DroidError};
+/// let mut droid = Droid::new(DroidConfig::default())?;
+/// droid.start_crash_monitor("com.example.shop", CrashOptions::default().pull_traces_to("crashes"))?;
+/// match droid.wait_for(Target::from("order_placed.png")).execute() {
+///     Err(DroidError::AppCrashed(report)) => {
+///         eprintln!("{}\n{}", report, report.stack_trace);
+///     }
+///     result => {
+///         result?;
+///     }
+/// }
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct CrashMonitor {
+    package: String,
+    reports: Arc<Mutex<Vec<CrashReport>>>,
+    stop: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl CrashMonitor {
+    pub(crate) fn start(
+        adb_addr: SocketAddrV4,
+        serial: &str,
+        package: &str,
+        options: CrashOptions,
+    ) -> Result<Self> {
+        // Crashes are logged by the system as well as the app, so the stream
+        // is not filtered by package.
+        let mut stream =
+            LogcatStream::start(adb_addr, serial, LogFilter::new().level(LogLevel::Error))?;
+        let mut device = ADBServer::new(adb_addr)
+            .get_device_by_name(serial)
+            .map_err(|e| DroidError::AdbError(e.to_string()))?;
+        if let Some(directory) = &options.pull_traces_to {
+            std::fs::create_dir_all(directory)?;
+        }
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let worker_reports = Arc::clone(&reports);
+        let worker_stop = Arc::clone(&stop);
+        let worker_package = package.to_string();
+        let worker = thread::Builder::new()
+            .name("droid-crash-monitor".to_string())
+            .spawn(move || {
+                let mut parser = CrashParser::new(&worker_package);
+                let mut last_reported: Option<Instant> = None;
+                let mut dialog_checked = Instant::now();
+                let mut dialog_shown = false;
+                while !worker_stop.load(Ordering::Relaxed) {
+                    let mut report = match stream.next_timeout(QUIET) {
+                        Some(entry) => parser.feed(&entry),
+                        None => parser.flush(),
+                    };
+                    if report.is_none() && dialog_checked.elapsed() > DIALOG_CHECK_INTERVAL {
+                        dialog_checked = Instant::now();
+                        let dialog = check_dialog(&mut device, &worker_package);
+                        // A dialog is reported once when it appears, unless
+                        // it belongs to a crash that was logged.
+                        let logged = last_reported.is_some_and(|at| at.elapsed() < DIALOG_GRACE);
+                        if !dialog_shown && !logged {
+                            report = dialog.clone();
+                        }
+                        dialog_shown = dialog.is_some();
+                    }
+                    if let Some(mut report) = report {
+                        if let Some(directory) = &options.pull_traces_to {
+                            report.trace_file = pull_trace(&mut device, &report, directory);
+                        }
+                        log::error!("Crash detected: {}", report);
+                        last_reported = Some(Instant::now());
+                        if let Ok(mut reports) = worker_reports.lock() {
+                            reports.push(report);
+                        }
+                    }
+                }
+                log::debug!("Crash monitor of {} exited", worker_package);
+            })?;
+
+        log::info!("Monitoring {} for crashes", package);
+        Ok(Self {
+            package: package.to_string(),
+            reports,
+            stop,
+            worker: Some(worker),
+        })
+    }
+
+    pub fn package(&self) -> &str {
+        &self.package
+    }
+
+    /// Returns the crashes detected since the last call.
+    pub fn take_reports(&self) -> Vec<CrashReport> {
+        self.reports
+            .lock()
+            .map(|mut reports| std::mem::take(&mut *reports))
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for CrashMonitor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// A crash whose log lines are still being collected.
+#[derive(Debug)]
+struct Pending {
+    kind: CrashKind,
+    /// The process that logs the crash, which is the system for ANRs.
+    logger_pid: u32,
+    tag: String,
+    process: Option<String>,
+    pid: Option<u32>,
+    summary: String,
+    lines: Vec<String>,
+    remote_trace: Option<String>,
+    started: Instant,
+}
+
+/// Recognizes crashes of one package in log entries.
+///
+/// ```text
+/// E AndroidRuntime: FATAL EXCEPTION: main
+/// E AndroidRuntime: Process: com.example.shop, PID: 4242
+/// E AndroidRuntime: java.lang.IllegalStateException: cart is empty
+///
+/// E ActivityManager: ANR in com.example.shop (com.example.shop/.MainActivity)
+/// E ActivityManager: PID: 4242
+/// E ActivityManager: Reason: Input dispatching timed out
+///
+/// F DEBUG   : *** *** *** *** *** *** *** *** *** *** *** *** *** *** *** ***
+/// F DEBUG   : pid: 4242, tid: 4250, name: RenderThread  >>> com.example.shop <<<
+/// F DEBUG   : signal 11 (SIGSEGV), code 1 (SEGV_MAPERR), fault addr 0x0
+/// E tombstoned: Tombstone written to: /data/tombstones/tombstone_03
+/// ```
+struct CrashParser {
+    package: String,
+    pending: Option<Pending>,
+}
+
+impl CrashParser {
+    fn new(package: &str) -> Self {
+        Self {
+            package: package.to_string(),
+            pending: None,
+        }
+    }
+
+    fn feed(&mut self, entry: &LogEntry) -> Option<CrashReport> {
+        let message = entry.message.trim_end();
+        let mut finished = None;
+        if let Some(pending) = &mut self.pending {
+            if let Some((_, path)) = message.split_once("Tombstone written to: ") {
+                pending.remote_trace = Some(path.trim().to_string());
+                return self.flush();
+            }
+            if entry.pid == pending.logger_pid && entry.tag == pending.tag {
+                pending.collect(message);
+                return None;
+            }
+            // Crash lines are logged in one burst, so a busy log must not
+            // keep the crash pending forever.
+            if pending.started.elapsed() > QUIET {
+                finished = self.flush();
+            }
+        }
+
+        let started = if entry.tag == "AndroidRuntime" && message.starts_with("FATAL EXCEPTION") {
+            Some((CrashKind::Java, None))
+        } else if entry.tag == "ActivityManager"
+            && let Some(rest) = message.strip_prefix("ANR in ")
+        {
+            let process = rest.split_whitespace().next().unwrap_or_default();
+            Some((CrashKind::Anr, Some(process.to_string())))
+        } else if entry.tag == "DEBUG" && message.starts_with("*** *** ***") {
+            Some((CrashKind::Native, None))
+        } else {
+            None
+        };
+        let Some((kind, process)) = started else {
+            return finished;
+        };
+        let finished = finished.or_else(|| self.flush());
+        self.pending = Some(Pending {
+            kind,
+            logger_pid: entry.pid,
+            tag: entry.tag.clone(),
+            process,
+            pid: None,
+            summary: String::new(),
+            lines: vec![message.to_string()],
+            remote_trace: None,
+            started: Instant::now(),
+        });
+        finished
+    }
+
+    /// Reports the pending crash if it belongs to the package.
+    fn flush(&mut self) -> Option<CrashReport> {
+        let pending = self.pending.take()?;
+        let process = pending.process?;
+        let ours = process == self.package
+            || process
+                .strip_prefix(self.package.as_str())
+                .is_some_and(|rest| rest.starts_with(':'));
+        if !ours {
+            return None;
+        }
+        Some(CrashReport {
+            package: self.package.clone(),
+            process,
+            kind: pending.kind,
+            pid: pending.pid,
+            time: SystemTime::now(),
+            summary: pending.summary,
+            stack_trace: pending.lines.join("\n"),
+            remote_trace: pending.remote_trace,
+            trace_file: None,
+        })
+    }
+}
+
+impl Pending {
+    fn collect(&mut self, line: &str) {
+        self.lines.push(line.to_string());
+        match self.kind {
+            CrashKind::Java => {
+                if let Some(rest) = line.strip_prefix("Process: ") {
+                    let (process, pid) = rest.split_once(", PID: ").unwrap_or((rest, ""));
+                    self.process = Some(process.trim().to_string());
+                    self.pid = pid.trim().parse().ok();
+                } else if self.summary.is_empty() && self.process.is_some() {
+                    self.summary = line.trim().to_string();
+                }
+            }
+            CrashKind::Anr => {
+                if let Some(pid) = line.strip_prefix("PID: ") {
+                    self.pid = pid.trim().parse().ok();
+                } else if let Some(reason) = line.strip_prefix("Reason: ") {
+                    self.summary = reason.trim().to_string();
+                }
+            }
+            CrashKind::Native => {
+                if let Some((pid, rest)) = line
+                    .strip_prefix("pid: ")
+                    .and_then(|rest| rest.split_once(','))
+                {
+                    self.pid = pid.trim().parse().ok();
+                    self.process = rest
+                        .split_once(">>> ")
+                        .and_then(|(_, name)| name.split_once(" <<<"))
+                        .map(|(name, _)| name.to_string());
+                } else if line.starts_with("signal ") {
+                    self.summary = line.to_string();
+                }
+            }
+            CrashKind::Dialog => {}
+        }
+    }
+}
+
+/// Reports a crash dialog of `package` in front of everything else.
+///
+/// ```text
+///   mCurrentFocus=Window{9e1e3a4 u0 Application Error: com.example.shop}
+///   mCurrentFocus=Window{9e1e3a4 u0 Application Not Responding: com.example.shop}
+/// ```
+fn check_dialog(device: &mut ADBServerDevice, package: &str) -> Option<CrashReport> {
+    let mut output = Vec::new();
+    device
+        .shell_command(
+            &["dumpsys", "window", "|", "grep", "mCurrentFocus"],
+            &mut output,
+        )
+        .ok()?;
+    let output = String::from_utf8_lossy(&output);
+    let title = ["Application Error: ", "Application Not Responding: "]
+        .into_iter()
+        .find(|title| output.contains(&format!("{}{}", title, package)))?;
+    Some(CrashReport {
+        package: package.to_string(),
+        process: package.to_string(),
+        kind: CrashKind::Dialog,
+        pid: None,
+        time: SystemTime::now(),
+        summary: title.trim_end_matches(": ").to_string(),
+        stack_trace: String::new(),
+        remote_trace: None,
+        trace_file: None,
+    })
+}
+
+/// Pulls the tombstone or newest ANR trace into `directory`.
+fn pull_trace(
+    device: &mut ADBServerDevice,
+    report: &CrashReport,
+    directory: &Path,
+) -> Option<PathBuf> {
+    let remote = match (&report.remote_trace, report.kind) {
+        (Some(remote), _) => remote.clone(),
+        (None, CrashKind::Anr) => {
+            let mut output = Vec::new();
+            device
+                .shell_command(&["ls", "-t", "/data/anr/"], &mut output)
+                .ok()?;
+            let newest = String::from_utf8_lossy(&output)
+                .split_whitespace()
+                .next()?
+                .to_string();
+            format!("/data/anr/{}", newest)
+        }
+        _ => return None,
+    };
+    let name = remote.rsplit('/').next().unwrap_or("trace");
+    let local = directory.join(format!("{}_{}", report.package, name));
+    let pulled = File::create(&local)
+        .map_err(DroidError::from)
+        .and_then(|mut file| {
+            device
+                .pull(&remote, &mut file)
+                .map_err(|e| DroidError::AdbError(e.to_string()))
+        });
+    match pulled {
+        Ok(()) => {
+            log::info!("Pulled {} to {:?}", remote, local);
+            Some(local)
+        }
+        Err(e) => {
+            log::warn!("Could not pull {}: {}", remote, e);
+            let _ = std::fs::remove_file(&local);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    fn entry(pid: u32, tag: &str, message: &str) -> LogEntry {
+        LogEntry {
+            time: UNIX_EPOCH,
+            pid,
+            tid: pid,
+            level: LogLevel::Error,
+            tag: tag.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    fn feed_all(parser: &mut CrashParser, entries: &[LogEntry]) -> Option<CrashReport> {
+        let mut report = None;
+        for entry in entries {
+            assert!(report.is_none(), "reported before the last entry");
+            report = parser.feed(entry);
+        }
+        report.or_else(|| parser.flush())
+    }
+
+    #[test]
+    fn java_exception() {
+        let mut parser = CrashParser::new("com.example.shop");
+        let report = feed_all(
+            &mut parser,
+            &[
+                entry(4242, "AndroidRuntime", "FATAL EXCEPTION: main"),
+                entry(
+                    4242,
+                    "AndroidRuntime",
+                    "Process: com.example.shop, PID: 4242",
+                ),
+                entry(
+                    4242,
+                    "AndroidRuntime",
+                    "java.lang.IllegalStateException: cart is empty",
+                ),
+                entry(1234, "ActivityManager", "  Force finishing activity"),
+                entry(
+                    4242,
+                    "AndroidRuntime",
+                    "\tat com.example.shop.Cart.checkout(Cart.kt:42)",
+                ),
+            ],
+        )
+        .unwrap();
+        assert_eq!(report.kind, CrashKind::Java);
+        assert_eq!(report.process, "com.example.shop");
+        assert_eq!(report.pid, Some(4242));
+        assert_eq!(
+            report.summary,
+            "java.lang.IllegalStateException: cart is empty"
+        );
+        assert_eq!(report.stack_trace.lines().count(), 4);
+        assert_eq!(report.remote_trace, None);
+    }
+
+    #[test]
+    fn anr_logged_by_the_system() {
+        let mut parser = CrashParser::new("com.example.shop");
+        let report = feed_all(
+            &mut parser,
+            &[
+                entry(
+                    1234,
+                    "ActivityManager",
+                    "ANR in com.example.shop:sync (com.example.shop/.SyncService)",
+                ),
+                entry(1234, "ActivityManager", "PID: 4260"),
+                entry(
+                    1234,
+                    "ActivityManager",
+                    "Reason: Input dispatching timed out",
+                ),
+            ],
+        )
+        .unwrap();
+        assert_eq!(report.kind, CrashKind::Anr);
+        assert_eq!(report.process, "com.example.shop:sync");
+        assert_eq!(report.pid, Some(4260));
+        assert_eq!(report.summary, "Input dispatching timed out");
+    }
+
+    #[test]
+    fn native_crash_with_tombstone() {
+        let mut parser = CrashParser::new("com.example.shop");
+        let report = feed_all(
+            &mut parser,
+            &[
+                entry(
+                    4300,
+                    "DEBUG",
+                    "*** *** *** *** *** *** *** *** *** *** *** *** *** *** *** ***",
+                ),
+                entry(
+                    4300,
+                    "DEBUG",
+                    "pid: 4242, tid: 4250, name: RenderThread  >>> com.example.shop <<<",
+                ),
+                entry(
+                    4300,
+                    "DEBUG",
+                    "signal 11 (SIGSEGV), code 1 (SEGV_MAPERR), fault addr 0x0",
+                ),
+                entry(
+                    520,
+                    "tombstoned",
+                    "Tombstone written to: /data/tombstones/tombstone_03",
+                ),
+            ],
+        )
+        .unwrap();
+        assert_eq!(report.kind, CrashKind::Native);
+        assert_eq!(report.process, "com.example.shop");
+        assert_eq!(report.pid, Some(4242));
+        assert_eq!(
+            report.summary,
+            "signal 11 (SIGSEGV), code 1 (SEGV_MAPERR), fault addr 0x0"
+        );
+        assert_eq!(
+            report.remote_trace.as_deref(),
+            Some("/data/tombstones/tombstone_03")
+        );
+    }
+
+    #[test]
+    fn ignores_other_processes() {
+        let mut parser = CrashParser::new("com.example.shop");
+        let other = [
+            entry(5000, "AndroidRuntime", "FATAL EXCEPTION: main"),
+            entry(
+                5000,
+                "AndroidRuntime",
+                "Process: com.example.shopping, PID: 5000",
+            ),
+            entry(5000, "AndroidRuntime", "java.lang.NullPointerException"),
+        ];
+        assert_eq!(feed_all(&mut parser, &other), None);
+
+        // A crash of the package that follows is still reported.
+        let mut entries = other.to_vec();
+        entries.extend([
+            entry(4242, "AndroidRuntime", "FATAL EXCEPTION: main"),
+            entry(
+                4242,
+                "AndroidRuntime",
+                "Process: com.example.shop, PID: 4242",
+            ),
+        ]);
+        let report = feed_all(&mut parser, &entries).unwrap();
+        assert_eq!(report.pid, Some(4242));
+    }
+}
@@ -1,81 +1,577 @@
+use std::fs::File;
+use std::io::{self, Write};
 use std::net::SocketAddrV4;
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use adb_client::{ADBDeviceExt, ADBServer, ADBServerDevice};
+use adb_client::{ADBDeviceExt, ADBServer, ADBServerDevice, RustADBError};
 use image::DynamicImage;
 
+mod screencap;
+mod session;
+mod state;
+mod supervisor;
+mod touchscreen;
+mod ui;
+
+pub use session::ShellOutput;
+pub use state::{ForegroundActivity, KeyboardState};
+pub use ui::UiNode;
+
+use crate::app::AppVersion;
 use crate::common::point::Point;
-use crate::error::{DroidError, Result};
+use crate::common::rect::Rect;
+use crate::common::relative_rect::RelativeRect;
+use crate::config::DroidConfig;
+use crate::crash::{CrashMonitor, CrashOptions};
+use crate::error::{DroidError, InstallFailure, Result};
+use crate::jank;
+use crate::logcat::{LogFilter, LogcatStream};
+use crate::models::{
+    CaptureStrategy, DeviceInfo, InputInjection, MotionAction, MotionEvent, ReconnectPolicy,
+    Rotation,
+};
+use crate::perf::{PerfSampler, SamplerOptions};
+use crate::selector;
+use crate::stream::{ScreenStream, StreamOptions};
+use crate::transfer::{self, Counting, RemoteFile};
+use screencap::RawLayout;
+use session::ShellSession;
+use supervisor::Supervisor;
+use touchscreen::Touchscreen;
+
+/// The first Android API level whose `input` tool understands `draganddrop`.
+const DRAG_AND_DROP_MIN_SDK: u32 = 24;
+/// The first Android API level whose `input` tool understands `motionevent`.
+const MOTION_EVENT_MIN_SDK: u32 = 29;
+/// How long a command may run in the persistent shell before the session is abandoned.
+const SESSION_TIMEOUT: Duration = Duration::from_secs(60);
+/// How long to wait for the tracker to report a lost device after a command failed.
+const LOSS_GRACE: Duration = Duration::from_secs(1);
+/// How long a queried display rotation is reused before it is read again.
+const ROTATION_TTL: Duration = Duration::from_secs(1);
+/// Where `uiautomator dump` writes the view hierarchy on the device.
+const UI_DUMP_PATH: &str = "/data/local/tmp/droid_ui.xml";
+/// Printed after a command together with its exit status.
+const EXIT_MARKER: &str = "__DROID_EXIT__";
+/// Where APKs are pushed before they are installed.
+const INSTALL_DIR: &str = "/data/local/tmp";
+/// Numbers the installs of this process, so their pushed APKs never share a name.
+static INSTALL_COUNTER: AtomicU64 = AtomicU64::new(0);
+/// Roughly how long one `input motionevent` takes, most of it starting the
+/// `input` tool's app process.
+const INPUT_EVENT_COST: Duration = Duration::from_millis(200);
+/// The longest command line built from a list of paths, which stays below the
+/// 4 KiB shell request limit of older adb daemons.
+const MAX_COMMAND_LENGTH: usize = 4000;
 
 pub struct DeviceController {
     device: ADBServerDevice,
+    adb_addr: SocketAddrV4,
+    serial: String,
+    info: Option<DeviceInfo>,
+    /// The most recently read rotation and when it was read.
+    rotation: Option<(Rotation, Instant)>,
+    persistent_shell: bool,
+    session: Option<ShellSession>,
+    input_injection: InputInjection,
+    touchscreen: Option<Touchscreen>,
+    touchscreen_probed: bool,
+    capture_strategy: CaptureStrategy,
+    raw_layout: Option<RawLayout>,
+    supervisor: Supervisor,
+    reconnect: ReconnectPolicy,
+    /// The supervisor's loss count when `device` was acquired.
+    losses_seen: u64,
 }
 
 impl DeviceController {
-    pub fn new(device_identifier: Option<&str>, adb_addr: SocketAddrV4) -> Result<Self> {
-        let mut server = ADBServer::new(adb_addr);
-
-        let devices = server
-            .devices()
-            .map_err(|e| DroidError::AdbError(e.to_string()))?;
-        if devices.is_empty() {
-            return Err(DroidError::DeviceNotFound);
+    pub fn new(config: &DroidConfig) -> Result<Self> {
+        let adb_addr = config.adb_server_addr;
+        let mut selector = config.device_selector.clone();
+        if let Some(serial) = &config.device_serial {
+            selector.serial = Some(serial.clone());
         }
-
-        let target_identifier = match device_identifier {
-            Some(identifier) => devices
-                .iter()
-                .find(|d| d.identifier == identifier)
-                .map(|d| d.identifier.clone())
-                .ok_or(DroidError::DeviceNotFound)?,
-            None => devices[0].identifier.clone(),
-        };
+        let target_identifier = selector::select(adb_addr, &selector)?.serial;
 
         log::info!(
             "Connecting to device '{}' via ADB server at {}",
             target_identifier,
             adb_addr
         );
-        let device = server
+        let device = ADBServer::new(adb_addr)
             .get_device_by_name(&target_identifier)
             .map_err(|e| DroidError::AdbError(e.to_string()))?;
+        let supervisor = Supervisor::start(adb_addr, &target_identifier)?;
 
-        Ok(Self { device })
+        Ok(Self {
+            device,
+            adb_addr,
+            serial: target_identifier,
+            info: None,
+            rotation: None,
+            persistent_shell: config.persistent_shell,
+            session: None,
+            input_injection: config.input_injection,
+            touchscreen: None,
+            touchscreen_probed: false,
+            capture_strategy: config.capture_strategy,
+            raw_layout: None,
+            supervisor,
+            reconnect: config.reconnect,
+            losses_seen: 0,
+        })
     }
 
     /// Executes a raw shell command string.
     /// This is the new, safer internal method.
     fn shell(&mut self, command: &str) -> Result<String> {
+        if self.persistent_shell {
+            return Ok(self.persistent_command(command)?.stdout);
+        }
         log::debug!("Executing ADB shell command: {}", command);
-        let args: Vec<&str> = command.split_whitespace().collect();
-        let mut output_buffer: Vec<u8> = Vec::new();
-
-        self.device
-            .shell_command(&args, &mut output_buffer)
-            .map_err(|e| DroidError::AdbError(e.to_string()))?;
+        let output_buffer = self.shell_bytes(command)?;
 
         String::from_utf8(output_buffer)
             .map_err(|e| DroidError::AdbError(format!("Shell output is not valid UTF-8: {}", e)))
     }
 
+    /// Executes a shell command and returns its output together with its exit status.
+    pub fn shell_status(&mut self, command: &str) -> Result<ShellOutput> {
+        if self.persistent_shell {
+            // The session reports the exit status of every command itself.
+            return self.persistent_command(command);
+        }
+        // The quotes split the marker, so an echoed copy of the command never matches it.
+        let output = self.shell(&format!("{}; echo \"__DROID\"\"_EXIT__$?\"", command))?;
+        let Some(index) = output.rfind(EXIT_MARKER) else {
+            return Err(DroidError::AdbError(format!(
+                "Missing exit status in output of `{}`",
+                command
+            )));
+        };
+        let status = &output[index + EXIT_MARKER.len()..];
+        let exit_code = status.trim().parse().map_err(|e| {
+            DroidError::AdbError(format!("Unexpected exit status '{}': {}", status.trim(), e))
+        })?;
+        Ok(ShellOutput {
+            stdout: output[..index].to_string(),
+            exit_code,
+        })
+    }
+
+    /// Executes a shell command on its own connection and returns its raw output.
+    fn shell_bytes(&mut self, command: &str) -> Result<Vec<u8>> {
+        self.with_device(|device| {
+            let mut output_buffer: Vec<u8> = Vec::new();
+            device.shell_command(&[command], &mut output_buffer)?;
+            Ok(output_buffer)
+        })
+    }
+
+    /// Runs `operation` on the device connection.
+    ///
+    /// If the operation fails because the device was lost, it is retried once
+    /// after the device is back.
+    fn with_device<T>(
+        &mut self,
+        mut operation: impl FnMut(&mut ADBServerDevice) -> std::result::Result<T, RustADBError>,
+    ) -> Result<T> {
+        self.ensure_connected()?;
+        match operation(&mut self.device) {
+            Ok(value) => Ok(value),
+            Err(e) => {
+                let connection_lost = is_connection_error(&e);
+                self.recover(DroidError::AdbError(e.to_string()), connection_lost)?;
+                operation(&mut self.device).map_err(|e| DroidError::AdbError(e.to_string()))
+            }
+        }
+    }
+
+    /// Decides whether a failed command should be retried.
+    ///
+    /// Returns `error` unchanged unless the device went away, in which case
+    /// this waits for it according to the reconnect policy. The tracker may
+    /// report the loss slightly after the command failed, so it is waited for
+    /// briefly if `connection_lost` says the connection broke; other errors,
+    /// such as a missing file, are returned right away.
+    fn recover(&mut self, error: DroidError, connection_lost: bool) -> Result<()> {
+        if matches!(error, DroidError::DeviceDisconnected { .. }) {
+            return Err(error);
+        }
+        let lost = !self.supervisor.is_online()
+            || (connection_lost && self.supervisor.wait_lost(LOSS_GRACE));
+        if !lost {
+            return Err(error);
+        }
+        log::warn!("Lost device '{}': {}", self.serial, error);
+        self.ensure_connected()
+    }
+
+    /// Waits for the device if it is not online and re-acquires the
+    /// connection if the device went away since it was last used.
+    fn ensure_connected(&mut self) -> Result<()> {
+        if !self.supervisor.is_online() {
+            log::warn!(
+                "Waiting up to {:?} for device '{}' to come back",
+                self.reconnect.timeout,
+                self.serial
+            );
+            if !self.supervisor.wait_online(self.reconnect.timeout) {
+                return Err(DroidError::DeviceDisconnected {
+                    serial: self.serial.clone(),
+                    waited: self.reconnect.timeout,
+                });
+            }
+        }
+
+        let losses = self.supervisor.losses();
+        if losses != self.losses_seen {
+            thread::sleep(self.reconnect.settle);
+            self.device = ADBServer::new(self.adb_addr)
+                .get_device_by_name(&self.serial)
+                .map_err(|e| DroidError::AdbError(e.to_string()))?;
+            // The session's connection died with the device.
+            self.session = None;
+            self.losses_seen = losses;
+            log::info!("Reconnected to device '{}'", self.serial);
+        }
+        Ok(())
+    }
+
+    /// Runs a command through the persistent shell session, retrying it once
+    /// if the device was lost.
+    ///
+    /// A session that failed is dropped either way, since a hung command
+    /// leaves it waiting, but only a broken connection waits for the device.
+    fn persistent_command(&mut self, command: &str) -> Result<ShellOutput> {
+        log::debug!("Executing ADB shell command in session: {}", command);
+        match self.session_shell(command) {
+            Err(e) => {
+                let connection_lost = self.session.as_ref().is_some_and(ShellSession::is_closed);
+                self.session = None;
+                self.recover(e, connection_lost)?;
+                self.session_shell(command)
+                    .inspect_err(|_| self.session = None)
+            }
+            output => output,
+        }
+    }
+
+    /// Runs a command through the persistent shell session, opening it on first use.
+    ///
+    /// If the session cannot be opened, the controller falls back to one-off
+    /// shell commands for the rest of its lifetime.
+    fn session_shell(&mut self, command: &str) -> Result<ShellOutput> {
+        if self.session.is_none() {
+            self.ensure_connected()?;
+            let opened = ADBServer::new(self.adb_addr)
+                .get_device_by_name(&self.serial)
+                .map_err(|e| DroidError::AdbError(e.to_string()))
+                .and_then(|device| ShellSession::open(device, SESSION_TIMEOUT));
+            match opened {
+                Ok(session) => self.session = Some(session),
+                Err(e) => {
+                    log::warn!(
+                        "Could not open a persistent shell, using one-off commands: {}",
+                        e
+                    );
+                    self.persistent_shell = false;
+                    return self.shell_status(command);
+                }
+            }
+        }
+
+        let Some(session) = self.session.as_mut() else {
+            return Err(DroidError::AdbError(
+                "Persistent shell is not open".to_string(),
+            ));
+        };
+        session.run(command)
+    }
+
+    /// Returns the touchscreen to drive with `sendevent`, if that injection is
+    /// enabled and the shell user may write to the device node.
+    fn touchscreen(&mut self) -> Result<Option<&Touchscreen>> {
+        if self.input_injection != InputInjection::Sendevent {
+            return Ok(None);
+        }
+        if !self.touchscreen_probed {
+            self.touchscreen_probed = true;
+            let found = Touchscreen::parse(&self.shell("getevent -p")?);
+            self.touchscreen = match found {
+                Some(touchscreen) => {
+                    let check =
+                        self.shell(&format!("test -w {} && echo writable", touchscreen.path))?;
+                    if check.contains("writable") {
+                        log::info!("Injecting touches directly into {}", touchscreen.path);
+                        Some(touchscreen)
+                    } else {
+                        log::warn!(
+                            "{} is not writable by the shell user; using `input` instead.",
+                            touchscreen.path
+                        );
+                        None
+                    }
+                }
+                None => {
+                    log::warn!("No multi-touch input device found; using `input` instead.");
+                    None
+                }
+            };
+        }
+        Ok(self.touchscreen.as_ref())
+    }
+
+    /// Replays `events` through `sendevent` if direct injection is available.
+    ///
+    /// Returns `false` without sending anything when it is not.
+    fn send_direct(&mut self, events: &[MotionEvent]) -> Result<bool> {
+        if self.touchscreen()?.is_none() {
+            return Ok(false);
+        }
+        let info = self.device_info()?;
+        // The panel's axes follow the natural orientation, the events the current one.
+        let rotation = self.cached_rotation()?;
+        let Some(touchscreen) = self.touchscreen.as_ref() else {
+            return Ok(false);
+        };
+        let script = touchscreen.script(events, (info.screen_width, info.screen_height), rotation);
+        // Long gestures are split between events; the pointer stays down across batches.
+        for (batch, _) in batch_commands("", &script, |command| command.clone()) {
+            self.shell(&batch)?;
+        }
+        Ok(true)
+    }
+
+    /// Returns the ADB serial of the connected device.
+    pub fn serial(&self) -> &str {
+        &self.serial
+    }
+
+    /// Whether the ADB server currently reports the device as online.
+    pub fn is_online(&self) -> bool {
+        self.supervisor.is_online()
+    }
+
+    /// Reads a system property with `getprop`.
+    ///
+    /// Returns an empty string if the property is not set.
+    pub fn getprop(&mut self, key: &str) -> Result<String> {
+        let output = self.shell(&format!("getprop {}", key))?;
+        Ok(output.trim().to_string())
+    }
+
+    /// Returns static information about the device.
+    ///
+    /// The values are queried once and cached for the lifetime of the controller.
+    pub fn device_info(&mut self) -> Result<DeviceInfo> {
+        if let Some(info) = self.info {
+            return Ok(info);
+        }
+
+        let output = self.getprop("ro.build.version.sdk")?;
+        let sdk_level = output.parse::<u32>().map_err(|e| {
+            DroidError::AdbError(format!("Unexpected SDK level '{}': {}", output, e))
+        })?;
+
+        let output = self.shell("wm size")?;
+        let (screen_width, screen_height) = parse_wm_size(&output).ok_or_else(|| {
+            DroidError::AdbError(format!("Unexpected `wm size` output: {}", output.trim()))
+        })?;
+
+        let info = DeviceInfo {
+            sdk_level,
+            screen_width,
+            screen_height,
+        };
+        log::debug!("Device info: {:?}", info);
+        self.info = Some(info);
+        Ok(info)
+    }
+
+    /// Returns the screen size in pixels in the current orientation, which is
+    /// the coordinate space of input events and screenshots.
+    ///
+    /// The rotation is reused for up to `ROTATION_TTL`, so consecutive
+    /// gestures do not each pay for a `dumpsys` round trip.
+    pub fn screen_size(&mut self) -> Result<(u32, u32)> {
+        let info = self.device_info()?;
+        if self.cached_rotation()?.is_sideways() {
+            Ok((info.screen_height, info.screen_width))
+        } else {
+            Ok((info.screen_width, info.screen_height))
+        }
+    }
+
+    /// Returns the Android API level of the device (`ro.build.version.sdk`).
+    pub fn sdk_level(&mut self) -> Result<u32> {
+        Ok(self.device_info()?.sdk_level)
+    }
+
+    /// Whether the device accepts raw DOWN/MOVE/UP motion events, either
+    /// through `input motionevent` or direct injection.
+    pub fn supports_motion_events(&mut self) -> Result<bool> {
+        if self.touchscreen()?.is_some() {
+            return Ok(true);
+        }
+        Ok(self.sdk_level()? >= MOTION_EVENT_MIN_SDK)
+    }
+
+    /// Whether the device accepts `input draganddrop`.
+    pub fn supports_drag_and_drop(&mut self) -> Result<bool> {
+        Ok(self.sdk_level()? >= DRAG_AND_DROP_MIN_SDK)
+    }
+
+    /// Captures the screen using the configured `CaptureStrategy`.
     pub fn screenshot(&mut self) -> Result<DynamicImage> {
+        match self.capture_strategy {
+            CaptureStrategy::Png => self.screenshot_png(),
+            CaptureStrategy::Raw => self.screenshot_raw(),
+        }
+    }
+
+    /// Captures uncompressed `screencap` output.
+    pub fn screenshot_raw(&mut self) -> Result<DynamicImage> {
+        log::debug!("Capturing raw screenshot...");
+        let data = self.shell_bytes("screencap")?;
+        let (layout, image) = screencap::decode(&data)?;
+        self.raw_layout = Some(layout);
+        Ok(image)
+    }
+
+    /// Captures only `rect` of the screen, given in pixels of the current orientation.
+    ///
+    /// Rows outside the region are cut off on the device, so only the rows
+    /// covering `rect` are transferred; columns are cropped on the host. The
+    /// pixel layout is learned from the most recent raw capture and refreshed
+    /// when it no longer matches the screen, e.g. after a rotation.
+    pub fn screenshot_region(&mut self, rect: Rect) -> Result<DynamicImage> {
+        let screen = self.screen_size()?;
+        self.capture_region(rect, screen)
+    }
+
+    /// Captures `rect`, given relative to the screen in its current orientation.
+    pub fn screenshot_relative(&mut self, rect: RelativeRect) -> Result<DynamicImage> {
+        let (width, height) = self.screen_size()?;
+        self.capture_region(rect.to_absolute(width, height), (width, height))
+    }
+
+    fn capture_region(&mut self, rect: Rect, screen: (u32, u32)) -> Result<DynamicImage> {
+        let layout = match self.raw_layout {
+            // A rotation keeps the number of bytes, so compare the orientation.
+            Some(layout) if (layout.width > layout.height) == (screen.0 > screen.1) => layout,
+            _ => {
+                let image = self.screenshot_raw()?;
+                return Ok(crop(&image, rect));
+            }
+        };
+
+        let y = rect.y.min(layout.height);
+        let rows = rect.height.min(layout.height - y);
+        let offset = layout.header_len + y as usize * layout.row_len();
+        let len = rows as usize * layout.row_len();
+        log::debug!("Capturing raw screenshot rows {}..{}", y, y + rows);
+        let data = self.shell_bytes(&format!(
+            "screencap | tail -c +{} | head -c {}",
+            offset + 1,
+            len
+        ))?;
+        if data.len() != len {
+            log::debug!("Raw screencap layout changed; capturing the full screen instead.");
+            let image = self.screenshot_raw()?;
+            return Ok(crop(&image, rect));
+        }
+
+        let band = screencap::decode_pixels(&layout, rows, &data)?;
+        Ok(crop(&band, Rect::new(rect.x, 0, rect.width, rows)))
+    }
+
+    fn screenshot_png(&mut self) -> Result<DynamicImage> {
         log::debug!("Capturing screenshot...");
-        let png_data = self
-            .device
-            .framebuffer_bytes()
-            .map_err(|e| DroidError::AdbError(e.to_string()))?;
+        let png_data = self.with_device(|device| device.framebuffer_bytes())?;
 
         image::load_from_memory(&png_data).map_err(DroidError::ImageError)
     }
 
+    /// Starts a continuous screen capture on a separate ADB connection, sized
+    /// for the current orientation.
+    pub fn start_stream(&mut self, options: StreamOptions) -> Result<ScreenStream> {
+        self.ensure_connected()?;
+        // A rotation read just before a restart may already be outdated.
+        self.rotation()?;
+        let screen = self.screen_size()?;
+        ScreenStream::start(self.adb_addr, &self.serial, screen, options)
+    }
+
+    /// Starts sampling the resource usage of `package` on a separate connection.
+    pub fn start_sampler(&mut self, package: &str, options: SamplerOptions) -> Result<PerfSampler> {
+        self.ensure_connected()?;
+        PerfSampler::start(self.adb_addr, &self.serial, package, options)
+    }
+
+    /// Starts watching for crashes of `package` on separate connections.
+    pub fn start_crash_monitor(
+        &mut self,
+        package: &str,
+        options: CrashOptions,
+    ) -> Result<CrashMonitor> {
+        self.ensure_connected()?;
+        CrashMonitor::start(self.adb_addr, &self.serial, package, options)
+    }
+
+    /// Starts reading the device log on a separate connection.
+    pub fn start_logcat(&mut self, filter: LogFilter) -> Result<LogcatStream> {
+        self.ensure_connected()?;
+        LogcatStream::start(self.adb_addr, &self.serial, filter)
+    }
+
+    /// Clears the frame statistics `dumpsys gfxinfo` keeps for `package`.
+    pub fn reset_frame_stats(&mut self, package: &str) -> Result<()> {
+        let output = self.shell(&format!("dumpsys gfxinfo {} reset", package))?;
+        if output.contains("No process found") {
+            log::warn!("Cannot reset frame stats, {} is not running", package);
+        }
+        Ok(())
+    }
+
+    /// Returns `dumpsys gfxinfo <package> framestats`.
+    pub fn framestats(&mut self, package: &str) -> Result<String> {
+        self.shell(&format!("dumpsys gfxinfo {} framestats", package))
+    }
+
+    /// Returns the display refresh rate in Hz.
+    pub fn refresh_rate(&mut self) -> Result<f32> {
+        let output = self.shell("dumpsys SurfaceFlinger --latency")?;
+        Ok(jank::parse_refresh_rate(&output).unwrap_or_else(|| {
+            log::debug!(
+                "Unknown refresh rate, assuming {} Hz",
+                jank::DEFAULT_REFRESH_RATE
+            );
+            jank::DEFAULT_REFRESH_RATE
+        }))
+    }
+
     /// Taps a point on the screen.
     pub fn tap(&mut self, point: Point) -> Result<()> {
+        let events = [
+            MotionEvent::new(MotionAction::Down, point, Duration::from_millis(50)),
+            MotionEvent::new(MotionAction::Up, point, Duration::ZERO),
+        ];
+        if self.send_direct(&events)? {
+            return Ok(());
+        }
         let cmd = format!("input tap {} {}", point.x, point.y);
         self.shell(&cmd)?;
         Ok(())
     }
 
     pub fn swipe(&mut self, start: Point, end: Point, duration: Duration) -> Result<()> {
+        if self.send_direct(&touchscreen::swipe_events(start, end, duration))? {
+            return Ok(());
+        }
         let cmd = format!(
             "input swipe {} {} {} {} {}",
             start.x,
@@ -88,6 +584,66 @@ impl DeviceController {
         Ok(())
     }
 
+    /// Long-presses `start` and drags it to `end` with `input draganddrop`.
+    ///
+    /// The device decides how long the pick-up press lasts.
+    pub fn drag_and_drop(&mut self, start: Point, end: Point, duration: Duration) -> Result<()> {
+        let cmd = format!(
+            "input draganddrop {} {} {} {} {}",
+            start.x,
+            start.y,
+            end.x,
+            end.y,
+            duration.as_millis()
+        );
+        self.shell(&cmd)?;
+        Ok(())
+    }
+
+    /// Sends a sequence of raw motion events in as few shell invocations as
+    /// the command length limit allows.
+    ///
+    /// Each event's `delay` is reproduced with an on-device `sleep`, so the
+    /// pointer stays down between events without a round trip to the host.
+    ///
+    /// Without direct injection, every event starts the `input` tool, which
+    /// takes about `INPUT_EVENT_COST`. Shorter delays cannot be kept: the
+    /// gesture slows down and its velocity profile flattens. Use
+    /// `InputInjection::Sendevent` where timing matters.
+    pub fn motion_events(&mut self, events: &[MotionEvent]) -> Result<()> {
+        if self.send_direct(events)? {
+            return Ok(());
+        }
+        if events.len() > 2 && events.iter().any(|e| e.delay < INPUT_EVENT_COST) {
+            log::warn!(
+                "Sending {} motion events through `input`, which takes about {:?} per event; \
+                 the gesture will be slower than requested.",
+                events.len(),
+                INPUT_EVENT_COST
+            );
+        }
+        let script: Vec<String> = events
+            .iter()
+            .map(|event| {
+                let cmd = format!(
+                    "input motionevent {} {} {};",
+                    event.action.as_str(),
+                    event.point.x,
+                    event.point.y
+                );
+                if event.delay.is_zero() {
+                    cmd
+                } else {
+                    format!("{}sleep {:.3};", cmd, event.delay.as_secs_f32())
+                }
+            })
+            .collect();
+        for (batch, _) in batch_commands("", &script, |command| command.clone()) {
+            self.shell(&batch)?;
+        }
+        Ok(())
+    }
+
     /// Inputs text.
     pub fn input_text(&mut self, text: &str) -> Result<()> {
         // ADB shell requires escaping spaces. %s is a common way.
@@ -103,6 +659,23 @@ impl DeviceController {
         Ok(())
     }
 
+    /// Drops the kernel's page cache; this needs a rooted device.
+    pub fn drop_caches(&mut self) -> Result<()> {
+        let result = self.shell_status("sync; echo 3 > /proc/sys/vm/drop_caches")?;
+        if result.exit_code != 0 {
+            return Err(DroidError::AdbError(format!(
+                "Dropping the page cache failed, the device may not be rooted: {}",
+                result.stdout.trim()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Runs an activity manager command, e.g. `am broadcast -a com.example.RESET`.
+    pub fn activity_manager(&mut self, command: &str, args: &str) -> Result<String> {
+        self.shell(&format!("am {} {}", command, args))
+    }
+
     /// Launches an app by package name using the launcher intent.
     pub fn launch_app(&mut self, package: &str) -> Result<()> {
         let cmd = format!(
@@ -125,4 +698,729 @@ impl DeviceController {
         }
         Ok(())
     }
+
+    /// Whether `package` is installed for the current user.
+    pub fn is_installed(&mut self, package: &str) -> Result<bool> {
+        let output = self.shell_status(&format!("pm path {}", package))?;
+        Ok(output.exit_code == 0 && output.stdout.contains("package:"))
+    }
+
+    /// Reads the installed version of `package`, or `None` if it is not installed.
+    pub fn package_version(&mut self, package: &str) -> Result<Option<AppVersion>> {
+        let output = self.shell(&format!(
+            "dumpsys package {} | grep -E 'versionCode=|versionName='",
+            package
+        ))?;
+        Ok(parse_package_version(&output))
+    }
+
+    /// Returns the ids of the processes running `package`.
+    pub fn pidof(&mut self, package: &str) -> Result<Vec<u32>> {
+        let output = self.shell_status(&format!("pidof {}", package))?;
+        Ok(output
+            .stdout
+            .split_whitespace()
+            .filter_map(|pid| pid.parse().ok())
+            .collect())
+    }
+
+    /// Stops every process of `package`.
+    pub fn force_stop(&mut self, package: &str) -> Result<()> {
+        self.package_command(package, "force-stop", &format!("am force-stop {}", package))
+    }
+
+    /// Deletes all data of `package`, like "Clear storage" in the settings.
+    pub fn clear_data(&mut self, package: &str) -> Result<()> {
+        self.package_command(package, "clear", &format!("pm clear {}", package))
+    }
+
+    /// Runs `command` for an installed package, failing on a non-zero exit status.
+    fn package_command(&mut self, package: &str, action: &str, command: &str) -> Result<()> {
+        if !self.is_installed(package)? {
+            return Err(DroidError::PackageNotFound(package.to_string()));
+        }
+        let output = self.shell_status(command)?;
+        if output.exit_code != 0 {
+            return Err(DroidError::AppCommandFailed {
+                package: package.to_string(),
+                action: action.to_string(),
+                exit_code: output.exit_code,
+                output: output.stdout.trim().to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Uploads a local file to `remote` on the device.
+    pub fn push_file(&mut self, local: &Path, remote: &str) -> Result<()> {
+        self.push_file_with_progress(local, remote, &mut |_| {})
+    }
+
+    /// Uploads a local file to `remote` on the device, calling `progress`
+    /// with the number of bytes sent so far.
+    pub fn push_file_with_progress(
+        &mut self,
+        local: &Path,
+        remote: &str,
+        progress: &mut dyn FnMut(u64),
+    ) -> Result<()> {
+        log::debug!("Pushing {:?} to {}", local, remote);
+        self.with_device(|device| {
+            let mut file = Counting {
+                inner: File::open(local)?,
+                count: 0,
+                report: &mut *progress,
+            };
+            device.push(&mut file, remote)
+        })
+    }
+
+    /// Downloads `remote` from the device to a local file, calling `progress`
+    /// with the number of bytes received so far.
+    pub fn pull_file(
+        &mut self,
+        remote: &str,
+        local: &Path,
+        progress: &mut dyn FnMut(u64),
+    ) -> Result<()> {
+        log::debug!("Pulling {} to {:?}", remote, local);
+        self.with_device(|device| {
+            let mut file = Counting {
+                inner: File::create(local)?,
+                count: 0,
+                report: &mut *progress,
+            };
+            device.pull(&remote, &mut file)?;
+            file.flush().map_err(RustADBError::from)
+        })
+    }
+
+    /// Describes `remote`, following symbolic links, or returns `None` if it
+    /// does not exist.
+    pub fn stat(&mut self, remote: &str) -> Result<Option<RemoteFile>> {
+        let output = self.shell_status(&format!("stat -L -c '%f %s %Y %n' {}", quote(remote)))?;
+        if output.exit_code != 0 {
+            return Ok(None);
+        }
+        Ok(transfer::parse_stat_lines(&output.stdout)
+            .into_iter()
+            .next())
+    }
+
+    /// Lists the contents of the directory `remote`, including those of its
+    /// subdirectories if `recursive` is set.
+    pub fn list_files(&mut self, remote: &str, recursive: bool) -> Result<Vec<RemoteFile>> {
+        let depth = if recursive { "" } else { " -maxdepth 1" };
+        let output = self.shell_status(&format!(
+            "find -H {} -mindepth 1{} -exec stat -c '%f %s %Y %n' {{}} +",
+            quote(remote),
+            depth
+        ))?;
+        let files = transfer::parse_stat_lines(&output.stdout);
+        // `find` also fails for unreadable subdirectories, after listing the rest.
+        if output.exit_code != 0 && files.is_empty() {
+            return Err(file_operation_failed("list", remote, &output.stdout));
+        }
+        Ok(files)
+    }
+
+    /// Sets the modification time of `remote`.
+    pub fn set_modified(&mut self, remote: &str, time: SystemTime) -> Result<()> {
+        let seconds = time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let output = self.shell_status(&format!("touch -m -d @{} {}", seconds, quote(remote)))?;
+        if output.exit_code != 0 {
+            return Err(file_operation_failed("touch", remote, &output.stdout));
+        }
+        Ok(())
+    }
+
+    /// Creates the directories `remote`, along with any missing parents.
+    pub fn make_dirs(&mut self, remote: &[String]) -> Result<()> {
+        for (command, paths) in batch_commands("mkdir -p", remote, |path| quote(path)) {
+            let output = self.shell_status(&command)?;
+            if output.exit_code != 0 {
+                return Err(file_operation_failed(
+                    "create",
+                    &paths.join(" "),
+                    &output.stdout,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Deletes `remote`, and everything in it if `recursive` is set.
+    pub fn remove(&mut self, remote: &str, recursive: bool) -> Result<()> {
+        let flags = if recursive { "-rf" } else { "-f" };
+        let output = self.shell_status(&format!("rm {} {}", flags, quote(remote)))?;
+        if output.exit_code != 0 {
+            return Err(file_operation_failed("remove", remote, &output.stdout));
+        }
+        Ok(())
+    }
+
+    /// Installs an app from one APK, or from a base APK and its splits.
+    ///
+    /// `flags` are passed to `pm install`, e.g. `-r -g`.
+    pub fn install(&mut self, apks: &[PathBuf], flags: &str) -> Result<()> {
+        let Some(base) = apks.first() else {
+            return Err(DroidError::NoApkToInstall);
+        };
+
+        let mut remotes = Vec::with_capacity(apks.len());
+        let result = self.push_and_install(base, apks, flags, &mut remotes);
+        if !remotes.is_empty()
+            && let Err(e) = self.shell(&format!("rm -f {}", remotes.join(" ")))
+        {
+            log::warn!("Could not remove the pushed APKs: {}", e);
+        }
+        result
+    }
+
+    /// Pushes `apks` to the device, recording their paths in `remotes`, and installs them.
+    fn push_and_install(
+        &mut self,
+        base: &Path,
+        apks: &[PathBuf],
+        flags: &str,
+        remotes: &mut Vec<String>,
+    ) -> Result<()> {
+        // Other controllers, in this process or on other hosts, may install on
+        // the same device at the same time.
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let prefix = format!(
+            "{}/droid_install_{}_{}_{}",
+            INSTALL_DIR,
+            std::process::id(),
+            millis,
+            INSTALL_COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        for (index, apk) in apks.iter().enumerate() {
+            let remote = format!("{}_{}.apk", prefix, index);
+            remotes.push(remote.clone());
+            self.push_file(apk, &remote)?;
+        }
+
+        if remotes.len() == 1 {
+            let output = self.shell(&format!("pm install {} {}", flags, remotes[0]))?;
+            check_install_output(base, &output)
+        } else {
+            self.install_session(base, remotes, flags)
+        }
+    }
+
+    /// Installs split APKs through a `pm install-create` session.
+    fn install_session(&mut self, base: &Path, remotes: &[String], flags: &str) -> Result<()> {
+        let output = self.shell(&format!("pm install-create {}", flags))?;
+        check_install_output(base, &output)?;
+        // Success: created install session [1234]
+        let session = output
+            .split(['[', ']'])
+            .nth(1)
+            .and_then(|id| id.parse::<u32>().ok())
+            .ok_or_else(|| {
+                DroidError::AdbError(format!(
+                    "Unexpected install-create output: {}",
+                    output.trim()
+                ))
+            })?;
+
+        let result = self.commit_session(base, session, remotes);
+        if result.is_err()
+            && let Err(e) = self.shell(&format!("pm install-abandon {}", session))
+        {
+            log::warn!("Could not abandon install session {}: {}", session, e);
+        }
+        result
+    }
+
+    fn commit_session(&mut self, base: &Path, session: u32, remotes: &[String]) -> Result<()> {
+        for (index, remote) in remotes.iter().enumerate() {
+            let output = self.shell(&format!(
+                "pm install-write {} {}.apk {}",
+                session, index, remote
+            ))?;
+            check_install_output(base, &output)?;
+        }
+        let output = self.shell(&format!("pm install-commit {}", session))?;
+        check_install_output(base, &output)
+    }
+
+    /// Removes `package`, keeping its data and cache directories if `keep_data` is set.
+    pub fn uninstall(&mut self, package: &str, keep_data: bool) -> Result<()> {
+        let flags = if keep_data { "-k " } else { "" };
+        let output = self.shell_status(&format!("pm uninstall {}{}", flags, package))?;
+        let stdout = output.stdout.trim();
+        if stdout.contains("Success") {
+            return Ok(());
+        }
+        if (stdout.contains("not installed") || stdout.contains("DELETE_FAILED_INTERNAL_ERROR"))
+            && !self.is_installed(package)?
+        {
+            return Err(DroidError::PackageNotFound(package.to_string()));
+        }
+        Err(DroidError::AppCommandFailed {
+            package: package.to_string(),
+            action: "uninstall".to_string(),
+            exit_code: output.exit_code,
+            output: stdout.to_string(),
+        })
+    }
+
+    /// Dumps the current view hierarchy with `uiautomator`.
+    ///
+    /// This takes a second or more, and fails while the screen is animating.
+    pub fn dump_ui(&mut self) -> Result<Vec<UiNode>> {
+        let output = self.shell(&format!(
+            "uiautomator dump {} >/dev/null && cat {}",
+            UI_DUMP_PATH, UI_DUMP_PATH
+        ))?;
+        if !output.contains("<hierarchy") {
+            return Err(DroidError::AdbError(format!(
+                "uiautomator dump failed: {}",
+                output.trim()
+            )));
+        }
+        Ok(ui::parse_hierarchy(&output))
+    }
+
+    /// Returns the window with input focus, e.g. `com.android.settings/.Settings`.
+    pub fn focused_window(&mut self) -> Result<Option<String>> {
+        let output = self.shell("dumpsys window | grep mCurrentFocus")?;
+        Ok(parse_focused_window(&output))
+    }
+
+    /// Returns the resumed activity, or `None` if there is none, e.g. while
+    /// the screen is off.
+    pub fn foreground_activity(&mut self) -> Result<Option<ForegroundActivity>> {
+        let output = self.shell("dumpsys activity activities | grep ResumedActivity")?;
+        Ok(state::parse_foreground_activity(&output))
+    }
+
+    /// Reads whether the input method window is shown and which input
+    /// method is current from `dumpsys input_method`.
+    pub fn keyboard_state(&mut self) -> Result<KeyboardState> {
+        let output = self.shell(
+            "dumpsys input_method | grep -E 'mInputShown=|mIsInputViewShown=|mCurMethodId='",
+        )?;
+        Ok(state::parse_keyboard_state(&output))
+    }
+
+    /// Reads the current display rotation from the device.
+    pub fn rotation(&mut self) -> Result<Rotation> {
+        let rotation = self.query_rotation()?;
+        self.rotation = Some((rotation, Instant::now()));
+        Ok(rotation)
+    }
+
+    /// Returns the rotation read within the last `ROTATION_TTL`, or reads it again.
+    fn cached_rotation(&mut self) -> Result<Rotation> {
+        match self.rotation {
+            Some((rotation, read_at)) if read_at.elapsed() < ROTATION_TTL => Ok(rotation),
+            _ => self.rotation(),
+        }
+    }
+
+    fn query_rotation(&mut self) -> Result<Rotation> {
+        let output =
+            self.shell("dumpsys window displays | grep -E 'mCurrentRotation=|mRotation='")?;
+        if let Some(rotation) = state::parse_rotation(&output) {
+            return Ok(rotation);
+        }
+        let output = self.shell("dumpsys input | grep SurfaceOrientation")?;
+        state::parse_rotation(&output).ok_or_else(|| {
+            DroidError::AdbError(format!(
+                "Could not read the display rotation: {}",
+                output.trim()
+            ))
+        })
+    }
+
+    /// Returns the package that opened the runtime permission dialog, if one is open.
+    pub fn permission_requester(&mut self) -> Result<Option<String>> {
+        let output = self.shell(
+            "dumpsys activity activities | grep -E 'GrantPermissionsActivity|launchedFromPackage='",
+        )?;
+        let mut lines = output
+            .lines()
+            .skip_while(|line| !line.contains("GrantPermissionsActivity"));
+        Ok(lines.find_map(|line| {
+            let value = line.split("launchedFromPackage=").nth(1)?;
+            value.split_whitespace().next().map(str::to_string)
+        }))
+    }
+
+    /// Returns the runtime permissions of `package` that a permission dialog
+    /// may still ask for: those neither granted nor fixed by the user or a policy.
+    pub fn pending_permissions(&mut self, package: &str) -> Result<Vec<String>> {
+        let output = self.shell(&format!(
+            "dumpsys package {} | grep -E 'permissions:|granted='",
+            package
+        ))?;
+        Ok(parse_pending_permissions(&output))
+    }
+}
+
+/// Maps the output of a `pm install` command to a result.
+///
+/// ```text
+/// Failure [INSTALL_FAILED_VERSION_DOWNGRADE: Downgrade detected: Update version code 1 is older than current 2]
+/// ```
+fn check_install_output(apk: &Path, output: &str) -> Result<()> {
+    let output = output.trim();
+    if output.contains("Success") {
+        return Ok(());
+    }
+    let code = output
+        .split_once("Failure [")
+        .and_then(|(_, rest)| rest.split([':', ']']).next())
+        .unwrap_or("UNKNOWN")
+        .trim();
+    Err(DroidError::InstallFailed {
+        apk: apk.to_path_buf(),
+        reason: InstallFailure::from_code(code),
+        output: output.to_string(),
+    })
+}
+
+/// Reads the first `versionCode` and `versionName` of `dumpsys package`.
+///
+/// ```text
+///     versionCode=331012 minSdk=26 targetSdk=34
+///     versionName=33.1.2
+/// ```
+fn parse_package_version(output: &str) -> Option<AppVersion> {
+    let field = |name: &str| {
+        output
+            .split_whitespace()
+            .find_map(|token| token.strip_prefix(name))
+            .map(str::to_string)
+    };
+    Some(AppVersion {
+        code: field("versionCode=")?.parse().ok()?,
+        name: field("versionName=").unwrap_or_default(),
+    })
+}
+
+/// Extracts the window name from the `mCurrentFocus` line of `dumpsys window`.
+///
+/// ```text
+///   mCurrentFocus=Window{5b1c6e2 u0 com.android.settings/com.android.settings.Settings}
+/// ```
+fn parse_focused_window(output: &str) -> Option<String> {
+    let line = output
+        .lines()
+        .find(|line| line.contains("mCurrentFocus="))?;
+    let window = line.split_whitespace().last()?.trim_end_matches('}');
+    (!window.is_empty() && window != "mCurrentFocus=null").then(|| window.to_string())
+}
+
+/// Collects the runtime permissions of `dumpsys package` that are not granted
+/// and not fixed, since the system never asks for a fixed one.
+///
+/// ```text
+///     runtime permissions:
+///       android.permission.CAMERA: granted=false, flags=[ USER_SENSITIVE_WHEN_GRANTED ]
+///       android.permission.READ_CONTACTS: granted=false, flags=[ USER_SET|USER_FIXED ]
+/// ```
+fn parse_pending_permissions(output: &str) -> Vec<String> {
+    let mut permissions: Vec<String> = Vec::new();
+    let mut in_runtime = false;
+    for line in output.lines().map(str::trim) {
+        if line.ends_with("permissions:") {
+            in_runtime = line == "runtime permissions:";
+            continue;
+        }
+        if !in_runtime {
+            continue;
+        }
+        let Some((name, rest)) = line.split_once(": granted=") else {
+            continue;
+        };
+        let fixed = ["USER_FIXED", "POLICY_FIXED", "SYSTEM_FIXED"]
+            .iter()
+            .any(|flag| rest.contains(flag));
+        // Every user has its own section; the permission is listed once.
+        if rest.starts_with("false") && !fixed && !permissions.iter().any(|p| p == name) {
+            permissions.push(name.to_string());
+        }
+    }
+    permissions
+}
+
+/// Parses `wm size` output, preferring an override size over the physical one.
+///
+/// ```text
+/// Physical size: 1080x2400
+/// Override size: 720x1600
+/// ```
+fn parse_wm_size(output: &str) -> Option<(u32, u32)> {
+    let mut physical = None;
+    let mut overridden = None;
+    for line in output.lines() {
+        let Some((label, value)) = line.split_once(':') else {
+            continue;
+        };
+        let Some((w, h)) = value.trim().split_once('x') else {
+            continue;
+        };
+        let size = (w.parse().ok()?, h.parse().ok()?);
+        if label.trim().starts_with("Override") {
+            overridden = Some(size);
+        } else {
+            physical = Some(size);
+        }
+    }
+    overridden.or(physical)
+}
+
+/// Whether `error` means the connection to the device broke, as opposed to
+/// the device or the host rejecting the operation.
+fn is_connection_error(error: &RustADBError) -> bool {
+    match error {
+        RustADBError::IOError(e) => matches!(
+            e.kind(),
+            io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::ConnectionRefused
+                | io::ErrorKind::NotConnected
+                | io::ErrorKind::BrokenPipe
+                | io::ErrorKind::UnexpectedEof
+                | io::ErrorKind::TimedOut
+        ),
+        RustADBError::ADBRequestFailed(message) => message.contains("device"),
+        RustADBError::DeviceNotFound(_) => true,
+        _ => false,
+    }
+}
+
+/// Splits `program` applied to `items`, each written out by `render`, into
+/// commands of at most `MAX_COMMAND_LENGTH` bytes, each returned with the
+/// items it covers. An item too long to share a command gets one of its own.
+///
+/// An empty `program` joins the rendered items themselves, e.g. a list of
+/// `;`-terminated commands.
+fn batch_commands<'a, T>(
+    program: &str,
+    items: &'a [T],
+    render: impl Fn(&T) -> String,
+) -> Vec<(String, &'a [T])> {
+    let mut batches = Vec::new();
+    let mut start = 0;
+    let mut command = program.to_string();
+    for (i, item) in items.iter().enumerate() {
+        let item = render(item);
+        if i > start && command.len() + 1 + item.len() > MAX_COMMAND_LENGTH {
+            batches.push((command, &items[start..i]));
+            start = i;
+            command = program.to_string();
+        }
+        if !command.is_empty() {
+            command.push(' ');
+        }
+        command.push_str(&item);
+    }
+    if start < items.len() {
+        batches.push((command, &items[start..]));
+    }
+    batches
+}
+
+fn file_operation_failed(action: &str, path: &str, output: &str) -> DroidError {
+    DroidError::FileOperationFailed {
+        action: action.to_string(),
+        path: path.to_string(),
+        output: output.trim().to_string(),
+    }
+}
+
+/// Quotes `value` for the device shell.
+pub(crate) fn quote(value: &str) -> String {
+    if !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "._-/:=@%+,".contains(c))
+    {
+        return value.to_string();
+    }
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Crops `image` to `rect`, clamped to the image bounds.
+fn crop(image: &DynamicImage, rect: Rect) -> DynamicImage {
+    let x = rect.x.min(image.width());
+    let y = rect.y.min(image.height());
+    image.crop_imm(
+        x,
+        y,
+        rect.width.min(image.width() - x),
+        rect.height.min(image.height() - y),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn install_output() {
+        let apk = Path::new("app.apk");
+        assert!(check_install_output(apk, "Performing Streamed Install\nSuccess\n").is_ok());
+
+        let output = "Failure [INSTALL_FAILED_VERSION_DOWNGRADE: Downgrade detected: \
+                      Update version code 1 is older than current 2]";
+        match check_install_output(apk, output) {
+            Err(DroidError::InstallFailed { reason, .. }) => {
+                assert_eq!(reason, InstallFailure::VersionDowngrade)
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        match check_install_output(apk, "Error: Unable to open file: app.apk") {
+            Err(DroidError::InstallFailed { reason, .. }) => {
+                assert_eq!(reason, InstallFailure::Other("UNKNOWN".to_string()))
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn package_version_fields() {
+        let output = "    versionCode=331012 minSdk=26 targetSdk=34\n    versionName=33.1.2\n";
+        assert_eq!(
+            parse_package_version(output),
+            Some(AppVersion {
+                name: "33.1.2".to_string(),
+                code: 331012,
+            })
+        );
+    }
+
+    #[test]
+    fn package_version_first_of_several() {
+        // An updated system app lists the update first, then the factory version.
+        let output = "    versionCode=40 minSdk=26 targetSdk=34\n    versionName=4.0\n    \
+                      versionCode=10 minSdk=26 targetSdk=34\n    versionName=1.0\n";
+        let version = parse_package_version(output).unwrap();
+        assert_eq!((version.code, version.name.as_str()), (40, "4.0"));
+        assert_eq!(parse_package_version(""), None);
+    }
+
+    #[test]
+    fn wm_size_prefers_override() {
+        let output = "Physical size: 1080x2400\nOverride size: 720x1600\n";
+        assert_eq!(parse_wm_size(output), Some((720, 1600)));
+    }
+
+    #[test]
+    fn focused_window_name() {
+        let output = "  mCurrentFocus=Window{5b1c6e2 u0 com.android.settings/com.android.settings.Settings}\n";
+        assert_eq!(
+            parse_focused_window(output).as_deref(),
+            Some("com.android.settings/com.android.settings.Settings")
+        );
+        assert_eq!(parse_focused_window("  mCurrentFocus=null\n"), None);
+    }
+
+    #[test]
+    fn pending_permissions_runtime_section_only() {
+        let output = "\
+    install permissions:
+      android.permission.INTERNET: granted=true
+      android.permission.FOREGROUND_SERVICE: granted=false
+    runtime permissions:
+      android.permission.CAMERA: granted=false, flags=[ USER_SENSITIVE_WHEN_GRANTED ]
+      android.permission.ACCESS_FINE_LOCATION: granted=true, flags=[ USER_SET ]
+      android.permission.READ_CONTACTS: granted=false, flags=[ USER_SET|USER_FIXED ]
+      android.permission.RECORD_AUDIO: granted=false, flags=[ POLICY_FIXED ]
+";
+        assert_eq!(
+            parse_pending_permissions(output),
+            vec!["android.permission.CAMERA".to_string()]
+        );
+    }
+
+    #[test]
+    fn wm_size_physical_only() {
+        assert_eq!(
+            parse_wm_size("Physical size: 1080x2400\n"),
+            Some((1080, 2400))
+        );
+        assert_eq!(parse_wm_size("error: no display"), None);
+    }
+
+    #[test]
+    fn batches_long_path_lists() {
+        let paths: Vec<String> = (0..300)
+            .map(|i| format!("/sdcard/Pictures/album {}", i))
+            .collect();
+        let batches = batch_commands("mkdir -p", &paths, |path| quote(path));
+        assert!(batches.len() > 1);
+        assert!(
+            batches
+                .iter()
+                .all(|(command, _)| command.len() <= MAX_COMMAND_LENGTH)
+        );
+        let covered: Vec<&String> = batches.iter().flat_map(|(_, paths)| *paths).collect();
+        assert_eq!(covered, paths.iter().collect::<Vec<_>>());
+        assert!(
+            batches[0]
+                .0
+                .starts_with("mkdir -p '/sdcard/Pictures/album 0' ")
+        );
+
+        let long = vec!["x".repeat(MAX_COMMAND_LENGTH)];
+        assert_eq!(
+            batch_commands("mkdir -p", &long, |path| quote(path)).len(),
+            1
+        );
+        assert!(batch_commands("mkdir -p", &[], |path: &String| quote(path)).is_empty());
+    }
+
+    #[test]
+    fn quotes_shell_arguments() {
+        assert_eq!(quote("/sdcard/Download/a.png"), "/sdcard/Download/a.png");
+        assert_eq!(quote("key=value,1+2@3%"), "key=value,1+2@3%");
+        assert_eq!(quote(""), "''");
+        assert_eq!(quote("a  b\tc"), "'a  b\tc'");
+        assert_eq!(quote("it's"), "'it'\\''s'");
+        assert_eq!(quote("$HOME;rm *"), "'$HOME;rm *'");
+
+        let command = format!(
+            "cp {} {}",
+            quote("/sdcard/my  file.txt"),
+            quote("/data/local/tmp/it's")
+        );
+        assert_eq!(
+            command,
+            "cp '/sdcard/my  file.txt' '/data/local/tmp/it'\\''s'"
+        );
+    }
+
+    #[test]
+    fn batches_long_scripts() {
+        let script: Vec<String> = (0..500)
+            .map(|i| format!("sendevent /dev/input/event3 3 53 {};", i))
+            .collect();
+        let batches = batch_commands("", &script, |command| command.clone());
+        assert!(batches.len() > 1);
+        assert!(
+            batches
+                .iter()
+                .all(|(command, _)| command.len() <= MAX_COMMAND_LENGTH)
+        );
+        assert!(
+            batches[0]
+                .0
+                .starts_with("sendevent /dev/input/event3 3 53 0; sendevent")
+        );
+        let covered: usize = batches.iter().map(|(_, commands)| commands.len()).sum();
+        assert_eq!(covered, script.len());
+    }
 }
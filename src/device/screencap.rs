@@ -0,0 +1,152 @@
+use image::{DynamicImage, RgbaImage};
+
+use crate::error::{DroidError, Result};
+
+/// `PixelFormat` values reported in the `screencap` header.
+const RGBA_8888: u32 = 1;
+const RGBX_8888: u32 = 2;
+const RGB_565: u32 = 4;
+const BGRA_8888: u32 = 5;
+
+/// The shape of raw `screencap` output: a little-endian header of width,
+/// height, pixel format (and, since Android 9, color space), then the pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RawLayout {
+    pub width: u32,
+    pub height: u32,
+    pub format: u32,
+    pub header_len: usize,
+}
+
+impl RawLayout {
+    pub fn bytes_per_pixel(&self) -> usize {
+        if self.format == RGB_565 { 2 } else { 4 }
+    }
+
+    pub fn row_len(&self) -> usize {
+        self.width as usize * self.bytes_per_pixel()
+    }
+}
+
+/// Decodes a complete raw `screencap` dump.
+pub(crate) fn decode(data: &[u8]) -> Result<(RawLayout, DynamicImage)> {
+    if data.len() < 12 {
+        return Err(DroidError::AdbError(format!(
+            "Raw screencap output is too short ({} bytes)",
+            data.len()
+        )));
+    }
+    let field = |i: usize| u32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+    let mut layout = RawLayout {
+        width: field(0),
+        height: field(4),
+        format: field(8),
+        header_len: 0,
+    };
+
+    let pixels_len = layout.row_len() * layout.height as usize;
+    layout.header_len = data.len().checked_sub(pixels_len).ok_or_else(|| {
+        DroidError::AdbError(format!(
+            "Raw screencap output is truncated: {} bytes for {}x{}",
+            data.len(),
+            layout.width,
+            layout.height
+        ))
+    })?;
+    if !(12..=16).contains(&layout.header_len) {
+        return Err(DroidError::AdbError(format!(
+            "Unexpected raw screencap header of {} bytes",
+            layout.header_len
+        )));
+    }
+
+    let image = decode_pixels(&layout, layout.height, &data[layout.header_len..])?;
+    Ok((layout, image))
+}
+
+/// Decodes `rows` full-width rows of pixels laid out as described by `layout`.
+pub(crate) fn decode_pixels(layout: &RawLayout, rows: u32, pixels: &[u8]) -> Result<DynamicImage> {
+    let rgba: Vec<u8> = match layout.format {
+        RGBA_8888 => pixels.to_vec(),
+        RGBX_8888 => pixels
+            .chunks_exact(4)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        BGRA_8888 => pixels
+            .chunks_exact(4)
+            .flat_map(|p| [p[2], p[1], p[0], p[3]])
+            .collect(),
+        RGB_565 => pixels
+            .chunks_exact(2)
+            .flat_map(|p| {
+                let v = u16::from_le_bytes([p[0], p[1]]);
+                let r = ((v >> 11) & 0x1f) as u8;
+                let g = ((v >> 5) & 0x3f) as u8;
+                let b = (v & 0x1f) as u8;
+                [
+                    (r << 3) | (r >> 2),
+                    (g << 2) | (g >> 4),
+                    (b << 3) | (b >> 2),
+                    255,
+                ]
+            })
+            .collect(),
+        other => {
+            return Err(DroidError::AdbError(format!(
+                "Unsupported screencap pixel format {}",
+                other
+            )));
+        }
+    };
+
+    RgbaImage::from_raw(layout.width, rows, rgba)
+        .map(DynamicImage::ImageRgba8)
+        .ok_or_else(|| DroidError::AdbError("Raw screencap pixel data is truncated".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::GenericImageView;
+
+    fn dump(width: u32, height: u32, format: u32, header_len: usize, pixels: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for field in [width, height, format, 0] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+        data.truncate(header_len);
+        data.extend_from_slice(pixels);
+        data
+    }
+
+    #[test]
+    fn decodes_rgba_with_color_space_header() {
+        let pixels = [10, 20, 30, 255, 40, 50, 60, 128];
+        let (layout, image) = decode(&dump(2, 1, RGBA_8888, 16, &pixels)).unwrap();
+        assert_eq!(layout.header_len, 16);
+        assert_eq!((layout.width, layout.height), (2, 1));
+        assert_eq!(image.get_pixel(1, 0).0, [40, 50, 60, 128]);
+    }
+
+    #[test]
+    fn decodes_legacy_header_and_other_formats() {
+        let (layout, image) = decode(&dump(1, 1, BGRA_8888, 12, &[30, 20, 10, 255])).unwrap();
+        assert_eq!(layout.header_len, 12);
+        assert_eq!(image.get_pixel(0, 0).0, [10, 20, 30, 255]);
+
+        let (_, image) = decode(&dump(1, 1, RGBX_8888, 16, &[1, 2, 3, 0])).unwrap();
+        assert_eq!(image.get_pixel(0, 0).0, [1, 2, 3, 255]);
+
+        let white = 0xffffu16.to_le_bytes();
+        let (layout, image) = decode(&dump(1, 1, RGB_565, 16, &white)).unwrap();
+        assert_eq!(layout.bytes_per_pixel(), 2);
+        assert_eq!(image.get_pixel(0, 0).0, [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn rejects_truncated_output() {
+        assert!(decode(&[0; 8]).is_err());
+        assert!(decode(&dump(4, 4, RGBA_8888, 16, &[0; 8])).is_err());
+        assert!(decode(&dump(1, 1, 99, 16, &[0; 4])).is_err());
+    }
+}
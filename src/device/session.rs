@@ -0,0 +1,188 @@
+use std::io::{self, Read, Write};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use adb_client::{ADBDeviceExt, ADBServerDevice};
+
+use crate::error::{DroidError, Result};
+
+/// The result of a command run through a `ShellSession`.
+#[derive(Debug, Clone)]
+pub struct ShellOutput {
+    pub stdout: String,
+    pub exit_code: i32,
+}
+
+/// A long-lived interactive `shell:` connection that runs commands one after another.
+///
+/// Every command is followed by an `echo` of a unique completion marker and
+/// its exit status, so the session knows where one command's output ends
+/// without opening a new ADB service connection per command.
+pub(crate) struct ShellSession {
+    input: Sender<Vec<u8>>,
+    output: Receiver<Vec<u8>>,
+    buffer: Vec<u8>,
+    next_id: u64,
+    timeout: Duration,
+    /// Set once the connection is gone, as opposed to a command failing or hanging.
+    closed: bool,
+}
+
+impl ShellSession {
+    /// Opens a session on `device`, which is owned by a background thread from now on.
+    pub fn open(mut device: ADBServerDevice, timeout: Duration) -> Result<Self> {
+        let (input_tx, input_rx) = mpsc::channel::<Vec<u8>>();
+        let (output_tx, output_rx) = mpsc::channel::<Vec<u8>>();
+
+        thread::Builder::new()
+            .name("droid-shell".to_string())
+            .spawn(move || {
+                let mut reader = ChannelReader {
+                    rx: input_rx,
+                    pending: Vec::new(),
+                    pos: 0,
+                };
+                if let Err(e) = device.shell(&mut reader, Box::new(ChannelWriter(output_tx))) {
+                    log::warn!("Persistent shell session ended: {}", e);
+                }
+            })?;
+
+        let mut session = Self {
+            input: input_tx,
+            output: output_rx,
+            buffer: Vec::new(),
+            next_id: 0,
+            timeout,
+            closed: false,
+        };
+        // The interactive shell echoes input and prints a prompt; silence both
+        // so command output can be returned as-is.
+        session.run("stty -echo 2>/dev/null; PS1=''")?;
+        log::debug!("Persistent shell session opened");
+        Ok(session)
+    }
+
+    /// Runs `command` and waits for its completion marker.
+    pub fn run(&mut self, command: &str) -> Result<ShellOutput> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let marker = format!("__DROID_DONE_{}__ ", id);
+        // The quotes split the marker, so an echoed copy of this line never matches it.
+        let line = format!("{}\necho \"__DROID\"\"_DONE_{}__ $?\"\n", command, id);
+        if self.input.send(line.into_bytes()).is_err() {
+            self.closed = true;
+            return Err(DroidError::AdbError(
+                "Persistent shell session is closed".to_string(),
+            ));
+        }
+
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            if let Some(result) = self.take_result(marker.as_bytes())? {
+                return Ok(result);
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.output.recv_timeout(remaining) {
+                Ok(chunk) => self.buffer.extend_from_slice(&chunk),
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(DroidError::AdbError(format!(
+                        "Persistent shell did not complete '{}' within {:?}",
+                        command, self.timeout
+                    )));
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.closed = true;
+                    return Err(DroidError::AdbError(
+                        "Persistent shell session is closed".to_string(),
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Whether the session's connection is gone.
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Splits a completed command's output off the front of the buffer.
+    fn take_result(&mut self, marker: &[u8]) -> Result<Option<ShellOutput>> {
+        let Some(start) = self
+            .buffer
+            .windows(marker.len())
+            .position(|window| window == marker)
+        else {
+            return Ok(None);
+        };
+        let status_start = start + marker.len();
+        let Some(status_len) = self.buffer[status_start..].iter().position(|&b| b == b'\n') else {
+            return Ok(None);
+        };
+
+        let status = String::from_utf8_lossy(&self.buffer[status_start..status_start + status_len])
+            .trim()
+            .to_string();
+        let exit_code = status.parse::<i32>().map_err(|e| {
+            DroidError::AdbError(format!("Unexpected exit status '{}': {}", status, e))
+        })?;
+
+        let stdout = String::from_utf8_lossy(&self.buffer[..start])
+            .lines()
+            .map(|line| line.trim_end_matches('\r'))
+            .filter(|line| !line.contains("__DROID\"\"_DONE_"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.buffer.drain(..status_start + status_len + 1);
+
+        Ok(Some(ShellOutput { stdout, exit_code }))
+    }
+}
+
+impl Drop for ShellSession {
+    fn drop(&mut self) {
+        let _ = self.input.send(b"exit\n".to_vec());
+    }
+}
+
+/// Feeds the shell's stdin from a channel; reports end-of-file once the session is dropped.
+struct ChannelReader {
+    rx: Receiver<Vec<u8>>,
+    pending: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos >= self.pending.len() {
+            match self.rx.recv() {
+                Ok(data) => {
+                    self.pending = data;
+                    self.pos = 0;
+                }
+                Err(_) => return Ok(0),
+            }
+        }
+        let len = buf.len().min(self.pending.len() - self.pos);
+        buf[..len].copy_from_slice(&self.pending[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+/// Forwards the shell's stdout to the session.
+struct ChannelWriter(Sender<Vec<u8>>);
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .send(buf.to_vec())
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "shell session dropped"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
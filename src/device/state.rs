@@ -0,0 +1,153 @@
+use crate::models::Rotation;
+
+/// The activity in the foreground, i.e. the one the user interacts with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForegroundActivity {
+    pub package: String,
+    /// The fully qualified class name, e.g. `com.android.settings.Settings`.
+    pub activity: String,
+}
+
+impl ForegroundActivity {
+    /// Whether this is `activity` of `package`, where `activity` may be fully
+    /// qualified or relative to the package, e.g. `.Settings`.
+    pub fn is(&self, package: &str, activity: &str) -> bool {
+        self.package == package && self.activity == qualify(package, activity)
+    }
+}
+
+/// The state of the on-screen keyboard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyboardState {
+    /// Whether the keyboard is visible.
+    pub shown: bool,
+    /// The component of the active input method, e.g.
+    /// `com.google.android.inputmethod.latin/com.android.inputmethod.latin.LatinIME`.
+    pub input_method: Option<String>,
+}
+
+/// Finds the resumed activity in `dumpsys activity activities`.
+///
+/// ```text
+///   mResumedActivity: ActivityRecord{8e3f5a1 u0 com.android.settings/.Settings t25}
+///   topResumedActivity=ActivityRecord{8e3f5a1 u0 com.android.settings/.Settings t25}
+/// ```
+pub(crate) fn parse_foreground_activity(output: &str) -> Option<ForegroundActivity> {
+    output
+        .lines()
+        .filter(|line| line.contains("ResumedActivity"))
+        .find_map(|line| {
+            let record = line.split("ActivityRecord{").nth(1)?;
+            let component = record
+                .split_whitespace()
+                .find(|token| token.contains('/'))?
+                .trim_end_matches('}');
+            let (package, activity) = component.split_once('/')?;
+            Some(ForegroundActivity {
+                package: package.to_string(),
+                activity: qualify(package, activity),
+            })
+        })
+}
+
+/// Reads the keyboard state from `dumpsys input_method`.
+///
+/// ```text
+///   mCurMethodId=com.google.android.inputmethod.latin/com.android.inputmethod.latin.LatinIME
+///   mShowRequested=true mShowExplicitlyRequested=false mShowForced=false mInputShown=true
+/// ```
+pub(crate) fn parse_keyboard_state(output: &str) -> KeyboardState {
+    let mut state = KeyboardState {
+        shown: false,
+        input_method: None,
+    };
+    for token in output.split_whitespace() {
+        match token.split_once('=') {
+            Some(("mInputShown" | "mIsInputViewShown", value)) => state.shown |= value == "true",
+            Some(("mCurMethodId", value)) if value != "null" => {
+                state.input_method = Some(value.to_string());
+            }
+            _ => {}
+        }
+    }
+    state
+}
+
+/// Reads the display rotation from `dumpsys window displays`, which prints
+/// `mCurrentRotation=ROTATION_90` on Android 10+ and `mRotation=1` before,
+/// or from `dumpsys input`, which prints `SurfaceOrientation: 1`.
+pub(crate) fn parse_rotation(output: &str) -> Option<Rotation> {
+    output.lines().find_map(|line| {
+        let (_, value) = ["mCurrentRotation=", "mRotation=", "SurfaceOrientation: "]
+            .iter()
+            .find_map(|key| line.split_once(key))?;
+        let value = value.split_whitespace().next()?;
+        match value.strip_prefix("ROTATION_") {
+            Some(degrees) => Rotation::from_surface(degrees.parse::<u32>().ok()? / 90),
+            None => Rotation::from_surface(value.parse().ok()?),
+        }
+    })
+}
+
+/// Expands a class name relative to `package`, e.g. `.Settings`.
+fn qualify(package: &str, activity: &str) -> String {
+    match activity.strip_prefix('.') {
+        Some(relative) => format!("{}.{}", package, relative),
+        None => activity.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn foreground_activity_from_resumed_record() {
+        let output = "  mResumedActivity: ActivityRecord{8e3f5a1 u0 com.android.settings/.Settings t25}\n\
+                      \x20 topResumedActivity=ActivityRecord{8e3f5a1 u0 com.android.settings/.Settings t25}\n";
+        let activity = parse_foreground_activity(output).unwrap();
+        assert_eq!(activity.package, "com.android.settings");
+        assert_eq!(activity.activity, "com.android.settings.Settings");
+        assert!(activity.is("com.android.settings", ".Settings"));
+        assert!(activity.is("com.android.settings", "com.android.settings.Settings"));
+        assert!(!activity.is("com.android.settings", ".SubSettings"));
+        assert_eq!(
+            parse_foreground_activity("  mResumedActivity: null\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn keyboard_state() {
+        let output = "  mCurMethodId=com.google.android.inputmethod.latin/com.android.inputmethod.latin.LatinIME\n\
+                      \x20 mShowRequested=true mShowExplicitlyRequested=false mShowForced=false mInputShown=true\n";
+        let state = parse_keyboard_state(output);
+        assert!(state.shown);
+        assert_eq!(
+            state.input_method.as_deref(),
+            Some("com.google.android.inputmethod.latin/com.android.inputmethod.latin.LatinIME")
+        );
+
+        let state = parse_keyboard_state("  mCurMethodId=null\n  mInputShown=false\n");
+        assert!(!state.shown);
+        assert_eq!(state.input_method, None);
+    }
+
+    #[test]
+    fn rotation_formats() {
+        assert_eq!(
+            parse_rotation("    mCurrentRotation=ROTATION_90 mLastOrientation=-1\n"),
+            Some(Rotation::Rotation90)
+        );
+        assert_eq!(
+            parse_rotation("  mRotation=3 mAltOrientation=false\n"),
+            Some(Rotation::Rotation270)
+        );
+        assert_eq!(
+            parse_rotation("      SurfaceOrientation: 0\n"),
+            Some(Rotation::Rotation0)
+        );
+        assert_eq!(parse_rotation("mRotation=7\n"), None);
+        assert_eq!(parse_rotation(""), None);
+    }
+}
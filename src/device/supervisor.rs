@@ -0,0 +1,250 @@
+use std::io::{self, Read, Write};
+use std::net::{SocketAddrV4, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::error::Result;
+use crate::selector::ConnectionState;
+
+/// How often the tracker thread checks whether it should stop.
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+/// Pause before reconnecting to the ADB server after the stream broke.
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+struct Tracked {
+    /// The device's state, or `None` while it is not attached or the ADB
+    /// server cannot be reached.
+    state: Option<ConnectionState>,
+    /// Incremented every time the device stops being online.
+    losses: u64,
+}
+
+impl Tracked {
+    fn is_online(&self) -> bool {
+        self.state == Some(ConnectionState::Online)
+    }
+}
+
+struct Shared {
+    tracked: Mutex<Tracked>,
+    changed: Condvar,
+    stop: AtomicBool,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Tracked> {
+        self.tracked.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn update(&self, serial: &str, state: Option<ConnectionState>) {
+        let mut tracked = self.lock();
+        if tracked.state == state {
+            return;
+        }
+        let was_online = tracked.is_online();
+        tracked.state = state;
+        match &tracked.state {
+            Some(ConnectionState::Online) => log::info!("Device '{}' is online", serial),
+            Some(state) => log::warn!("Device '{}' went {}", serial, state),
+            None => log::warn!("Device '{}' disconnected", serial),
+        }
+        if was_online {
+            tracked.losses += 1;
+        }
+        self.changed.notify_all();
+    }
+}
+
+/// Watches the ADB server's `track-devices` stream for one serial.
+///
+/// The server pushes the full device list whenever it changes, so the
+/// supervisor learns about unplugged, rebooting or re-authorized devices
+/// without polling.
+pub(crate) struct Supervisor {
+    shared: Arc<Shared>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl Supervisor {
+    /// Starts tracking `serial`, which is assumed to be online right now.
+    pub fn start(adb_addr: SocketAddrV4, serial: &str) -> Result<Self> {
+        let shared = Arc::new(Shared {
+            tracked: Mutex::new(Tracked {
+                state: Some(ConnectionState::Online),
+                losses: 0,
+            }),
+            changed: Condvar::new(),
+            stop: AtomicBool::new(false),
+        });
+
+        let worker_shared = Arc::clone(&shared);
+        let serial = serial.to_string();
+        let worker = thread::Builder::new()
+            .name("droid-tracker".to_string())
+            .spawn(move || {
+                while !worker_shared.stop.load(Ordering::Relaxed) {
+                    if let Err(e) = track(adb_addr, &serial, &worker_shared) {
+                        log::debug!("Device tracking interrupted: {}", e);
+                        worker_shared.update(&serial, None);
+                        thread::sleep(RETRY_DELAY);
+                    }
+                }
+                log::debug!("Device tracker exited");
+            })?;
+
+        Ok(Self {
+            shared,
+            worker: Some(worker),
+        })
+    }
+
+    pub fn is_online(&self) -> bool {
+        self.shared.lock().is_online()
+    }
+
+    /// How many times the device has gone away since tracking started.
+    pub fn losses(&self) -> u64 {
+        self.shared.lock().losses
+    }
+
+    /// Waits up to `timeout` for the device to stop being online.
+    ///
+    /// Returns `true` if it did.
+    pub fn wait_lost(&self, timeout: Duration) -> bool {
+        self.wait_until(timeout, |tracked| !tracked.is_online())
+    }
+
+    /// Waits up to `timeout` for the device to be online.
+    ///
+    /// Returns `true` if it is.
+    pub fn wait_online(&self, timeout: Duration) -> bool {
+        self.wait_until(timeout, Tracked::is_online)
+    }
+
+    fn wait_until(&self, timeout: Duration, done: impl Fn(&Tracked) -> bool) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut tracked = self.shared.lock();
+        while !done(&tracked) {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return false;
+            }
+            tracked = self
+                .shared
+                .changed
+                .wait_timeout(tracked, remaining)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+        true
+    }
+}
+
+impl Drop for Supervisor {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::Relaxed);
+        // The worker wakes up at least every `STOP_CHECK_INTERVAL`.
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Follows `host:track-devices` until the connection breaks or tracking is stopped.
+fn track(adb_addr: SocketAddrV4, serial: &str, shared: &Shared) -> io::Result<()> {
+    let mut stream = TcpStream::connect(adb_addr)?;
+    let request = "host:track-devices";
+    stream.write_all(format!("{:04x}{}", request.len(), request).as_bytes())?;
+
+    let mut status = [0u8; 4];
+    stream.read_exact(&mut status)?;
+    if &status != b"OKAY" {
+        return Err(io::Error::other(format!(
+            "ADB server refused track-devices: {}",
+            String::from_utf8_lossy(&status)
+        )));
+    }
+
+    stream.set_read_timeout(Some(STOP_CHECK_INTERVAL))?;
+    while !shared.stop.load(Ordering::Relaxed) {
+        let Some(devices) = read_message(&mut stream)? else {
+            continue;
+        };
+        shared.update(serial, device_state(&devices, serial));
+    }
+    Ok(())
+}
+
+/// Reads one length-prefixed message, or `None` if nothing arrived before the read timeout.
+fn read_message(stream: &mut TcpStream) -> io::Result<Option<String>> {
+    let mut length = [0u8; 4];
+    match stream.read_exact(&mut length) {
+        Ok(()) => {}
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+            ) =>
+        {
+            return Ok(None);
+        }
+        Err(e) => return Err(e),
+    }
+    let length = std::str::from_utf8(&length)
+        .ok()
+        .and_then(|hex| usize::from_str_radix(hex, 16).ok())
+        .ok_or_else(|| io::Error::other("Malformed track-devices message length"))?;
+
+    // The body is sent together with its length, so it is already on its way.
+    let mut body = vec![0u8; length];
+    stream.read_exact(&mut body)?;
+    Ok(Some(String::from_utf8_lossy(&body).into_owned()))
+}
+
+/// Finds `serial` in a device list of `<serial>\t<state>` lines.
+fn device_state(devices: &str, serial: &str) -> Option<ConnectionState> {
+    devices.lines().find_map(|line| {
+        let (name, state) = line.split_once('\t')?;
+        (name == serial).then(|| match state.trim() {
+            "device" => ConnectionState::Online,
+            "offline" => ConnectionState::Offline,
+            "unauthorized" => ConnectionState::Unauthorized,
+            other => ConnectionState::Other(other.to_string()),
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_state_of_serial() {
+        let devices = "emulator-5554\tdevice\nR58M123ABC\tunauthorized\n0A1B2C\toffline\n";
+        assert_eq!(
+            device_state(devices, "emulator-5554"),
+            Some(ConnectionState::Online)
+        );
+        assert_eq!(
+            device_state(devices, "R58M123ABC"),
+            Some(ConnectionState::Unauthorized)
+        );
+        assert_eq!(
+            device_state(devices, "0A1B2C"),
+            Some(ConnectionState::Offline)
+        );
+    }
+
+    #[test]
+    fn unknown_states_and_missing_devices() {
+        assert_eq!(
+            device_state("emulator-5554\trecovery\n", "emulator-5554"),
+            Some(ConnectionState::Other("recovery".to_string()))
+        );
+        assert_eq!(device_state("emulator-5554\tdevice\n", "emulator"), None);
+        assert_eq!(device_state("", "emulator-5554"), None);
+    }
+}
@@ -0,0 +1,279 @@
+use std::fmt::Write;
+use std::time::Duration;
+
+use crate::common::point::Point;
+use crate::models::{MotionAction, MotionEvent, Rotation};
+
+const EV_SYN: u32 = 0;
+const EV_KEY: u32 = 1;
+const EV_ABS: u32 = 3;
+const BTN_TOUCH: u32 = 0x14a;
+const ABS_MT_POSITION_X: u32 = 0x35;
+const ABS_MT_POSITION_Y: u32 = 0x36;
+const ABS_MT_TRACKING_ID: u32 = 0x39;
+
+/// A multi-touch input device that can be driven with `sendevent`.
+///
+/// Writing kernel input events directly skips the `input` tool, which starts
+/// a new app process for every command.
+#[derive(Debug, Clone)]
+pub(crate) struct Touchscreen {
+    pub path: String,
+    x_range: (i64, i64),
+    y_range: (i64, i64),
+}
+
+impl Touchscreen {
+    /// Finds the first device reporting multi-touch positions in `getevent -p` output.
+    ///
+    /// ```text
+    /// add device 2: /dev/input/event3
+    ///   name:     "touchscreen"
+    ///   events:
+    ///     ABS (0003): 0035  : value 0, min 0, max 1079, fuzz 0, flat 0, resolution 0
+    ///                 0036  : value 0, min 0, max 2399, fuzz 0, flat 0, resolution 0
+    /// ```
+    pub fn parse(output: &str) -> Option<Self> {
+        let mut path: Option<&str> = None;
+        let mut x_range = None;
+        let mut y_range = None;
+
+        for line in output.lines() {
+            if let Some(rest) = line.trim().strip_prefix("add device") {
+                if let (Some(path), Some(x_range), Some(y_range)) = (path, x_range, y_range) {
+                    return Some(Self::new(path, x_range, y_range));
+                }
+                path = rest.split_once(':').map(|(_, p)| p.trim());
+                x_range = None;
+                y_range = None;
+                continue;
+            }
+
+            let line = line.trim();
+            let line = line
+                .strip_prefix("ABS (0003):")
+                .map(str::trim)
+                .unwrap_or(line);
+            let Some((code, values)) = line.split_once(':') else {
+                continue;
+            };
+            match u32::from_str_radix(code.trim(), 16) {
+                Ok(ABS_MT_POSITION_X) => x_range = parse_range(values),
+                Ok(ABS_MT_POSITION_Y) => y_range = parse_range(values),
+                _ => {}
+            }
+        }
+
+        match (path, x_range, y_range) {
+            (Some(path), Some(x_range), Some(y_range)) => Some(Self::new(path, x_range, y_range)),
+            _ => None,
+        }
+    }
+
+    fn new(path: &str, x_range: (i64, i64), y_range: (i64, i64)) -> Self {
+        Self {
+            path: path.to_string(),
+            x_range,
+            y_range,
+        }
+    }
+
+    /// Builds shell commands replaying `events` on this device, one per event.
+    ///
+    /// The event points are in the current orientation, which is `rotation`
+    /// away from the natural one. `screen` is the display size in the natural
+    /// orientation, the orientation the touch panel's axes are fixed to.
+    pub fn script(
+        &self,
+        events: &[MotionEvent],
+        screen: (u32, u32),
+        rotation: Rotation,
+    ) -> Vec<String> {
+        let mut commands = Vec::with_capacity(events.len());
+        for event in events {
+            let mut script = String::new();
+            let point = to_natural(event.point, rotation, screen);
+            let x = scale(point.x, screen.0, self.x_range);
+            let y = scale(point.y, screen.1, self.y_range);
+            match event.action {
+                MotionAction::Down => {
+                    self.push(&mut script, EV_ABS, ABS_MT_TRACKING_ID, 0);
+                    self.push(&mut script, EV_ABS, ABS_MT_POSITION_X, x);
+                    self.push(&mut script, EV_ABS, ABS_MT_POSITION_Y, y);
+                    self.push(&mut script, EV_KEY, BTN_TOUCH, 1);
+                }
+                MotionAction::Move => {
+                    self.push(&mut script, EV_ABS, ABS_MT_POSITION_X, x);
+                    self.push(&mut script, EV_ABS, ABS_MT_POSITION_Y, y);
+                }
+                MotionAction::Up => {
+                    self.push(&mut script, EV_ABS, ABS_MT_TRACKING_ID, -1);
+                    self.push(&mut script, EV_KEY, BTN_TOUCH, 0);
+                }
+            }
+            self.push(&mut script, EV_SYN, 0, 0);
+            if !event.delay.is_zero() {
+                let _ = write!(script, "sleep {:.3};", event.delay.as_secs_f32());
+            }
+            commands.push(script);
+        }
+        commands
+    }
+
+    fn push(&self, script: &mut String, kind: u32, code: u32, value: i64) {
+        let _ = write!(
+            script,
+            "sendevent {} {} {} {};",
+            self.path, kind, code, value
+        );
+    }
+}
+
+/// Expands a straight swipe into motion events, one move roughly every 20ms.
+pub(crate) fn swipe_events(start: Point, end: Point, duration: Duration) -> Vec<MotionEvent> {
+    let steps = (duration.as_millis() / 20).clamp(1, 50) as u32;
+    let delay = duration / steps;
+
+    let mut events = vec![MotionEvent::new(MotionAction::Down, start, Duration::ZERO)];
+    for step in 1..=steps {
+        let t = step as f32 / steps as f32;
+        let x = start.x as f32 + (end.x as f32 - start.x as f32) * t;
+        let y = start.y as f32 + (end.y as f32 - start.y as f32) * t;
+        events.push(MotionEvent::new(
+            MotionAction::Move,
+            Point::new(x.round() as u32, y.round() as u32),
+            delay,
+        ));
+    }
+    events.push(MotionEvent::new(MotionAction::Up, end, Duration::ZERO));
+    events
+}
+
+fn parse_range(values: &str) -> Option<(i64, i64)> {
+    let mut min = None;
+    let mut max = None;
+    for part in values.split(',') {
+        let part = part.trim();
+        if let Some(value) = part.strip_prefix("min ") {
+            min = value.trim().parse().ok();
+        } else if let Some(value) = part.strip_prefix("max ") {
+            max = value.trim().parse().ok();
+        }
+    }
+    Some((min?, max?))
+}
+
+/// Maps `point` from the current orientation, `rotation` clockwise from the
+/// natural one, back to the natural orientation of a `natural` sized display.
+fn to_natural(point: Point, rotation: Rotation, (width, height): (u32, u32)) -> Point {
+    let max_x = width.saturating_sub(1);
+    let max_y = height.saturating_sub(1);
+    match rotation {
+        Rotation::Rotation0 => point,
+        Rotation::Rotation90 => Point::new(max_x.saturating_sub(point.y), point.x),
+        Rotation::Rotation180 => {
+            Point::new(max_x.saturating_sub(point.x), max_y.saturating_sub(point.y))
+        }
+        Rotation::Rotation270 => Point::new(point.y, max_y.saturating_sub(point.x)),
+    }
+}
+
+fn scale(value: u32, screen_extent: u32, (min, max): (i64, i64)) -> i64 {
+    if screen_extent <= 1 {
+        return min;
+    }
+    min + i64::from(value) * (max - min) / i64::from(screen_extent - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GETEVENT: &str = "\
+add device 1: /dev/input/event1
+  name:     \"gpio-keys\"
+  events:
+    KEY (0001): 0072  0073  0074
+add device 2: /dev/input/event3
+  name:     \"touchscreen\"
+  events:
+    ABS (0003): 0035  : value 0, min 0, max 1079, fuzz 0, flat 0, resolution 0
+                0036  : value 0, min 0, max 2399, fuzz 0, flat 0, resolution 0
+                0039  : value 0, min 0, max 65535, fuzz 0, flat 0, resolution 0
+";
+
+    #[test]
+    fn finds_multi_touch_device() {
+        let touchscreen = Touchscreen::parse(GETEVENT).unwrap();
+        assert_eq!(touchscreen.path, "/dev/input/event3");
+        assert_eq!(touchscreen.x_range, (0, 1079));
+        assert_eq!(touchscreen.y_range, (0, 2399));
+    }
+
+    #[test]
+    fn ignores_devices_without_positions() {
+        let output = "add device 1: /dev/input/event1\n  name:     \"gpio-keys\"\n";
+        assert!(Touchscreen::parse(output).is_none());
+    }
+
+    #[test]
+    fn scales_to_panel_range() {
+        let touchscreen = Touchscreen::new("/dev/input/event3", (0, 4095), (0, 4095));
+        let events = [MotionEvent::new(
+            MotionAction::Down,
+            Point::new(1079, 0),
+            Duration::ZERO,
+        )];
+        let script = touchscreen.script(&events, (1080, 2400), Rotation::Rotation0);
+        assert_eq!(script.len(), 1);
+        assert!(script[0].contains("sendevent /dev/input/event3 3 53 4095;"));
+        assert!(script[0].contains("sendevent /dev/input/event3 3 54 0;"));
+    }
+
+    // A 1080x2400 portrait display; the current-orientation point is 100
+    // pixels from the left and 10 from the top of the screen as seen.
+    const NATURAL: (u32, u32) = (1080, 2400);
+
+    #[test]
+    fn natural_orientation_is_unchanged() {
+        let point = to_natural(Point::new(100, 10), Rotation::Rotation0, NATURAL);
+        assert_eq!(point, Point::new(100, 10));
+    }
+
+    #[test]
+    fn maps_rotation_90() {
+        // The natural right edge is the top of the landscape screen.
+        let point = to_natural(Point::new(100, 10), Rotation::Rotation90, NATURAL);
+        assert_eq!(point, Point::new(1069, 100));
+        let corner = to_natural(Point::new(2399, 1079), Rotation::Rotation90, NATURAL);
+        assert_eq!(corner, Point::new(0, 2399));
+    }
+
+    #[test]
+    fn maps_rotation_180() {
+        let point = to_natural(Point::new(100, 10), Rotation::Rotation180, NATURAL);
+        assert_eq!(point, Point::new(979, 2389));
+    }
+
+    #[test]
+    fn maps_rotation_270() {
+        // The natural left edge is the top of the landscape screen.
+        let point = to_natural(Point::new(100, 10), Rotation::Rotation270, NATURAL);
+        assert_eq!(point, Point::new(10, 2299));
+        let corner = to_natural(Point::new(2399, 1079), Rotation::Rotation270, NATURAL);
+        assert_eq!(corner, Point::new(1079, 0));
+    }
+
+    #[test]
+    fn scales_rotated_points() {
+        let touchscreen = Touchscreen::new("/dev/input/event3", (0, 1079), (0, 2399));
+        let events = [MotionEvent::new(
+            MotionAction::Down,
+            Point::new(0, 0),
+            Duration::ZERO,
+        )];
+        let script = touchscreen.script(&events, NATURAL, Rotation::Rotation90);
+        assert!(script[0].contains("sendevent /dev/input/event3 3 53 1079;"));
+        assert!(script[0].contains("sendevent /dev/input/event3 3 54 0;"));
+    }
+}
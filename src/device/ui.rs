@@ -0,0 +1,94 @@
+use crate::common::rect::Rect;
+
+/// An element of the on-screen view hierarchy, as dumped by `uiautomator`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UiNode {
+    /// The view id, e.g. `com.android.settings:id/search_bar`; empty if the
+    /// view has none.
+    pub resource_id: String,
+    pub text: String,
+    /// The package whose window contains the view.
+    pub package: String,
+    /// The view's position on the screen, in pixels.
+    pub bounds: Rect,
+}
+
+/// Parses the nodes of a `uiautomator dump` XML file, in document order.
+///
+/// ```text
+/// <node index="0" text="Allow" resource-id="com.android.permissioncontroller:id/permission_allow_button"
+///       class="android.widget.Button" package="com.android.permissioncontroller" ... bounds="[84,1600][996,1732]">
+/// ```
+pub(crate) fn parse_hierarchy(xml: &str) -> Vec<UiNode> {
+    xml.split("<node ")
+        .skip(1)
+        .filter_map(|element| {
+            // Attributes end at the first `>`, which never appears unescaped in values.
+            let element = element.split('>').next()?;
+            Some(UiNode {
+                resource_id: attribute(element, "resource-id").unwrap_or_default(),
+                text: attribute(element, "text").unwrap_or_default(),
+                package: attribute(element, "package").unwrap_or_default(),
+                bounds: parse_bounds(&attribute(element, "bounds")?)?,
+            })
+        })
+        .collect()
+}
+
+fn attribute(element: &str, name: &str) -> Option<String> {
+    let start = element.find(&format!(" {}=\"", name))? + name.len() + 3;
+    let len = element[start..].find('"')?;
+    Some(unescape(&element[start..start + len]))
+}
+
+/// Parses `[left,top][right,bottom]`.
+fn parse_bounds(bounds: &str) -> Option<Rect> {
+    let mut numbers = bounds
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse::<u32>().ok());
+    let (left, top) = (numbers.next()??, numbers.next()??);
+    let (right, bottom) = (numbers.next()??, numbers.next()??);
+    Some(Rect::new(
+        left,
+        top,
+        right.saturating_sub(left),
+        bottom.saturating_sub(top),
+    ))
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#10;", "\n")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_nodes_in_document_order() {
+        let xml = r#"<?xml version='1.0' encoding='UTF-8' standalone='yes' ?><hierarchy rotation="0">
+<node index="0" text="Allow &quot;Camera&quot;?" resource-id="com.android.permissioncontroller:id/permission_message" class="android.widget.TextView" package="com.android.permissioncontroller" bounds="[84,1200][996,1320]">
+<node index="1" text="Allow" resource-id="com.android.permissioncontroller:id/permission_allow_button" class="android.widget.Button" package="com.android.permissioncontroller" bounds="[84,1600][996,1732]" /></node></hierarchy>"#;
+        let nodes = parse_hierarchy(xml);
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[0].text, "Allow \"Camera\"?");
+        assert_eq!(
+            nodes[1].resource_id,
+            "com.android.permissioncontroller:id/permission_allow_button"
+        );
+        assert_eq!(nodes[1].package, "com.android.permissioncontroller");
+        assert_eq!(nodes[1].bounds, Rect::new(84, 1600, 912, 132));
+    }
+
+    #[test]
+    fn skips_nodes_without_bounds() {
+        assert!(parse_hierarchy(r#"<node index="0" text="x" />"#).is_empty());
+    }
+}
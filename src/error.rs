@@ -1,7 +1,10 @@
+use std::fmt;
 use std::path::PathBuf;
 use std::time::Duration;
 use thiserror::Error;
 
+use crate::crash::CrashReport;
+
 #[derive(Error, Debug)]
 pub enum DroidError {
     #[error("ADB command failed: {0}")]
@@ -10,6 +13,15 @@ pub enum DroidError {
     #[error("Device not found or specified serial is invalid")]
     DeviceNotFound,
 
+    #[error("Device '{serial}' disconnected and did not come back within {waited:?}")]
+    DeviceDisconnected { serial: String, waited: Duration },
+
+    #[error("No device matches [{selector}]. Available devices: {available:?}")]
+    NoMatchingDevice {
+        selector: String,
+        available: Vec<String>,
+    },
+
     #[error("Image processing error: {0}")]
     ImageError(#[from] image::ImageError),
 
@@ -25,14 +37,203 @@ pub enum DroidError {
     #[error("Invalid target for operation: {0}")]
     InvalidTarget(String),
 
+    #[error(
+        "Reached the end of the scrollable area after {swipes} swipes without finding {target}"
+    )]
+    EndOfScroll { target: String, swipes: u32 },
+
+    #[error("Could not find {target} after scrolling {swipes} times")]
+    ScrollLimitReached { target: String, swipes: u32 },
+
+    #[error("{action} did not have the expected effect ({expectation}) after {attempts} attempts")]
+    ExpectationFailed {
+        action: String,
+        expectation: String,
+        attempts: u32,
+    },
+
+    #[error("{expected} was not in the foreground after {timeout:?}; last was {last:?}")]
+    ActivityTimeout {
+        expected: String,
+        last: Option<String>,
+        timeout: Duration,
+    },
+
+    #[error("Permission dialog has no button to {choice}; found {buttons:?}")]
+    PermissionChoiceUnavailable {
+        choice: String,
+        buttons: Vec<String>,
+    },
+
+    #[error("Script panicked on device '{serial}': {message}")]
+    ScriptPanicked { serial: String, message: String },
+
+    #[error("Mirrored {action} diverged: failed on {failed:?}")]
+    MirrorDiverged { action: String, failed: Vec<String> },
+
     #[error("GPU/OpenCL error: {0}")]
     GpuError(String),
 
     #[error("App package not found or has no launcher activity: {0}")]
     PackageNotFound(String),
 
+    #[error("Failed to {action} '{package}' (exit status {exit_code}): {output}")]
+    AppCommandFailed {
+        package: String,
+        action: String,
+        exit_code: i32,
+        output: String,
+    },
+
+    #[error("No APK to install")]
+    NoApkToInstall,
+
+    #[error("Failed to install {apk:?}: {reason} ({output})")]
+    InstallFailed {
+        apk: PathBuf,
+        reason: InstallFailure,
+        output: String,
+    },
+
     #[error("Failed to launch app '{package}': {output}")]
     AppLaunchFailed { package: String, output: String },
+
+    #[error("App crashed: {0}")]
+    AppCrashed(Box<CrashReport>),
+
+    #[error("Invalid log pattern '{pattern}': {message}")]
+    InvalidLogPattern { pattern: String, message: String },
+
+    #[error("'am {command} {intent}' failed: {output}")]
+    IntentFailed {
+        command: String,
+        intent: String,
+        output: String,
+    },
+
+    #[error("Failed to {action} '{path}' on the device: {output}")]
+    FileOperationFailed {
+        action: String,
+        path: String,
+        output: String,
+    },
+}
+
+/// Why the package manager rejected an APK, parsed from its failure code,
+/// e.g. `INSTALL_FAILED_VERSION_DOWNGRADE`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstallFailure {
+    /// The app is installed already; install with `reinstall(true)`.
+    AlreadyExists,
+    /// The installed version is newer; install with `downgrade(true)`.
+    VersionDowngrade,
+    /// The APK is signed with a different key than the installed app.
+    UpdateIncompatible,
+    InsufficientStorage,
+    /// The device's Android version is older than the app's `minSdkVersion`.
+    OlderSdk,
+    /// The APK has no native code for the device's ABIs.
+    NoMatchingAbis,
+    /// The APK is marked `testOnly`; install with `allow_test(true)`.
+    TestOnly,
+    /// The file is not a valid APK, e.g. corrupt or unsigned.
+    InvalidApk,
+    /// Any other failure code.
+    Other(String),
+}
+
+impl InstallFailure {
+    pub fn from_code(code: &str) -> Self {
+        match code {
+            "INSTALL_FAILED_ALREADY_EXISTS" => InstallFailure::AlreadyExists,
+            "INSTALL_FAILED_VERSION_DOWNGRADE" => InstallFailure::VersionDowngrade,
+            "INSTALL_FAILED_UPDATE_INCOMPATIBLE" => InstallFailure::UpdateIncompatible,
+            "INSTALL_FAILED_INSUFFICIENT_STORAGE" => InstallFailure::InsufficientStorage,
+            "INSTALL_FAILED_OLDER_SDK" => InstallFailure::OlderSdk,
+            "INSTALL_FAILED_NO_MATCHING_ABIS" => InstallFailure::NoMatchingAbis,
+            "INSTALL_FAILED_TEST_ONLY" => InstallFailure::TestOnly,
+            code if code.starts_with("INSTALL_PARSE_FAILED")
+                || code == "INSTALL_FAILED_INVALID_APK" =>
+            {
+                InstallFailure::InvalidApk
+            }
+            code => InstallFailure::Other(code.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for InstallFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstallFailure::AlreadyExists => write!(f, "app is already installed"),
+            InstallFailure::VersionDowngrade => write!(f, "installed version is newer"),
+            InstallFailure::UpdateIncompatible => {
+                write!(f, "signature does not match installed app")
+            }
+            InstallFailure::InsufficientStorage => write!(f, "not enough storage"),
+            InstallFailure::OlderSdk => write!(f, "device SDK is too old"),
+            InstallFailure::NoMatchingAbis => write!(f, "no native code for device ABIs"),
+            InstallFailure::TestOnly => write!(f, "test-only APK"),
+            InstallFailure::InvalidApk => write!(f, "invalid APK"),
+            InstallFailure::Other(code) => write!(f, "{}", code),
+        }
+    }
+}
+
+/// A coarse category of `DroidError`, e.g. for choosing which failures a
+/// `RetryPolicy` retries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// `DroidError::AdbError`
+    Adb,
+    /// `DroidError::IoError`
+    Io,
+    /// `DroidError::DeviceDisconnected`
+    Disconnected,
+    /// `DroidError::ImageNotFound`
+    ImageNotFound,
+    /// `DroidError::Timeout`
+    Timeout,
+    /// Any other error, which is usually not transient.
+    Other,
+}
+
+impl DroidError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            DroidError::AdbError(_) => ErrorKind::Adb,
+            DroidError::IoError(_) => ErrorKind::Io,
+            DroidError::DeviceDisconnected { .. } => ErrorKind::Disconnected,
+            DroidError::ImageNotFound(_) => ErrorKind::ImageNotFound,
+            DroidError::Timeout(_) | DroidError::ActivityTimeout { .. } => ErrorKind::Timeout,
+            _ => ErrorKind::Other,
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, DroidError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn install_failure_codes() {
+        assert_eq!(
+            InstallFailure::from_code("INSTALL_FAILED_VERSION_DOWNGRADE"),
+            InstallFailure::VersionDowngrade
+        );
+        assert_eq!(
+            InstallFailure::from_code("INSTALL_PARSE_FAILED_NO_CERTIFICATES"),
+            InstallFailure::InvalidApk
+        );
+        assert_eq!(
+            InstallFailure::from_code("INSTALL_FAILED_INVALID_APK"),
+            InstallFailure::InvalidApk
+        );
+        assert_eq!(
+            InstallFailure::from_code("INSTALL_FAILED_DEXOPT"),
+            InstallFailure::Other("INSTALL_FAILED_DEXOPT".to_string())
+        );
+    }
+}
@@ -0,0 +1,429 @@
+use std::fmt::Write;
+use std::time::Duration;
+
+use crate::device::quote;
+use crate::error::{DroidError, Result};
+
+/// A typed value attached to an intent.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Extra {
+    String(String),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Bool(bool),
+    Uri(String),
+    StringArray(Vec<String>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+    FloatArray(Vec<f32>),
+}
+
+impl Extra {
+    /// Appends the `am` option for this extra, e.g. `--ei count 3`.
+    fn write_arg(&self, args: &mut String, key: &str) {
+        fn join<T: ToString>(values: &[T]) -> String {
+            values
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(",")
+        }
+
+        let key = quote(key);
+        let _ = match self {
+            Extra::String(value) => write!(args, " --es {} {}", key, quote(value)),
+            Extra::Int(value) => write!(args, " --ei {} {}", key, value),
+            Extra::Long(value) => write!(args, " --el {} {}", key, value),
+            Extra::Float(value) => write!(args, " --ef {} {}", key, value),
+            Extra::Bool(value) => write!(args, " --ez {} {}", key, value),
+            Extra::Uri(value) => write!(args, " --eu {} {}", key, quote(value)),
+            Extra::StringArray(values) => {
+                // `am` splits string arrays on unescaped commas.
+                let escaped: Vec<String> = values.iter().map(|v| v.replace(',', "\\,")).collect();
+                write!(args, " --esa {} {}", key, quote(&escaped.join(",")))
+            }
+            Extra::IntArray(values) => write!(args, " --eia {} {}", key, join(values)),
+            Extra::LongArray(values) => write!(args, " --ela {} {}", key, join(values)),
+            Extra::FloatArray(values) => write!(args, " --efa {} {}", key, join(values)),
+        };
+    }
+}
+
+/// An Android intent, sent with `Droid::start_activity()`, `Droid::broadcast()`
+/// or `Droid::start_service()`.
+///
+/// Values are quoted for the device shell.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rust_droid::{Droid, DroidConfig, intent::Intent};
+/// let mut droid = Droid::new(DroidConfig::default())?;
+/// let intent = Intent::new()
+///     .action("android.intent.action.VIEW")
+///     .data("https://example.com/products/42")
+///     .component("com.example.shop/.ProductActivity")
+///     .extra_bool("from_test", true)
+///     .flags(Intent::FLAG_ACTIVITY_CLEAR_TOP);
+/// let launch = droid.start_activity(&intent)?;
+/// println!("{:?} launch took {:?}", launch.launch_state, launch.total_time);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Intent {
+    pub action: Option<String>,
+    pub data: Option<String>,
+    pub mime_type: Option<String>,
+    pub categories: Vec<String>,
+    /// `package/class`, where a class starting with `.` is relative to the package.
+    pub component: Option<String>,
+    /// Restricts the intent to one package when no component is given.
+    pub package: Option<String>,
+    pub extras: Vec<(String, Extra)>,
+    pub flags: u32,
+}
+
+impl Intent {
+    pub const FLAG_ACTIVITY_NEW_TASK: u32 = 0x1000_0000;
+    pub const FLAG_ACTIVITY_SINGLE_TOP: u32 = 0x2000_0000;
+    pub const FLAG_ACTIVITY_CLEAR_TOP: u32 = 0x0400_0000;
+    pub const FLAG_ACTIVITY_CLEAR_TASK: u32 = 0x0000_8000;
+    pub const FLAG_ACTIVITY_NO_ANIMATION: u32 = 0x0001_0000;
+    pub const FLAG_INCLUDE_STOPPED_PACKAGES: u32 = 0x0000_0020;
+    pub const FLAG_RECEIVER_FOREGROUND: u32 = 0x1000_0000;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn action(mut self, action: &str) -> Self {
+        self.action = Some(action.to_string());
+        self
+    }
+
+    /// Sets the data URI, e.g. a deep link.
+    pub fn data(mut self, uri: &str) -> Self {
+        self.data = Some(uri.to_string());
+        self
+    }
+
+    pub fn mime_type(mut self, mime_type: &str) -> Self {
+        self.mime_type = Some(mime_type.to_string());
+        self
+    }
+
+    /// Adds a category, e.g. `android.intent.category.BROWSABLE`.
+    pub fn category(mut self, category: &str) -> Self {
+        self.categories.push(category.to_string());
+        self
+    }
+
+    /// Targets a component, e.g. `com.example/.MainActivity`.
+    pub fn component(mut self, component: &str) -> Self {
+        self.component = Some(component.to_string());
+        self
+    }
+
+    pub fn package(mut self, package: &str) -> Self {
+        self.package = Some(package.to_string());
+        self
+    }
+
+    /// Adds an extra of any type.
+    pub fn extra(mut self, key: &str, value: Extra) -> Self {
+        self.extras.push((key.to_string(), value));
+        self
+    }
+
+    pub fn extra_string(self, key: &str, value: &str) -> Self {
+        self.extra(key, Extra::String(value.to_string()))
+    }
+
+    pub fn extra_int(self, key: &str, value: i32) -> Self {
+        self.extra(key, Extra::Int(value))
+    }
+
+    pub fn extra_bool(self, key: &str, value: bool) -> Self {
+        self.extra(key, Extra::Bool(value))
+    }
+
+    /// Adds `flags` to the intent flags; see the `FLAG_*` constants.
+    pub fn flags(mut self, flags: u32) -> Self {
+        self.flags |= flags;
+        self
+    }
+
+    /// The package the intent is sent to, from its component or package.
+    pub fn target_package(&self) -> Option<&str> {
+        match &self.component {
+            Some(component) => component.split_once('/').map(|(package, _)| package),
+            None => self.package.as_deref(),
+        }
+    }
+
+    /// Builds the intent arguments of an `am` command.
+    pub fn to_args(&self) -> String {
+        let mut args = String::new();
+        if let Some(action) = &self.action {
+            let _ = write!(args, " -a {}", quote(action));
+        }
+        if let Some(data) = &self.data {
+            let _ = write!(args, " -d {}", quote(data));
+        }
+        if let Some(mime_type) = &self.mime_type {
+            let _ = write!(args, " -t {}", quote(mime_type));
+        }
+        for category in &self.categories {
+            let _ = write!(args, " -c {}", quote(category));
+        }
+        for (key, extra) in &self.extras {
+            extra.write_arg(&mut args, key);
+        }
+        if self.flags != 0 {
+            let _ = write!(args, " -f {:#x}", self.flags);
+        }
+        // The component or package goes last, as a bare argument.
+        if let Some(component) = &self.component {
+            let _ = write!(args, " -n {}", quote(component));
+        } else if let Some(package) = &self.package {
+            let _ = write!(args, " {}", quote(package));
+        }
+        args.trim_start().to_string()
+    }
+}
+
+/// How `am start -W` reports an activity launch.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LaunchResult {
+    /// `ok`, or e.g. `timeout` if the activity did not finish drawing in time.
+    pub status: String,
+    /// `COLD`, `WARM`, `HOT` or `UNKNOWN` (Android 10+).
+    pub launch_state: Option<String>,
+    /// The activity that ended up on top.
+    pub activity: Option<String>,
+    /// Time from the start request until the activity drew its first frame.
+    pub total_time: Option<Duration>,
+    /// Like `total_time`, plus the time `am` spent waiting for the system.
+    pub wait_time: Option<Duration>,
+    /// Launch time of the last activity of the launch; older Android versions only.
+    pub this_time: Option<Duration>,
+    /// Whether an existing task was brought to the front instead of starting
+    /// the activity.
+    pub brought_to_front: bool,
+}
+
+impl LaunchResult {
+    /// Parses the output of `am start -W`.
+    ///
+    /// ```text
+    /// Starting: Intent { cmp=com.android.settings/.Settings }
+    /// Status: ok
+    /// LaunchState: COLD
+    /// Activity: com.android.settings/.Settings
+    /// TotalTime: 523
+    /// WaitTime: 527
+    /// Complete
+    /// ```
+    pub(crate) fn parse(output: &str) -> Self {
+        let millis = |value: &str| value.parse().ok().map(Duration::from_millis);
+        let mut result = Self::default();
+        for line in output.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim() {
+                "Status" => result.status = value.to_string(),
+                "LaunchState" => result.launch_state = Some(value.to_string()),
+                "Activity" => result.activity = Some(value.to_string()),
+                "TotalTime" => result.total_time = millis(value),
+                "WaitTime" => result.wait_time = millis(value),
+                "ThisTime" => result.this_time = millis(value),
+                "Warning" => {
+                    result.brought_to_front |= value.contains("brought to the front");
+                }
+                _ => {}
+            }
+        }
+        result
+    }
+}
+
+/// How `am broadcast` reports a delivered broadcast.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BroadcastResult {
+    /// The result code set by the receivers, `0` if none set one.
+    pub code: i32,
+    /// The result data set by the receivers.
+    pub data: Option<String>,
+}
+
+/// Checks the output of `am start -W`.
+pub(crate) fn start_activity(output: &str, intent: &Intent) -> Result<LaunchResult> {
+    if let Some(error) = find_error(output) {
+        return Err(DroidError::IntentFailed {
+            command: "start".to_string(),
+            intent: intent.to_args(),
+            output: error,
+        });
+    }
+    Ok(LaunchResult::parse(output))
+}
+
+/// Parses the output of `am broadcast`.
+///
+/// ```text
+/// Broadcasting: Intent { act=com.example.RESET flg=0x400000 }
+/// Broadcast completed: result=0, data="done"
+/// ```
+pub(crate) fn broadcast(output: &str, intent: &Intent) -> Result<BroadcastResult> {
+    let completed = output
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Broadcast completed:"));
+    let Some(completed) = completed else {
+        return Err(DroidError::IntentFailed {
+            command: "broadcast".to_string(),
+            intent: intent.to_args(),
+            output: output.trim().to_string(),
+        });
+    };
+    let code = completed
+        .split_once("result=")
+        .and_then(|(_, rest)| rest.split(',').next())
+        .and_then(|code| code.trim().parse().ok())
+        .unwrap_or(0);
+    let data = completed
+        .split_once("data=\"")
+        .and_then(|(_, rest)| rest.rsplit_once('"'))
+        .map(|(data, _)| data.to_string());
+    Ok(BroadcastResult { code, data })
+}
+
+/// Checks the output of `am startservice`.
+pub(crate) fn start_service(output: &str, intent: &Intent) -> Result<()> {
+    match find_error(output) {
+        Some(error) => Err(DroidError::IntentFailed {
+            command: "startservice".to_string(),
+            intent: intent.to_args(),
+            output: error,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Returns the first error line `am` printed, e.g.
+/// `Error: Activity class {com.example/.Missing} does not exist.`
+///
+/// A message line is preferred over a bare `Error type 3`.
+fn find_error(output: &str) -> Option<String> {
+    let lines = || output.lines().map(str::trim);
+    lines()
+        .find(|line| line.starts_with("Error:") || line.starts_with("Exception"))
+        .or_else(|| lines().find(|line| line.starts_with("Error")))
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_launch_result() {
+        let output = "\
+Starting: Intent { cmp=com.android.settings/.Settings }
+Status: ok
+LaunchState: COLD
+Activity: com.android.settings/.Settings
+TotalTime: 523
+WaitTime: 527
+Complete
+";
+        let result = LaunchResult::parse(output);
+        assert_eq!(result.status, "ok");
+        assert_eq!(result.launch_state.as_deref(), Some("COLD"));
+        assert_eq!(
+            result.activity.as_deref(),
+            Some("com.android.settings/.Settings")
+        );
+        assert_eq!(result.total_time, Some(Duration::from_millis(523)));
+        assert_eq!(result.wait_time, Some(Duration::from_millis(527)));
+        assert_eq!(result.this_time, None);
+        assert!(!result.brought_to_front);
+    }
+
+    #[test]
+    fn launch_brought_to_front() {
+        let output = "\
+Starting: Intent { cmp=com.android.settings/.Settings }
+Warning: Activity not started, its current task has been brought to the front
+Status: ok
+Activity: com.android.settings/.Settings
+ThisTime: 0
+TotalTime: 0
+WaitTime: 12
+Complete
+";
+        let result = LaunchResult::parse(output);
+        assert!(result.brought_to_front);
+        assert_eq!(result.this_time, Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn start_activity_error() {
+        let intent = Intent::new().component("com.example/.Missing");
+        let output = "Starting: Intent { cmp=com.example/.Missing }\n\
+                      Error type 3\n\
+                      Error: Activity class {com.example/.Missing} does not exist.\n";
+        match start_activity(output, &intent) {
+            Err(DroidError::IntentFailed {
+                command, output, ..
+            }) => {
+                assert_eq!(command, "start");
+                assert_eq!(
+                    output,
+                    "Error: Activity class {com.example/.Missing} does not exist."
+                );
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_broadcast_result() {
+        let intent = Intent::new().action("com.example.RESET");
+        let output = "Broadcasting: Intent { act=com.example.RESET flg=0x400000 }\n\
+                      Broadcast completed: result=0, data=\"done\"\n";
+        assert_eq!(
+            broadcast(output, &intent).unwrap(),
+            BroadcastResult {
+                code: 0,
+                data: Some("done".to_string()),
+            }
+        );
+
+        let output = "Broadcast completed: result=-1\n";
+        assert_eq!(
+            broadcast(output, &intent).unwrap(),
+            BroadcastResult {
+                code: -1,
+                data: None,
+            }
+        );
+        assert!(broadcast("Security exception: not allowed\n", &intent).is_err());
+    }
+
+    #[test]
+    fn quotes_intent_arguments() {
+        let intent = Intent::new()
+            .action("android.intent.action.VIEW")
+            .data("https://example.com/a b")
+            .extra_string("name", "it's")
+            .extra_string("note", "two  spaces");
+        let args = intent.to_args();
+        assert!(args.contains("-a android.intent.action.VIEW"));
+        assert!(args.contains("-d 'https://example.com/a b'"));
+        assert!(args.contains("--es name 'it'\\''s'"));
+        assert!(args.contains("--es note 'two  spaces'"));
+    }
+}
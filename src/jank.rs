@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::common::stats::percentile;
+use crate::perf::{self, FrameCounts};
+
+/// The refresh rate assumed when the device does not report one.
+pub(crate) const DEFAULT_REFRESH_RATE: f32 = 60.0;
+
+/// How long each stage of rendering a frame took.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FramePhases {
+    /// From the vsync the frame was meant for until input handling started,
+    /// i.e. how late the UI thread picked the frame up.
+    pub delay: Duration,
+    /// Handling input events.
+    pub input: Duration,
+    /// Running animators and `Choreographer` callbacks.
+    pub animation: Duration,
+    /// Measure and layout.
+    pub layout: Duration,
+    /// Recording the draw commands on the UI thread.
+    pub draw: Duration,
+    /// Uploading bitmaps and syncing the draw commands to the render thread.
+    pub sync: Duration,
+    /// Issuing the draw commands to the GPU.
+    pub command_issue: Duration,
+    /// Handing the frame to the compositor.
+    pub swap: Duration,
+}
+
+impl FramePhases {
+    /// Returns the name of the slowest phase and its duration.
+    pub fn slowest(&self) -> (&'static str, Duration) {
+        [
+            ("delay", self.delay),
+            ("input", self.input),
+            ("animation", self.animation),
+            ("layout", self.layout),
+            ("draw", self.draw),
+            ("sync", self.sync),
+            ("command_issue", self.command_issue),
+            ("swap", self.swap),
+        ]
+        .into_iter()
+        .max_by_key(|(_, duration)| *duration)
+        .unwrap_or(("delay", Duration::ZERO))
+    }
+}
+
+/// The timing of one rendered frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameTiming {
+    /// The vsync the frame was meant for, in nanoseconds of the device's
+    /// monotonic clock.
+    pub intended_vsync_ns: u64,
+    /// From the intended vsync until the frame was complete.
+    pub duration: Duration,
+    pub phases: FramePhases,
+}
+
+/// Frame time percentiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FramePercentiles {
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+}
+
+impl FramePercentiles {
+    /// Computes the percentiles of `sorted` frame durations, or `None` if
+    /// there are none.
+    fn from_sorted(sorted: &[Duration]) -> Option<Self> {
+        Some(Self {
+            p50: percentile(sorted, 50)?,
+            p90: percentile(sorted, 90)?,
+            p95: percentile(sorted, 95)?,
+            p99: percentile(sorted, 99)?,
+        })
+    }
+}
+
+/// Frame timing analysis of the frames rendered between
+/// `Droid::reset_frame_stats()` and `Droid::collect_frame_stats()`.
+///
+/// The per-frame analysis covers at most the last 120 frames, which is all
+/// Android keeps. `rendered` counts every frame since the reset.
+#[derive(Debug, Clone)]
+pub struct JankReport {
+    pub package: String,
+    /// The display refresh rate in Hz.
+    pub refresh_rate: f32,
+    /// The time a frame may take before it misses a vsync: `1 / refresh_rate`.
+    pub frame_budget: Duration,
+    /// The analyzed frames, oldest first.
+    pub frames: Vec<FrameTiming>,
+    /// How many of `frames` took longer than `frame_budget`.
+    pub janky_frames: usize,
+    /// `None` if no frames were rendered.
+    pub percentiles: Option<FramePercentiles>,
+    /// The frame counts Android reports for all frames since the reset. Its
+    /// jank criteria differ slightly from `janky_frames`.
+    pub rendered: Option<FrameCounts>,
+}
+
+impl JankReport {
+    pub(crate) fn new(package: &str, output: &str, refresh_rate: f32) -> Self {
+        let frames = parse_framestats(output);
+        let frame_budget = Duration::from_secs_f32(1.0 / refresh_rate);
+        let janky_frames = frames.iter().filter(|f| f.duration > frame_budget).count();
+
+        let mut sorted: Vec<Duration> = frames.iter().map(|f| f.duration).collect();
+        sorted.sort();
+        let percentiles = FramePercentiles::from_sorted(&sorted);
+
+        Self {
+            package: package.to_string(),
+            refresh_rate,
+            frame_budget,
+            janky_frames,
+            percentiles,
+            rendered: perf::parse_frame_counts(output),
+            frames,
+        }
+    }
+
+    /// The share of analyzed frames that were janky, from 0.0 to 1.0.
+    pub fn jank_ratio(&self) -> f32 {
+        if self.frames.is_empty() {
+            return 0.0;
+        }
+        self.janky_frames as f32 / self.frames.len() as f32
+    }
+
+    /// Returns up to `count` of the slowest frames, slowest first.
+    pub fn worst(&self, count: usize) -> Vec<FrameTiming> {
+        let mut frames = self.frames.clone();
+        frames.sort_by_key(|frame| std::cmp::Reverse(frame.duration));
+        frames.truncate(count);
+        frames
+    }
+}
+
+/// Parses the `---PROFILEDATA---` tables of `dumpsys gfxinfo <package> framestats`.
+///
+/// ```text
+/// ---PROFILEDATA---
+/// Flags,FrameTimelineVsyncId,IntendedVsync,Vsync,InputEventId,HandleInputStart,AnimationStart,...
+/// 0,2416,10275393578125,10275393578125,0,10275394045717,10275394061238,...
+/// ---PROFILEDATA---
+/// ```
+///
+/// Columns are looked up by name, since they differ between Android versions.
+/// Frames with non-zero flags, such as the first frame of a window, are skipped.
+fn parse_framestats(output: &str) -> Vec<FrameTiming> {
+    let mut frames = Vec::new();
+    let mut columns: Option<HashMap<&str, usize>> = None;
+    for line in output.lines().map(str::trim) {
+        if line == "---PROFILEDATA---" {
+            columns = None;
+            continue;
+        }
+        if line.starts_with("Flags,") {
+            columns = Some(
+                line.split(',')
+                    .enumerate()
+                    .map(|(i, name)| (name, i))
+                    .collect(),
+            );
+            continue;
+        }
+        let Some(columns) = &columns else {
+            continue;
+        };
+        let values: Vec<u64> = line
+            .split(',')
+            .map(|value| value.trim().parse().unwrap_or(0))
+            .collect();
+        let get = |name: &str| columns.get(name).and_then(|&i| values.get(i)).copied();
+        if get("Flags") != Some(0) {
+            continue;
+        }
+        if let Some(frame) = frame_timing(&get) {
+            frames.push(frame);
+        }
+    }
+    frames.sort_by_key(|frame| frame.intended_vsync_ns);
+    frames
+}
+
+fn frame_timing(get: &dyn Fn(&str) -> Option<u64>) -> Option<FrameTiming> {
+    let span = |from: &str, to: &str| -> Option<Duration> {
+        Some(Duration::from_nanos(get(to)?.saturating_sub(get(from)?)))
+    };
+    let intended_vsync_ns = get("IntendedVsync")?;
+    Some(FrameTiming {
+        intended_vsync_ns,
+        duration: span("IntendedVsync", "FrameCompleted")?,
+        phases: FramePhases {
+            delay: span("IntendedVsync", "HandleInputStart")?,
+            input: span("HandleInputStart", "AnimationStart")?,
+            animation: span("AnimationStart", "PerformTraversalsStart")?,
+            layout: span("PerformTraversalsStart", "DrawStart")?,
+            draw: span("DrawStart", "SyncQueued")?,
+            sync: span("SyncStart", "IssueDrawCommandsStart")?,
+            command_issue: span("IssueDrawCommandsStart", "SwapBuffers")?,
+            swap: span("SwapBuffers", "FrameCompleted")?,
+        },
+    })
+}
+
+/// Parses the refresh period that `dumpsys SurfaceFlinger --latency` prints
+/// on its first line, in nanoseconds, into a refresh rate in Hz.
+pub(crate) fn parse_refresh_rate(output: &str) -> Option<f32> {
+    let period: u64 = output.lines().next()?.trim().parse().ok()?;
+    (period > 0).then(|| 1_000_000_000.0 / period as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAMESTATS: &str = "\
+Applications Graphics Acceleration Info:
+---PROFILEDATA---
+Flags,IntendedVsync,Vsync,HandleInputStart,AnimationStart,PerformTraversalsStart,DrawStart,SyncQueued,SyncStart,IssueDrawCommandsStart,SwapBuffers,FrameCompleted,
+0,10050000000,10050000000,10051000000,10052000000,10053000000,10055000000,10058000000,10059000000,10060000000,10064000000,10080000000,
+1,10010000000,10010000000,10011000000,10012000000,10013000000,10014000000,10015000000,10016000000,10017000000,10018000000,10019000000,
+0,10000000000,10000000000,10001000000,10002000000,10003000000,10005000000,10008000000,10009000000,10010000000,10014000000,10020000000,
+---PROFILEDATA---
+";
+
+    #[test]
+    fn parses_frames_in_vsync_order() {
+        let frames = parse_framestats(FRAMESTATS);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].intended_vsync_ns, 10_000_000_000);
+        assert_eq!(frames[0].duration, Duration::from_millis(20));
+        assert_eq!(
+            frames[0].phases,
+            FramePhases {
+                delay: Duration::from_millis(1),
+                input: Duration::from_millis(1),
+                animation: Duration::from_millis(1),
+                layout: Duration::from_millis(2),
+                draw: Duration::from_millis(3),
+                sync: Duration::from_millis(1),
+                command_issue: Duration::from_millis(4),
+                swap: Duration::from_millis(6),
+            }
+        );
+        assert_eq!(frames[1].duration, Duration::from_millis(30));
+        assert_eq!(
+            frames[1].phases.slowest(),
+            ("swap", Duration::from_millis(16))
+        );
+    }
+
+    #[test]
+    fn report_counts_frames_over_budget() {
+        let report = JankReport::new("com.example", FRAMESTATS, 60.0);
+        assert_eq!(report.janky_frames, 2);
+        let report = JankReport::new("com.example", FRAMESTATS, 40.0);
+        assert_eq!(report.janky_frames, 1);
+        assert_eq!(report.worst(1)[0].intended_vsync_ns, 10_050_000_000);
+        let percentiles = report.percentiles.unwrap();
+        assert_eq!(percentiles.p50, Duration::from_millis(20));
+        assert_eq!(percentiles.p99, Duration::from_millis(30));
+        assert!(
+            JankReport::new("com.example", "", 60.0)
+                .percentiles
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn refresh_rate_from_period() {
+        let output = "16666666\n0\t0\t0\n";
+        let rate = parse_refresh_rate(output).unwrap();
+        assert!((rate - 60.0).abs() < 0.01);
+        assert_eq!(parse_refresh_rate("0\n"), None);
+        assert_eq!(parse_refresh_rate(""), None);
+    }
+}
@@ -1,22 +1,41 @@
 pub mod action;
+pub mod app;
 pub mod common;
 pub mod config;
+pub mod crash;
 pub mod device;
 pub mod error;
+pub mod intent;
+pub mod jank;
+pub mod logcat;
+pub mod mirror;
 pub mod models;
+pub mod perf;
+pub mod permission;
+pub mod pool;
+pub mod retry;
+pub mod selector;
+pub mod startup;
+pub mod stream;
+pub mod transfer;
 pub mod vision;
+pub mod watcher;
 
 use crate::common::point::Point;
 use crate::common::rect::Rect;
 use crate::common::relative_rect::RelativeRect;
-use crate::models::KeyCode;
+use crate::models::{DeviceInfo, Direction, KeyCode, Rotation};
 pub use config::DroidConfig;
-use device::DeviceController;
+use crash::{CrashMonitor, CrashOptions, CrashReport};
+use device::{DeviceController, ForegroundActivity, KeyboardState};
 use error::{DroidError, Result};
-use image::{DynamicImage, GenericImageView};
+use image::{DynamicImage, GenericImageView, imageops::FilterType};
 pub use models::{AppPackages, Target, TIKTOK_LIKE_POINT};
+use permission::{PermissionDecision, PermissionPolicy};
 use std::path::Path;
 use std::time::Duration;
+use stream::{ScreenStream, StreamOptions};
+use watcher::{WatcherBuilder, WatcherStats, Watchers};
 
 /// The main entry point for interacting with an Android device.
 ///
@@ -25,14 +44,24 @@ use std::time::Duration;
 pub struct Droid {
     controller: DeviceController,
     pub(crate) config: DroidConfig,
+    stream: Option<ScreenStream>,
+    watchers: Watchers,
+    permission_guard: Option<PermissionPolicy>,
+    crash_monitor: Option<CrashMonitor>,
 }
 
 impl Droid {
     /// Creates a new `Droid` instance and connects to a device.
     pub fn new(config: DroidConfig) -> Result<Self> {
-        let controller =
-            DeviceController::new(config.device_serial.as_deref(), config.adb_server_addr)?;
-        Ok(Self { controller, config })
+        let controller = DeviceController::new(&config)?;
+        Ok(Self {
+            controller,
+            config,
+            stream: None,
+            watchers: Watchers::default(),
+            permission_guard: None,
+            crash_monitor: None,
+        })
     }
 
     pub(crate) fn resolve_target(
@@ -49,10 +78,131 @@ impl Droid {
                 log::debug!("Target resolved to a direct point: {:?}", p);
                 Ok(*p)
             }
+            Target::Image(_) => self.locate(target, threshold, search_rect)?.0,
+        }
+    }
+
+    /// Captures the screen and resolves `target` on it. If the target is not
+    /// found, an interruption such as a permission dialog is handled and the
+    /// search is repeated once on a fresh capture.
+    ///
+    /// Returns the result of the search together with the capture it ran on.
+    pub(crate) fn locate(
+        &mut self,
+        target: &Target,
+        threshold: f32,
+        search_rect: Option<RelativeRect>,
+    ) -> Result<(Result<Point>, DynamicImage)> {
+        let (haystack, scale) = self.capture()?;
+        match self.resolve_target_on(target, threshold, search_rect, &haystack, scale) {
+            Err(DroidError::ImageNotFound(path)) => {
+                if self.handle_interruptions(&haystack, scale)?.is_none() {
+                    return Ok((Err(DroidError::ImageNotFound(path)), haystack));
+                }
+                let (haystack, scale) = self.capture()?;
+                let result =
+                    self.resolve_target_on(target, threshold, search_rect, &haystack, scale);
+                Ok((result, haystack))
+            }
+            result => Ok((result, haystack)),
+        }
+    }
+
+    /// Answers a permission dialog if the permission guard is set, or else
+    /// runs the first registered watcher whose target is on `screen`.
+    ///
+    /// Returns the name of the watcher that fired, or `"permission_dialog"`.
+    /// Handlers never trigger watchers themselves.
+    pub(crate) fn handle_interruptions(
+        &mut self,
+        screen: &DynamicImage,
+        scale: f32,
+    ) -> Result<Option<String>> {
+        if let Some(policy) = &self.permission_guard
+            && permission::handle_dialog(&mut self.controller, policy)?.is_some()
+        {
+            self.sleep(self.config.default_interval);
+            return Ok(Some("permission_dialog".to_string()));
+        }
+        if !self.watchers.is_active() {
+            return Ok(None);
+        }
+        Watchers::check(self, screen, scale)
+    }
+
+    /// Captures the current screen, from the active stream if there is one.
+    ///
+    /// Returns the image together with its scale relative to the screen.
+    pub(crate) fn capture(&mut self) -> Result<(DynamicImage, f32)> {
+        self.check_crash()?;
+        self.follow_rotation()?;
+        match &mut self.stream {
+            Some(stream) => {
+                let frame = stream.latest(self.config.default_timeout)?;
+                Ok((frame.image, frame.scale))
+            }
+            None => Ok((self.controller.screenshot()?, 1.0)),
+        }
+    }
+
+    /// Restarts the active stream if the display rotated since it started,
+    /// so that its frames are not letterboxed and map back to the screen.
+    fn follow_rotation(&mut self) -> Result<()> {
+        let Some(stream) = &self.stream else {
+            return Ok(());
+        };
+        let screen = self.controller.screen_size()?;
+        if stream.screen_size() == screen {
+            return Ok(());
+        }
+        let options = stream.options();
+        log::info!(
+            "Display rotated to {}x{}, restarting the screen stream",
+            screen.0,
+            screen.1
+        );
+        self.stream = None;
+        self.stream = Some(self.controller.start_stream(options)?);
+        Ok(())
+    }
+
+    /// Waits until the active stream delivers a new frame, or sleeps for
+    /// `interval` when no stream is running.
+    pub(crate) fn wait_for_change(&mut self, interval: Duration, timeout: Duration) {
+        match &mut self.stream {
+            Some(stream) => {
+                stream.next_timeout(timeout);
+            }
+            None => std::thread::sleep(interval),
+        }
+    }
+
+    /// Resolves `target` against an already captured screen image.
+    ///
+    /// `scale` is the size of `haystack` relative to the screen; image
+    /// templates are scaled to match and the result is mapped back to screen
+    /// coordinates.
+    pub(crate) fn resolve_target_on(
+        &self,
+        target: &Target,
+        threshold: f32,
+        search_rect: Option<RelativeRect>,
+        haystack: &DynamicImage,
+        scale: f32,
+    ) -> Result<Point> {
+        match target {
+            Target::Point(p) => Ok(*p),
             Target::Image(path) => {
                 log::debug!("Attempting to resolve image target: {:?}", path);
-                let needle = image::open(path)?;
-                let haystack = self.controller.screenshot()?;
+                let mut needle = image::open(path)?;
+                if scale != 1.0 {
+                    let (w, h) = needle.dimensions();
+                    needle = needle.resize_exact(
+                        ((w as f32 * scale) as u32).max(1),
+                        ((h as f32 * scale) as u32).max(1),
+                        FilterType::Triangle,
+                    );
+                }
 
                 let absolute_search_rect: Option<Rect> = search_rect.map(|relative_rect| {
                     let (w, h) = haystack.dimensions();
@@ -60,14 +210,18 @@ impl Droid {
                 });
 
                 let match_result = vision::find_template(
-                    &haystack,
+                    haystack,
                     &needle,
                     threshold,
                     path,
                     absolute_search_rect,
                 )?;
 
-                let center_point = match_result.rect.center();
+                let center = match_result.rect.center();
+                let center_point = Point::new(
+                    (center.x as f32 / scale) as u32,
+                    (center.y as f32 / scale) as u32,
+                );
                 log::info!(
                     "Image target found at {:?}, center: {:?}, confidence: {:.4}",
                     match_result.rect,
@@ -104,6 +258,71 @@ impl Droid {
         action::swipe::SwipeBuilder::new(self, start, end)
     }
 
+    /// Initiates a drag-and-drop from one target to another.
+    ///
+    /// Returns a `DragBuilder` to configure hold and move durations.
+    pub fn drag(&mut self, from: Target, to: Target) -> action::drag::DragBuilder<'_> {
+        action::drag::DragBuilder::new(self, from, to)
+    }
+
+    /// Initiates a drag along a path that starts at `start`.
+    ///
+    /// Returns a `PathBuilder` to add waypoints, holds and a motion profile.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rust_droid::{Droid, DroidConfig, Target, common::point::Point};
+    /// # use std::time::Duration;
+    /// # let mut droid = Droid::new(DroidConfig::default()).unwrap();
+    /// // Draw an "L" unlock pattern, holding briefly on the first dot.
+    /// droid
+    ///     .path(Point::new(200, 1200).into())
+    ///     .hold(Duration::from_millis(300))
+    ///     .to(Point::new(200, 1600).into())
+    ///     .to(Point::new(600, 1600).into())
+    ///     .execute()?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn path(&mut self, start: Target) -> action::path::PathBuilder<'_> {
+        action::path::PathBuilder::new(self, start)
+    }
+
+    /// Initiates a scroll gesture in the given direction.
+    ///
+    /// Returns a `ScrollBuilder` to configure the region, distance and speed.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rust_droid::{Droid, DroidConfig, models::Direction};
+    /// # let mut droid = Droid::new(DroidConfig::default()).unwrap();
+    /// // Flick to the next item of a vertical feed.
+    /// droid.scroll(Direction::Up).fling().execute()?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn scroll(&mut self, direction: Direction) -> action::scroll::ScrollBuilder<'_> {
+        action::scroll::ScrollBuilder::new(self, direction)
+    }
+
+    /// Scrolls until a target becomes visible.
+    ///
+    /// Returns a `ScrollToBuilder` to configure the direction, region and limits.
+    /// The operation succeeds by returning the `Point` where the target was found.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rust_droid::{Droid, DroidConfig, Target};
+    /// # let mut droid = Droid::new(DroidConfig::default()).unwrap();
+    /// let item = droid.scroll_to(Target::from("wifi_entry.png")).max_swipes(20).execute()?;
+    /// droid.touch(item.into()).execute()?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn scroll_to(&mut self, target: Target) -> action::scroll_to::ScrollToBuilder<'_> {
+        action::scroll_to::ScrollToBuilder::new(self, target)
+    }
+
     /// Waits for a target to appear on the screen.
     ///
     /// Returns a `WaitBuilder` to configure timeouts and execute the wait operation.
@@ -112,6 +331,64 @@ impl Droid {
         action::wait::WaitBuilder::new(self, target)
     }
 
+    /// Registers a watcher that handles `target` whenever it gets in the way.
+    ///
+    /// See `WatcherBuilder` for when watchers are checked. `target` must be
+    /// an image; `WatcherBuilder::register` rejects points.
+    pub fn watch(&mut self, name: &str, target: Target) -> WatcherBuilder<'_> {
+        WatcherBuilder::new(self, name, target)
+    }
+
+    /// Enables a watcher. Returns `false` if there is no watcher named `name`.
+    pub fn enable_watcher(&mut self, name: &str) -> bool {
+        self.watchers.set_enabled(name, true)
+    }
+
+    /// Disables a watcher without forgetting its statistics.
+    ///
+    /// Returns `false` if there is no watcher named `name`.
+    pub fn disable_watcher(&mut self, name: &str) -> bool {
+        self.watchers.set_enabled(name, false)
+    }
+
+    /// Removes a watcher. Returns `false` if there is no watcher named `name`.
+    pub fn remove_watcher(&mut self, name: &str) -> bool {
+        self.watchers.remove(name)
+    }
+
+    /// Returns how often each registered watcher has fired.
+    pub fn watcher_stats(&self) -> Vec<WatcherStats> {
+        self.watchers.stats()
+    }
+
+    /// Checks the registered watchers against the current screen right away.
+    ///
+    /// Returns the name of the watcher that fired, if any.
+    pub fn check_watchers(&mut self) -> Result<Option<String>> {
+        let (screen, scale) = self.capture()?;
+        self.handle_interruptions(&screen, scale)
+    }
+
+    /// Answers the runtime permission dialog on screen according to `policy`.
+    ///
+    /// The dialog is recognised from the system permission controller's view
+    /// hierarchy, not from images, so it works across Android versions and
+    /// vendors. Returns `None` if no dialog is open.
+    pub fn handle_permission_dialog(
+        &mut self,
+        policy: &PermissionPolicy,
+    ) -> Result<Option<PermissionDecision>> {
+        permission::handle_dialog(&mut self.controller, policy)
+    }
+
+    /// Sets a policy for answering permission dialogs automatically.
+    ///
+    /// Like watchers, the guard is checked whenever an image target cannot be
+    /// found or a wait has not succeeded yet. `None` turns it off.
+    pub fn set_permission_guard(&mut self, policy: Option<PermissionPolicy>) {
+        self.permission_guard = policy;
+    }
+
     /// Initiates a text input action.
     ///
     /// Returns a `TextBuilder` to execute the action.
@@ -125,6 +402,280 @@ impl Droid {
         std::thread::sleep(duration);
     }
 
+    /// Returns the ADB serial of the connected device.
+    pub fn serial(&self) -> &str {
+        self.controller.serial()
+    }
+
+    /// Whether the ADB server currently reports the device as online.
+    ///
+    /// While it is not, device operations wait for it according to
+    /// `DroidConfig::reconnect`.
+    pub fn is_online(&self) -> bool {
+        self.controller.is_online()
+    }
+
+    /// Returns the activity in the foreground, or `None` if there is none,
+    /// e.g. while the screen is off.
+    pub fn foreground_activity(&mut self) -> Result<Option<ForegroundActivity>> {
+        self.controller.foreground_activity()
+    }
+
+    /// Returns the window with input focus, e.g.
+    /// `com.android.settings/com.android.settings.Settings` or `NotificationShade`.
+    ///
+    /// Unlike `foreground_activity()`, this reports dialogs, the notification
+    /// shade and other system windows on top of the activity.
+    pub fn focused_window(&mut self) -> Result<Option<String>> {
+        self.controller.focused_window()
+    }
+
+    /// Returns whether the on-screen keyboard is shown, and which one is active.
+    pub fn keyboard_state(&mut self) -> Result<KeyboardState> {
+        self.controller.keyboard_state()
+    }
+
+    /// Returns the current display rotation, read from the device on every
+    /// call so it reflects a rotation that just happened.
+    pub fn rotation(&mut self) -> Result<Rotation> {
+        self.controller.rotation()
+    }
+
+    /// Waits until `activity` of `package` is in the foreground, using the
+    /// default timeout and interval from `DroidConfig`.
+    ///
+    /// `activity` may be fully qualified or relative to the package, e.g. `.Settings`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DroidError::ActivityTimeout` with the last foreground activity
+    /// if it does not show up in time.
+    pub fn wait_for_activity(
+        &mut self,
+        package: &str,
+        activity: &str,
+    ) -> Result<ForegroundActivity> {
+        let timeout = self.config.default_timeout;
+        let start = std::time::Instant::now();
+        log::info!("Waiting for {}/{}", package, activity);
+        loop {
+            self.check_crash()?;
+            let current = self.controller.foreground_activity()?;
+            if let Some(current) = current.as_ref()
+                && current.is(package, activity)
+            {
+                return Ok(current.clone());
+            }
+            if start.elapsed() > timeout {
+                return Err(DroidError::ActivityTimeout {
+                    expected: format!("{}/{}", package, activity),
+                    last: current.map(|c| format!("{}/{}", c.package, c.activity)),
+                    timeout,
+                });
+            }
+            std::thread::sleep(self.config.default_interval);
+        }
+    }
+
+    /// Reads a system property of the device, e.g. `ro.product.model`.
+    ///
+    /// Returns an empty string if the property is not set.
+    pub fn getprop(&mut self, key: &str) -> Result<String> {
+        self.controller.getprop(key)
+    }
+
+    /// Returns cached static information about the device, such as its screen size.
+    pub fn device_info(&mut self) -> Result<DeviceInfo> {
+        self.controller.device_info()
+    }
+
+    /// Starts streaming the screen continuously in the background.
+    ///
+    /// While a stream is active, image targets are resolved against the most
+    /// recent frame instead of a fresh screenshot, and waits react to every
+    /// screen change instead of polling at a fixed interval. Starting a new
+    /// stream replaces the previous one.
+    pub fn start_stream(&mut self, options: StreamOptions) -> Result<()> {
+        self.stream = None;
+        self.stream = Some(self.controller.start_stream(options)?);
+        Ok(())
+    }
+
+    /// Stops the active screen stream, if any.
+    pub fn stop_stream(&mut self) {
+        if self.stream.take().is_some() {
+            log::info!("Screen stream stopped");
+        }
+    }
+
+    /// Returns the active screen stream, e.g. to iterate over its frames.
+    pub fn stream(&mut self) -> Option<&mut ScreenStream> {
+        self.stream.as_mut()
+    }
+
+    /// Starts sampling the CPU, memory and frame statistics of `package` in
+    /// the background.
+    ///
+    /// Call `PerfSampler::stop()` to end sampling and export the samples.
+    pub fn start_sampler(
+        &mut self,
+        package: impl AsRef<str>,
+        options: perf::SamplerOptions,
+    ) -> Result<perf::PerfSampler> {
+        self.controller.start_sampler(package.as_ref(), options)
+    }
+
+    /// Starts watching for crashes, ANRs and crash dialogs of `package`,
+    /// replacing any running crash monitor.
+    ///
+    /// See `CrashMonitor` for how detected crashes are reported.
+    pub fn start_crash_monitor(
+        &mut self,
+        package: impl AsRef<str>,
+        options: CrashOptions,
+    ) -> Result<()> {
+        self.crash_monitor = None;
+        self.crash_monitor = Some(
+            self.controller
+                .start_crash_monitor(package.as_ref(), options)?,
+        );
+        Ok(())
+    }
+
+    /// Stops the crash monitor and returns the crashes it detected that
+    /// were not reported yet.
+    pub fn stop_crash_monitor(&mut self) -> Vec<CrashReport> {
+        self.crash_monitor
+            .take()
+            .map(|monitor| monitor.take_reports())
+            .unwrap_or_default()
+    }
+
+    /// Fails with `DroidError::AppCrashed` if the crash monitor detected a
+    /// crash since the last check.
+    ///
+    /// Actions and waits check this on their own; call it between other steps.
+    pub fn check_crash(&mut self) -> Result<()> {
+        let Some(monitor) = &self.crash_monitor else {
+            return Ok(());
+        };
+        let mut reports = monitor.take_reports().into_iter();
+        let Some(report) = reports.next() else {
+            return Ok(());
+        };
+        for later in reports {
+            log::warn!("Another crash was detected as well: {}", later);
+        }
+        Err(DroidError::AppCrashed(Box::new(report)))
+    }
+
+    /// Starts streaming the device log entries that pass `filter`.
+    ///
+    /// See `LogcatStream` for an example.
+    pub fn logcat(&mut self, filter: logcat::LogFilter) -> Result<logcat::LogcatStream> {
+        self.controller.start_logcat(filter)
+    }
+
+    /// Waits for a log entry whose message matches the regular expression `pattern`.
+    ///
+    /// Returns a `WaitForLogBuilder` to configure the filter and timeout and
+    /// execute the wait.
+    pub fn wait_for_log(&mut self, pattern: &str) -> logcat::WaitForLogBuilder<'_> {
+        logcat::WaitForLogBuilder::new(self, pattern)
+    }
+
+    /// Runs `actions` and writes the log entries that pass `filter` meanwhile to `path`.
+    ///
+    /// The file is written even if `actions` fails, so it can be used to
+    /// investigate the failure.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rust_droid::{Droid, DroidConfig, Target, logcat::LogFilter};
+    /// let mut droid = Droid::new(DroidConfig::default())?;
+    /// let filter = LogFilter::new().package("com.example.shop");
+    /// droid.capture_logcat("checkout.log", filter, |droid| {
+    ///     droid.touch(Target::from("checkout_button.png")).execute()?;
+    ///     droid.wait_for(Target::from("order_placed.png")).execute()?;
+    ///     Ok(())
+    /// })?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn capture_logcat<P, T, F>(
+        &mut self,
+        path: P,
+        filter: logcat::LogFilter,
+        actions: F,
+    ) -> Result<T>
+    where
+        P: AsRef<Path>,
+        F: FnOnce(&mut Droid) -> Result<T>,
+    {
+        let mut stream = self.controller.start_logcat(filter)?;
+        let settle = self.config.default_interval;
+        logcat::capture(self, &mut stream, path.as_ref(), settle, actions)
+    }
+
+    /// Clears the frame statistics of `package`, to analyze the frames of the
+    /// following actions with `collect_frame_stats()`.
+    pub fn reset_frame_stats(&mut self, package: impl AsRef<str>) -> Result<()> {
+        self.controller.reset_frame_stats(package.as_ref())
+    }
+
+    /// Analyzes the frames `package` rendered since `reset_frame_stats()`.
+    pub fn collect_frame_stats(&mut self, package: impl AsRef<str>) -> Result<jank::JankReport> {
+        let package = package.as_ref();
+        let refresh_rate = self.controller.refresh_rate()?;
+        let output = self.controller.framestats(package)?;
+        let report = jank::JankReport::new(package, &output, refresh_rate);
+        log::info!(
+            "{}: {} of {} frames janky at {:.0} Hz",
+            package,
+            report.janky_frames,
+            report.frames.len(),
+            refresh_rate
+        );
+        Ok(report)
+    }
+
+    /// Runs `actions` between `reset_frame_stats()` and `collect_frame_stats()`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rust_droid::{Droid, DroidConfig, Target, common::point::Point};
+    /// let mut droid = Droid::new(DroidConfig::default())?;
+    /// let report = droid.measure_frames("com.example.shop", |droid| {
+    ///     for _ in 0..5 {
+    ///         droid
+    ///             .swipe(Target::Point(Point::new(540, 1800)), Target::Point(Point::new(540, 600)))
+    ///             .execute()?;
+    ///     }
+    ///     Ok(())
+    /// })?;
+    /// if let Some(percentiles) = report.percentiles {
+    ///     println!("p90 {:?}, {} janky frames", percentiles.p90, report.janky_frames);
+    /// }
+    /// for frame in report.worst(3) {
+    ///     println!("{:?}, mostly {:?}", frame.duration, frame.phases.slowest());
+    /// }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn measure_frames<F>(
+        &mut self,
+        package: impl AsRef<str>,
+        actions: F,
+    ) -> Result<jank::JankReport>
+    where
+        F: FnOnce(&mut Droid) -> Result<()>,
+    {
+        let package = package.as_ref();
+        self.reset_frame_stats(package)?;
+        actions(self)?;
+        self.collect_frame_stats(package)
+    }
+
     /// Takes a screenshot of the current device screen and returns it as an image object.
     ///
     /// This is the programmatic alternative to `snapshot`, which saves the image to a file.
@@ -136,6 +687,15 @@ impl Droid {
         self.controller.screenshot()
     }
 
+    /// Captures only a region of the screen from raw `screencap` output.
+    ///
+    /// Only the rows covering the region are transferred from the device,
+    /// which makes this considerably faster than a full screenshot for small
+    /// regions such as a status line or a toolbar.
+    pub fn screenshot_region(&mut self, rect: RelativeRect) -> Result<DynamicImage> {
+        self.controller.screenshot_relative(rect)
+    }
+
     /// Takes a screenshot of the current device screen and saves it to a file.
     ///
     /// # Arguments
@@ -156,6 +716,105 @@ impl Droid {
         action::keyevent::KeyeventBuilder::new(self, key_code)
     }
 
+    /// Manages an installed app, given its package name or an `AppPackages` variant.
+    pub fn app(&mut self, package: impl AsRef<str>) -> app::App<'_> {
+        app::App::new(self, package.as_ref())
+    }
+
+    /// Installs an APK from the host.
+    pub fn install(&mut self, apk: impl Into<std::path::PathBuf>) -> app::InstallBuilder<'_> {
+        app::InstallBuilder::new(self, apk.into())
+    }
+
+    /// Starts an activity with `am start -W` and waits until it has drawn.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DroidError::IntentFailed` if no activity matches the intent.
+    pub fn start_activity(&mut self, intent: &intent::Intent) -> Result<intent::LaunchResult> {
+        log::info!("Starting activity: {}", intent.to_args());
+        let output = self
+            .controller
+            .activity_manager("start -W", &intent.to_args())?;
+        intent::start_activity(&output, intent)
+    }
+
+    /// Sends a broadcast with `am broadcast` and waits until it is delivered.
+    pub fn broadcast(&mut self, intent: &intent::Intent) -> Result<intent::BroadcastResult> {
+        log::info!("Broadcasting: {}", intent.to_args());
+        let output = self
+            .controller
+            .activity_manager("broadcast", &intent.to_args())?;
+        intent::broadcast(&output, intent)
+    }
+
+    /// Starts a service with `am startservice`.
+    pub fn start_service(&mut self, intent: &intent::Intent) -> Result<()> {
+        log::info!("Starting service: {}", intent.to_args());
+        let output = self
+            .controller
+            .activity_manager("startservice", &intent.to_args())?;
+        intent::start_service(&output, intent)
+    }
+
+    /// Measures how long the activity of `intent` takes to start.
+    ///
+    /// Returns a `StartupBuilder` to configure the runs and execute the measurement.
+    pub fn measure_startup(&mut self, intent: intent::Intent) -> startup::StartupBuilder<'_> {
+        startup::StartupBuilder::new(self, intent)
+    }
+
+    /// Copies a file or directory from the host to the device.
+    ///
+    /// Returns a `TransferBuilder` to configure syncing and progress
+    /// reporting and execute the copy.
+    pub fn push(
+        &mut self,
+        local: impl Into<std::path::PathBuf>,
+        remote: &str,
+    ) -> transfer::TransferBuilder<'_> {
+        transfer::TransferBuilder::push(self, local.into(), remote)
+    }
+
+    /// Copies a file or directory from the device to the host.
+    ///
+    /// Returns a `TransferBuilder` to configure syncing and progress
+    /// reporting and execute the copy.
+    pub fn pull(
+        &mut self,
+        remote: &str,
+        local: impl Into<std::path::PathBuf>,
+    ) -> transfer::TransferBuilder<'_> {
+        transfer::TransferBuilder::pull(self, remote, local.into())
+    }
+
+    /// Describes a file or directory on the device, or returns `None` if it
+    /// does not exist.
+    pub fn stat(&mut self, remote: &str) -> Result<Option<transfer::RemoteFile>> {
+        self.controller.stat(remote)
+    }
+
+    /// Lists a directory on the device, including its subdirectories if
+    /// `recursive` is set.
+    pub fn list_dir(&mut self, remote: &str, recursive: bool) -> Result<Vec<transfer::RemoteFile>> {
+        self.controller.list_files(remote, recursive)
+    }
+
+    /// Creates a directory on the device, along with any missing parents.
+    pub fn make_dir(&mut self, remote: &str) -> Result<()> {
+        self.controller.make_dirs(&[remote.to_string()])
+    }
+
+    /// Deletes a file on the device. Succeeds if it does not exist.
+    pub fn remove(&mut self, remote: &str) -> Result<()> {
+        self.controller.remove(remote, false)
+    }
+
+    /// Deletes a directory on the device and everything in it.
+    pub fn remove_dir(&mut self, remote: &str) -> Result<()> {
+        self.controller.remove(remote, true)
+    }
+
     /// Launches an app by package name using the launcher intent.
     pub fn launch_app(&mut self, package: &str) -> Result<()> {
         self.controller.launch_app(package)
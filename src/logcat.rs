@@ -0,0 +1,703 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{self, Write};
+use std::net::SocketAddrV4;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use adb_client::{ADBDeviceExt, ADBServer, ADBServerDevice};
+use regex::Regex;
+
+use crate::Droid;
+use crate::error::{DroidError, Result};
+
+/// How often the process ids of a `LogFilter::package` are looked up again
+/// at most, when entries of unknown processes arrive. Processes started while
+/// the stream runs are picked up from their `Start proc` entry right away.
+const PID_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+/// How long dropping a stream waits for the worker after stopping `logcat`.
+const STOP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The priority of a log entry, from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    Verbose,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl LogLevel {
+    /// Parses the letter logcat prints, e.g. `W`.
+    pub fn from_letter(letter: char) -> Option<Self> {
+        match letter {
+            'V' => Some(LogLevel::Verbose),
+            'D' => Some(LogLevel::Debug),
+            'I' => Some(LogLevel::Info),
+            'W' => Some(LogLevel::Warn),
+            'E' => Some(LogLevel::Error),
+            'F' | 'A' => Some(LogLevel::Fatal),
+            _ => None,
+        }
+    }
+
+    pub fn letter(self) -> char {
+        match self {
+            LogLevel::Verbose => 'V',
+            LogLevel::Debug => 'D',
+            LogLevel::Info => 'I',
+            LogLevel::Warn => 'W',
+            LogLevel::Error => 'E',
+            LogLevel::Fatal => 'F',
+        }
+    }
+}
+
+/// One line of the device log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    /// When the entry was logged, by the device clock.
+    pub time: SystemTime,
+    pub pid: u32,
+    pub tid: u32,
+    pub level: LogLevel,
+    pub tag: String,
+    pub message: String,
+}
+
+impl LogEntry {
+    /// Parses a line printed by `logcat -v threadtime -v epoch`.
+    ///
+    /// ```text
+    /// 1697630472.452  1234  1250 I ActivityManager: Start proc 4242:com.example.shop/u0a187
+    /// ```
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut rest = line.trim_start();
+        let mut token = || {
+            let end = rest.find(char::is_whitespace)?;
+            let (token, tail) = rest.split_at(end);
+            rest = tail.trim_start();
+            Some(token)
+        };
+        let time: f64 = token()?.parse().ok()?;
+        let pid = token()?.parse().ok()?;
+        let tid = token()?.parse().ok()?;
+        let level = token()?;
+        let level = LogLevel::from_letter(level.chars().next()?).filter(|_| level.len() == 1)?;
+        // The tag is padded with spaces and ends at the first `: `, or at a
+        // trailing `:` if the message is empty.
+        let (tag, message) = rest
+            .split_once(": ")
+            .unwrap_or_else(|| (rest.strip_suffix(':').unwrap_or(rest), ""));
+        Some(Self {
+            time: UNIX_EPOCH.checked_add(Duration::try_from_secs_f64(time).ok()?)?,
+            pid,
+            tid,
+            level,
+            tag: tag.trim_end().to_string(),
+            message: message.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for LogEntry {
+    /// Formats the entry like `logcat -v threadtime -v epoch` does.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Rounded, since the parsed time is off by the `f64` precision.
+        let time = self.time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let millis = (time.as_nanos() + 500_000) / 1_000_000;
+        write!(
+            f,
+            "{}.{:03} {:5} {:5} {} {}: {}",
+            millis / 1000,
+            millis % 1000,
+            self.pid,
+            self.tid,
+            self.level.letter(),
+            self.tag,
+            self.message
+        )
+    }
+}
+
+/// Selects which log entries a `LogcatStream` delivers.
+///
+/// An entry has to pass every filter that is set.
+#[derive(Debug, Clone)]
+pub struct LogFilter {
+    pub tags: Vec<String>,
+    pub min_level: LogLevel,
+    /// Only entries of this app's processes, which are looked up again when
+    /// the app restarts.
+    pub package: Option<String>,
+}
+
+impl Default for LogFilter {
+    /// - Tags: all
+    /// - Minimum level: `LogLevel::Verbose`
+    /// - Package: any
+    fn default() -> Self {
+        Self {
+            tags: Vec::new(),
+            min_level: LogLevel::Verbose,
+            package: None,
+        }
+    }
+}
+
+impl LogFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a tag to accept. Without tags, entries of every tag are accepted.
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tags.push(tag.to_string());
+        self
+    }
+
+    /// Accepts only entries of `level` and above.
+    pub fn level(mut self, level: LogLevel) -> Self {
+        self.min_level = level;
+        self
+    }
+
+    pub fn package(mut self, package: &str) -> Self {
+        self.package = Some(package.to_string());
+        self
+    }
+
+    /// Checks the tag and level of `entry`; the package is checked by the stream.
+    fn accepts(&self, entry: &LogEntry) -> bool {
+        entry.level >= self.min_level && (self.tags.is_empty() || self.tags.contains(&entry.tag))
+    }
+}
+
+/// A continuous stream of device log entries, read on a background thread.
+///
+/// The stream starts with the entries logged after it was created. Iterating
+/// over it blocks until the next matching entry arrives.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use rust_droid::{Droid, DroidConfig, Target};
+/// # use rust_droid::logcat::{LogFilter, LogLevel};
+/// let mut droid = Droid::new(DroidConfig::default())?;
+/// // Start listening before the action, so the entry cannot be missed.
+/// let mut log = droid.logcat(LogFilter::new().package("com.example.shop"))?;
+/// droid.touch(Target::from("checkout_button.png")).execute()?;
+/// let entry = log.wait_for(r"Order \d+ placed", Duration::from_secs(10))?;
+/// println!("{}: {}", entry.tag, entry.message);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct LogcatStream {
+    receiver: Receiver<LogEntry>,
+    stop: Arc<AtomicBool>,
+    /// The pid of the running `logcat`, or 0 before it reported it.
+    pid: Arc<AtomicU32>,
+    adb_addr: SocketAddrV4,
+    serial: String,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl LogcatStream {
+    pub(crate) fn start(adb_addr: SocketAddrV4, serial: &str, filter: LogFilter) -> Result<Self> {
+        let open = || {
+            ADBServer::new(adb_addr)
+                .get_device_by_name(serial)
+                .map_err(|e| DroidError::AdbError(e.to_string()))
+        };
+        let mut device = open()?;
+        let pid_lookup = match &filter.package {
+            Some(_) => Some(open()?),
+            None => None,
+        };
+
+        // `-T` with an epoch time skips the history; its precision depends on
+        // whether the device's `date` supports `%N`.
+        let mut now = Vec::new();
+        device
+            .shell_command(&["date", "+%s.%N"], &mut now)
+            .map_err(|e| DroidError::AdbError(e.to_string()))?;
+        let now = String::from_utf8_lossy(&now);
+        let unexpected = || DroidError::AdbError(format!("Unexpected device time: {}", now));
+        let seconds: f64 = now
+            .trim()
+            .parse()
+            .or_else(|_| now.trim().split('.').next().unwrap_or_default().parse())
+            .map_err(|_| unexpected())?;
+        let start = Duration::try_from_secs_f64(seconds)
+            .ok()
+            .and_then(|since_epoch| UNIX_EPOCH.checked_add(since_epoch))
+            .ok_or_else(unexpected)?;
+        // The shell prints its pid before it becomes `logcat`, so the reader
+        // can be stopped even while nothing is logged.
+        let command = format!(
+            "echo $$; exec logcat -v threadtime -v epoch -T {:.3}",
+            seconds
+        );
+
+        let (sender, receiver) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let pid = Arc::new(AtomicU32::new(0));
+        let worker_stop = Arc::clone(&stop);
+        let worker_pid = Arc::clone(&pid);
+        let worker = thread::Builder::new()
+            .name("droid-logcat".to_string())
+            .spawn(move || {
+                let mut writer = LogWriter {
+                    filter,
+                    start,
+                    header: true,
+                    pending: Vec::new(),
+                    sender,
+                    stop: Arc::clone(&worker_stop),
+                    pid: worker_pid,
+                    pid_lookup,
+                    pids: HashSet::new(),
+                    pids_refreshed: None,
+                };
+                if let Err(e) = device.shell_command(&[&command], &mut writer)
+                    && !worker_stop.load(Ordering::Relaxed)
+                {
+                    log::warn!("Logcat stream ended: {}", e);
+                }
+                log::debug!("Logcat worker exited");
+            })?;
+
+        Ok(Self {
+            receiver,
+            stop,
+            pid,
+            adb_addr,
+            serial: serial.to_string(),
+            worker: Some(worker),
+        })
+    }
+
+    /// Waits up to `timeout` for the next entry.
+    pub fn next_timeout(&mut self, timeout: Duration) -> Option<LogEntry> {
+        self.receiver.recv_timeout(timeout).ok()
+    }
+
+    /// Returns the entries that arrived so far without waiting.
+    pub fn drain(&mut self) -> Vec<LogEntry> {
+        self.receiver.try_iter().collect()
+    }
+
+    /// Waits up to `timeout` for an entry whose message matches `pattern`,
+    /// skipping entries that do not.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DroidError::InvalidLogPattern` if `pattern` is not a valid
+    /// regular expression, and `DroidError::Timeout` if no entry matches in time.
+    pub fn wait_for(&mut self, pattern: &str, timeout: Duration) -> Result<LogEntry> {
+        let regex = compile(pattern)?;
+        let start = Instant::now();
+        loop {
+            let remaining = timeout.saturating_sub(start.elapsed());
+            match self.receiver.recv_timeout(remaining) {
+                Ok(entry) if regex.is_match(&entry.message) => {
+                    log::info!("Log entry matched '{}': {}", pattern, entry);
+                    return Ok(entry);
+                }
+                Ok(_) => {}
+                Err(RecvTimeoutError::Timeout) => return Err(DroidError::Timeout(timeout)),
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(DroidError::AdbError("Logcat stream ended".to_string()));
+                }
+            }
+        }
+    }
+
+    /// Whether the background reader is still running.
+    pub fn is_running(&self) -> bool {
+        self.worker
+            .as_ref()
+            .is_some_and(|worker| !worker.is_finished())
+    }
+
+    /// Stops `logcat` on the device, which ends the worker's read.
+    fn kill_logcat(&self) {
+        let pid = self.pid.load(Ordering::Relaxed);
+        if pid == 0 {
+            return;
+        }
+        let result = ADBServer::new(self.adb_addr)
+            .get_device_by_name(&self.serial)
+            .and_then(|mut device| {
+                device.shell_command(&["kill", &pid.to_string()], &mut io::sink())
+            });
+        if let Err(e) = result {
+            log::debug!("Could not stop logcat ({}): {}", pid, e);
+        }
+    }
+}
+
+impl Iterator for LogcatStream {
+    type Item = LogEntry;
+
+    fn next(&mut self) -> Option<LogEntry> {
+        self.receiver.recv().ok()
+    }
+}
+
+impl Drop for LogcatStream {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.kill_logcat();
+        let Some(worker) = self.worker.take() else {
+            return;
+        };
+        let deadline = Instant::now() + STOP_TIMEOUT;
+        while !worker.is_finished() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(20));
+        }
+        if worker.is_finished() {
+            let _ = worker.join();
+        } else {
+            log::warn!("Logcat worker did not stop within {:?}", STOP_TIMEOUT);
+        }
+    }
+}
+
+/// Splits the logcat output into entries and hands the matching ones to the consumer.
+struct LogWriter {
+    filter: LogFilter,
+    /// Entries before this time are history that `-T` let through.
+    start: SystemTime,
+    /// Whether the pid line before the log is still expected.
+    header: bool,
+    pending: Vec<u8>,
+    sender: Sender<LogEntry>,
+    stop: Arc<AtomicBool>,
+    pid: Arc<AtomicU32>,
+    pid_lookup: Option<ADBServerDevice>,
+    pids: HashSet<u32>,
+    pids_refreshed: Option<Instant>,
+}
+
+impl LogWriter {
+    fn accepts(&mut self, entry: &LogEntry) -> bool {
+        // Checked before the filters, which usually reject the system's entries.
+        if let Some(package) = &self.filter.package
+            && let Some(pid) = started_pid(entry, package)
+        {
+            self.pids.insert(pid);
+        }
+        if entry.time < self.start || !self.filter.accepts(entry) {
+            return false;
+        }
+        let Some(package) = &self.filter.package else {
+            return true;
+        };
+        if !self.pids.contains(&entry.pid)
+            && self
+                .pids_refreshed
+                .is_none_or(|refreshed| refreshed.elapsed() > PID_REFRESH_INTERVAL)
+            && let Some(device) = &mut self.pid_lookup
+        {
+            let mut output = Vec::new();
+            if device
+                .shell_command(&["pidof", package], &mut output)
+                .is_ok()
+            {
+                self.pids = String::from_utf8_lossy(&output)
+                    .split_whitespace()
+                    .filter_map(|pid| pid.parse().ok())
+                    .collect();
+            }
+            self.pids_refreshed = Some(Instant::now());
+        }
+        self.pids.contains(&entry.pid)
+    }
+}
+
+/// Returns the process id if `entry` reports that the main process of
+/// `package` was started.
+///
+/// ```text
+/// 1697630472.452  1234  1250 I ActivityManager: Start proc 4242:com.example.shop/u0a187 for activity {...}
+/// ```
+fn started_pid(entry: &LogEntry, package: &str) -> Option<u32> {
+    if entry.tag != "ActivityManager" {
+        return None;
+    }
+    let (pid, rest) = entry.message.strip_prefix("Start proc ")?.split_once(':')?;
+    let (process, _) = rest.split_once('/')?;
+    if process != package {
+        return None;
+    }
+    pid.parse().ok()
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.stop.load(Ordering::Relaxed) {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "logcat stopped"));
+        }
+
+        self.pending.extend_from_slice(buf);
+        if self.header {
+            let Some(end) = self.pending.iter().position(|&b| b == b'\n') else {
+                return Ok(buf.len());
+            };
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            let pid = String::from_utf8_lossy(&line).trim().parse().unwrap_or(0);
+            self.pid.store(pid, Ordering::Relaxed);
+            self.header = false;
+        }
+        while let Some(end) = self.pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let Some(entry) = LogEntry::parse(line.trim_end()) else {
+                // E.g. `--------- beginning of main`.
+                continue;
+            };
+            if self.accepts(&entry) && self.sender.send(entry).is_err() {
+                return Err(io::Error::new(io::ErrorKind::BrokenPipe, "logcat dropped"));
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Builds and executes a wait for a log entry.
+///
+/// Only entries logged after `execute()` is called are considered. To wait
+/// for an entry caused by an action, open a `Droid::logcat()` stream before
+/// the action and use `LogcatStream::wait_for()` instead.
+///
+/// This struct is created by the `Droid::wait_for_log()` method.
+pub struct WaitForLogBuilder<'a> {
+    droid: &'a mut Droid,
+    pattern: String,
+    filter: LogFilter,
+    timeout: Duration,
+}
+
+impl<'a> WaitForLogBuilder<'a> {
+    pub fn new(droid: &'a mut Droid, pattern: &str) -> Self {
+        let timeout = droid.config.default_timeout;
+        Self {
+            droid,
+            pattern: pattern.to_string(),
+            filter: LogFilter::default(),
+            timeout,
+        }
+    }
+
+    pub fn timeout(mut self, duration: Duration) -> Self {
+        self.timeout = duration;
+        self
+    }
+
+    /// Replaces the filter entries have to pass before the pattern is checked.
+    pub fn filter(mut self, filter: LogFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.filter = self.filter.tag(tag);
+        self
+    }
+
+    pub fn level(mut self, level: LogLevel) -> Self {
+        self.filter = self.filter.level(level);
+        self
+    }
+
+    pub fn package(mut self, package: &str) -> Self {
+        self.filter = self.filter.package(package);
+        self
+    }
+
+    pub fn execute(self) -> Result<LogEntry> {
+        // Fail on a bad pattern before connecting.
+        compile(&self.pattern)?;
+        log::info!(
+            "Waiting for log entry matching '{}', timeout: {:?}",
+            self.pattern,
+            self.timeout
+        );
+        let mut stream = self.droid.logcat(self.filter)?;
+        stream.wait_for(&self.pattern, self.timeout)
+    }
+}
+
+/// Runs `actions` and writes the entries `stream` delivered meanwhile to
+/// `path`, one per line, including those logged up to `settle` afterwards.
+///
+/// If the actions fail, their error is returned even if writing the file
+/// fails as well.
+pub(crate) fn capture<T>(
+    droid: &mut Droid,
+    stream: &mut LogcatStream,
+    path: &Path,
+    settle: Duration,
+    actions: impl FnOnce(&mut Droid) -> Result<T>,
+) -> Result<T> {
+    let result = actions(droid);
+    // Entries logged at the end of the actions may still be in transit.
+    let deadline = Instant::now() + settle;
+    let mut entries = stream.drain();
+    while let Some(entry) = stream.next_timeout(deadline.saturating_duration_since(Instant::now()))
+    {
+        entries.push(entry);
+    }
+    let written = write_entries(path, &entries);
+    match (result, written) {
+        (Ok(value), Ok(())) => {
+            log::info!("Captured {} log entries to {:?}", entries.len(), path);
+            Ok(value)
+        }
+        (Ok(_), Err(e)) => Err(e.into()),
+        (Err(e), written) => {
+            if let Err(write_error) = written {
+                log::warn!("Writing log entries to {:?} failed: {}", path, write_error);
+            }
+            Err(e)
+        }
+    }
+}
+
+fn write_entries(path: &Path, entries: &[LogEntry]) -> io::Result<()> {
+    let mut file = io::BufWriter::new(std::fs::File::create(path)?);
+    for entry in entries {
+        writeln!(file, "{}", entry)?;
+    }
+    file.flush()
+}
+
+fn compile(pattern: &str) -> Result<Regex> {
+    Regex::new(pattern).map_err(|e| DroidError::InvalidLogPattern {
+        pattern: pattern.to_string(),
+        message: e.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START_PROC: &str = "1697630472.452  1234  1250 I ActivityManager: Start proc 4242:com.example.shop/u0a187 for activity {com.example.shop/.MainActivity}";
+
+    #[test]
+    fn parses_threadtime_epoch_line() {
+        let entry = LogEntry::parse(START_PROC).unwrap();
+        let time = entry.time.duration_since(UNIX_EPOCH).unwrap();
+        assert!(time.abs_diff(Duration::from_millis(1_697_630_472_452)) < Duration::from_micros(1));
+        assert_eq!(entry.pid, 1234);
+        assert_eq!(entry.tid, 1250);
+        assert_eq!(entry.level, LogLevel::Info);
+        assert_eq!(entry.tag, "ActivityManager");
+        assert!(entry.message.starts_with("Start proc 4242:"));
+
+        let entry = LogEntry::parse("1697630472.452  4242  4242 W Shop    :").unwrap();
+        assert_eq!(entry.tag, "Shop");
+        assert_eq!(entry.message, "");
+    }
+
+    #[test]
+    fn formats_like_logcat() {
+        let line = "1697630472.452  4242  4250 E Shop: Order failed: timeout";
+        assert_eq!(LogEntry::parse(line).unwrap().to_string(), line);
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        assert_eq!(LogEntry::parse("--------- beginning of main"), None);
+        assert_eq!(LogEntry::parse("1697630472.452 1 1 X tag: message"), None);
+        assert_eq!(LogEntry::parse("1697630472.452 1 1 II tag: message"), None);
+        assert_eq!(LogEntry::parse("-1 1 1 I t: m"), None);
+        assert_eq!(LogEntry::parse("nan 1 1 I t: m"), None);
+        assert_eq!(LogEntry::parse("inf 1 1 I t: m"), None);
+        assert_eq!(LogEntry::parse("1e300 1 1 I t: m"), None);
+    }
+
+    #[test]
+    fn started_pid_of_main_process() {
+        let entry = LogEntry::parse(START_PROC).unwrap();
+        assert_eq!(started_pid(&entry, "com.example.shop"), Some(4242));
+        assert_eq!(started_pid(&entry, "com.example"), None);
+
+        let secondary = LogEntry {
+            message: "Start proc 4260:com.example.shop:remote/u0a187 for service".to_string(),
+            ..entry.clone()
+        };
+        assert_eq!(started_pid(&secondary, "com.example.shop"), None);
+        let other_tag = LogEntry {
+            tag: "Shop".to_string(),
+            ..entry
+        };
+        assert_eq!(started_pid(&other_tag, "com.example.shop"), None);
+    }
+
+    #[test]
+    fn package_filter_follows_started_process() {
+        let (sender, receiver) = mpsc::channel();
+        let mut writer = LogWriter {
+            filter: LogFilter::new().package("com.example.shop").tag("Shop"),
+            start: UNIX_EPOCH,
+            header: false,
+            pending: Vec::new(),
+            sender,
+            stop: Arc::new(AtomicBool::new(false)),
+            pid: Arc::new(AtomicU32::new(0)),
+            pid_lookup: None,
+            pids: HashSet::new(),
+            pids_refreshed: None,
+        };
+        let output = format!(
+            "{}\n\
+             1697630472.460  4242  4242 I Shop: started\n\
+             1697630472.461  5000  5000 I Shop: other process\n",
+            START_PROC
+        );
+        writer.write_all(output.as_bytes()).unwrap();
+        let entries: Vec<LogEntry> = receiver.try_iter().collect();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].pid, 4242);
+        assert_eq!(entries[0].message, "started");
+    }
+
+    #[test]
+    fn reads_logcat_pid_before_entries() {
+        let (sender, receiver) = mpsc::channel();
+        let pid = Arc::new(AtomicU32::new(0));
+        let mut writer = LogWriter {
+            filter: LogFilter::new(),
+            start: UNIX_EPOCH,
+            header: true,
+            pending: Vec::new(),
+            sender,
+            stop: Arc::new(AtomicBool::new(false)),
+            pid: Arc::clone(&pid),
+            pid_lookup: None,
+            pids: HashSet::new(),
+            pids_refreshed: None,
+        };
+        writer
+            .write_all(b"31337\n1697630472.460  4242  4242 I Shop: ")
+            .unwrap();
+        assert_eq!(pid.load(Ordering::Relaxed), 31337);
+        writer.write_all(b"started\n").unwrap();
+        let entries: Vec<LogEntry> = receiver.try_iter().collect();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].message, "started");
+    }
+}
@@ -0,0 +1,490 @@
+use std::thread;
+use std::time::Duration;
+
+use crate::action::expect::{self, Condition, Expectation};
+use crate::common::point::Point;
+use crate::common::relative_rect::RelativeRect;
+use crate::error::{DroidError, Result};
+use crate::models::KeyCode;
+use crate::pool::panic_message;
+use crate::retry::RetryPolicy;
+use crate::{Droid, Target};
+
+/// The outcome of a mirrored action on one device.
+#[derive(Debug)]
+pub struct DeviceOutcome {
+    pub serial: String,
+    pub result: Result<()>,
+}
+
+/// The per-device outcomes of one mirrored action.
+#[derive(Debug)]
+pub struct MirrorReport {
+    /// A short name of the action, e.g. `"touch"`.
+    pub action: String,
+    /// One outcome per device, the leader first.
+    pub outcomes: Vec<DeviceOutcome>,
+}
+
+impl MirrorReport {
+    /// Whether the action succeeded on every device.
+    pub fn all_succeeded(&self) -> bool {
+        self.outcomes.iter().all(|o| o.result.is_ok())
+    }
+
+    /// Whether the action succeeded on some devices but failed on others,
+    /// e.g. because an image target was only found on one of them.
+    pub fn diverged(&self) -> bool {
+        !self.all_succeeded() && self.outcomes.iter().any(|o| o.result.is_ok())
+    }
+
+    /// Returns the outcomes of the devices the action failed on.
+    pub fn failures(&self) -> impl Iterator<Item = &DeviceOutcome> {
+        self.outcomes.iter().filter(|o| o.result.is_err())
+    }
+
+    /// Converts the report into an error if the action failed on any device.
+    pub fn into_result(self) -> Result<()> {
+        let failed: Vec<String> = self.failures().map(|o| o.serial.clone()).collect();
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(DroidError::MirrorDiverged {
+                action: self.action,
+                failed,
+            })
+        }
+    }
+}
+
+/// Maps point targets from the leader's screen to a follower's screen.
+///
+/// Both sizes are in the devices' current orientation.
+#[derive(Debug, Clone, Copy)]
+struct Rescale {
+    from: (u32, u32),
+    to: (u32, u32),
+}
+
+impl Rescale {
+    fn target(&self, target: &Target) -> Target {
+        match target {
+            Target::Point(p) => Target::Point(Point::new(
+                (u64::from(p.x) * u64::from(self.to.0) / u64::from(self.from.0.max(1))) as u32,
+                (u64::from(p.y) * u64::from(self.to.1) / u64::from(self.from.1.max(1))) as u32,
+            )),
+            Target::Image(_) => target.clone(),
+        }
+    }
+
+    fn condition(&self, condition: &Condition) -> Condition {
+        match condition {
+            Condition::Appears(target) => Condition::Appears(self.target(target)),
+            Condition::Gone(target) => Condition::Gone(self.target(target)),
+            Condition::ScreenChange => Condition::ScreenChange,
+        }
+    }
+}
+
+/// Applies a mirrored builder's retry policy and expectation to the
+/// builder of one device.
+macro_rules! forward_checks {
+    ($builder:ident, $retry:expr, $expectation:expr, $rescale:expr) => {
+        if let Some(policy) = $retry {
+            $builder = $builder.retry(policy.clone());
+        }
+        if let Some(condition) = &$expectation.condition {
+            $builder = match $rescale.condition(condition) {
+                Condition::Appears(target) => $builder.expect_appears(target),
+                Condition::Gone(target) => $builder.expect_gone(target),
+                Condition::ScreenChange => $builder.expect_screen_change(),
+            }
+            .expect_within($expectation.timeout)
+            .reissue($expectation.reissues);
+        }
+    };
+}
+
+/// Drives several devices in lockstep.
+///
+/// Every action is sent to the leader and replayed on all followers at the
+/// same time. Point targets are given in the leader's coordinates and
+/// rescaled to each follower's resolution; image targets are searched on
+/// each device independently.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rust_droid::{Droid, DroidConfig, Target, mirror::Mirror};
+/// let leader = Droid::new(DroidConfig::default().serial("emulator-5554".into()))?;
+/// let follower = Droid::new(DroidConfig::default().serial("R58M123456".into()))?;
+/// let mut mirror = Mirror::new(leader, vec![follower]);
+///
+/// let report = mirror.touch(Target::from("login_button.png")).execute();
+/// if report.diverged() {
+///     for failure in report.failures() {
+///         eprintln!("{}: {:?}", failure.serial, failure.result);
+///     }
+/// }
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct Mirror {
+    droids: Vec<Droid>,
+}
+
+impl Mirror {
+    pub fn new(leader: Droid, followers: Vec<Droid>) -> Self {
+        let mut droids = Vec::with_capacity(followers.len() + 1);
+        droids.push(leader);
+        droids.extend(followers);
+        Self { droids }
+    }
+
+    /// Returns all devices, the leader first.
+    pub fn droids(&mut self) -> &mut [Droid] {
+        &mut self.droids
+    }
+
+    /// Gives the devices back, the leader first.
+    pub fn into_inner(self) -> Vec<Droid> {
+        self.droids
+    }
+
+    pub fn touch(&mut self, target: Target) -> MirrorTouchBuilder<'_> {
+        MirrorTouchBuilder {
+            mirror: self,
+            target,
+            times: None,
+            duration: None,
+            threshold: None,
+            search_rect: None,
+            retry: None,
+            expectation: Expectation::default(),
+        }
+    }
+
+    pub fn swipe(&mut self, start: Target, end: Target) -> MirrorSwipeBuilder<'_> {
+        MirrorSwipeBuilder {
+            mirror: self,
+            start,
+            end,
+            duration: None,
+            threshold: None,
+            search_rect: None,
+            retry: None,
+            expectation: Expectation::default(),
+        }
+    }
+
+    pub fn text(&mut self, text: &str) -> MirrorTextBuilder<'_> {
+        MirrorTextBuilder {
+            mirror: self,
+            text: text.to_string(),
+        }
+    }
+
+    pub fn keyevent(&mut self, key_code: KeyCode) -> MirrorKeyeventBuilder<'_> {
+        MirrorKeyeventBuilder {
+            mirror: self,
+            key_code,
+            times: None,
+        }
+    }
+
+    /// Runs `action` on every device in parallel and collects the outcomes.
+    fn broadcast<F>(&mut self, name: &str, action: F) -> MirrorReport
+    where
+        F: Fn(&mut Droid, Rescale) -> Result<()> + Sync,
+    {
+        let leader_size = self.droids[0]
+            .controller
+            .screen_size()
+            .map_err(|e| e.to_string());
+        let serials: Vec<String> = self.droids.iter().map(|d| d.serial().to_string()).collect();
+
+        let results: Vec<_> = thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .droids
+                .iter_mut()
+                .map(|droid| {
+                    let action = &action;
+                    let leader_size = &leader_size;
+                    scope.spawn(move || match leader_size {
+                        Ok(from) => droid
+                            .controller
+                            .screen_size()
+                            .and_then(|to| action(droid, Rescale { from: *from, to })),
+                        Err(e) => Err(DroidError::AdbError(format!(
+                            "Could not read the leader's screen size: {}",
+                            e
+                        ))),
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|handle| handle.join()).collect()
+        });
+
+        let outcomes = serials
+            .into_iter()
+            .zip(results)
+            .map(|(serial, joined)| {
+                let result = joined.unwrap_or_else(|payload| {
+                    Err(DroidError::ScriptPanicked {
+                        serial: serial.clone(),
+                        message: panic_message(payload.as_ref()),
+                    })
+                });
+                DeviceOutcome { serial, result }
+            })
+            .collect();
+
+        let report = MirrorReport {
+            action: name.to_string(),
+            outcomes,
+        };
+        if report.diverged() {
+            for failure in report.failures() {
+                log::warn!(
+                    "Mirrored {} diverged on '{}': {:?}",
+                    name,
+                    failure.serial,
+                    failure.result
+                );
+            }
+        }
+        report
+    }
+}
+
+/// Mirrors `Droid::touch()`; see `TouchBuilder` for the options.
+pub struct MirrorTouchBuilder<'a> {
+    mirror: &'a mut Mirror,
+    target: Target,
+    times: Option<u32>,
+    duration: Option<Duration>,
+    threshold: Option<f32>,
+    search_rect: Option<RelativeRect>,
+    retry: Option<RetryPolicy>,
+    expectation: Expectation,
+}
+
+impl MirrorTouchBuilder<'_> {
+    pub fn times(mut self, count: u32) -> Self {
+        self.times = Some(count);
+        self
+    }
+
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    pub fn threshold(mut self, value: f32) -> Self {
+        self.threshold = Some(value);
+        self
+    }
+
+    pub fn search_in(mut self, rect: RelativeRect) -> Self {
+        self.search_rect = Some(rect);
+        self
+    }
+
+    expect::setters!("touch");
+
+    pub fn execute(self) -> MirrorReport {
+        let Self {
+            mirror,
+            target,
+            times,
+            duration,
+            threshold,
+            search_rect,
+            retry,
+            expectation,
+        } = self;
+        mirror.broadcast("touch", |droid, rescale| {
+            let mut builder = droid.touch(rescale.target(&target));
+            if let Some(times) = times {
+                builder = builder.times(times);
+            }
+            if let Some(duration) = duration {
+                builder = builder.duration(duration);
+            }
+            if let Some(threshold) = threshold {
+                builder = builder.threshold(threshold);
+            }
+            if let Some(rect) = search_rect {
+                builder = builder.search_in(rect);
+            }
+            forward_checks!(builder, &retry, expectation, rescale);
+            builder.execute().map(|_| ())
+        })
+    }
+}
+
+/// Mirrors `Droid::swipe()`; see `SwipeBuilder` for the options.
+pub struct MirrorSwipeBuilder<'a> {
+    mirror: &'a mut Mirror,
+    start: Target,
+    end: Target,
+    duration: Option<Duration>,
+    threshold: Option<f32>,
+    search_rect: Option<RelativeRect>,
+    retry: Option<RetryPolicy>,
+    expectation: Expectation,
+}
+
+impl MirrorSwipeBuilder<'_> {
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    pub fn threshold(mut self, value: f32) -> Self {
+        self.threshold = Some(value);
+        self
+    }
+
+    /// Restricts the search for image targets to `rect` on every device.
+    pub fn search_in(mut self, rect: RelativeRect) -> Self {
+        self.search_rect = Some(rect);
+        self
+    }
+
+    expect::setters!("swipe");
+
+    pub fn execute(self) -> MirrorReport {
+        let Self {
+            mirror,
+            start,
+            end,
+            duration,
+            threshold,
+            search_rect,
+            retry,
+            expectation,
+        } = self;
+        mirror.broadcast("swipe", |droid, rescale| {
+            let mut builder = droid.swipe(rescale.target(&start), rescale.target(&end));
+            if let Some(duration) = duration {
+                builder = builder.duration(duration);
+            }
+            if let Some(threshold) = threshold {
+                builder = builder.threshold(threshold);
+            }
+            if let Some(rect) = search_rect {
+                builder = builder.search_start_in(rect).search_end_in(rect);
+            }
+            forward_checks!(builder, &retry, expectation, rescale);
+            builder.execute().map(|_| ())
+        })
+    }
+}
+
+/// Mirrors `Droid::text()`.
+pub struct MirrorTextBuilder<'a> {
+    mirror: &'a mut Mirror,
+    text: String,
+}
+
+impl MirrorTextBuilder<'_> {
+    pub fn execute(self) -> MirrorReport {
+        let Self { mirror, text } = self;
+        mirror.broadcast("text", |droid, _| droid.text(&text).execute().map(|_| ()))
+    }
+}
+
+/// Mirrors `Droid::keyevent()`.
+pub struct MirrorKeyeventBuilder<'a> {
+    mirror: &'a mut Mirror,
+    key_code: KeyCode,
+    times: Option<u32>,
+}
+
+impl MirrorKeyeventBuilder<'_> {
+    pub fn times(mut self, count: u32) -> Self {
+        self.times = Some(count);
+        self
+    }
+
+    pub fn execute(self) -> MirrorReport {
+        let Self {
+            mirror,
+            key_code,
+            times,
+        } = self;
+        mirror.broadcast("keyevent", |droid, _| {
+            let mut builder = droid.keyevent(key_code);
+            if let Some(times) = times {
+                builder = builder.times(times);
+            }
+            builder.execute().map(|_| ())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rescale_maps_points_between_resolutions() {
+        let rescale = Rescale {
+            from: (1080, 1920),
+            to: (720, 1280),
+        };
+        let Target::Point(p) = rescale.target(&Target::Point(Point::new(540, 1920))) else {
+            panic!("expected a point");
+        };
+        assert_eq!((p.x, p.y), (360, 1280));
+
+        let image = Target::from("button.png");
+        assert!(matches!(rescale.target(&image), Target::Image(_)));
+    }
+
+    #[test]
+    fn rescale_keeps_the_leader_orientation() {
+        // A landscape leader mirrored onto a landscape follower.
+        let rescale = Rescale {
+            from: (1920, 1080),
+            to: (2400, 1080),
+        };
+        let Target::Point(p) = rescale.target(&Target::Point(Point::new(960, 540))) else {
+            panic!("expected a point");
+        };
+        assert_eq!((p.x, p.y), (1200, 540));
+    }
+
+    fn report(results: Vec<Result<()>>) -> MirrorReport {
+        MirrorReport {
+            action: "touch".to_string(),
+            outcomes: results
+                .into_iter()
+                .enumerate()
+                .map(|(i, result)| DeviceOutcome {
+                    serial: format!("device-{}", i),
+                    result,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn diverged_only_when_some_devices_fail() {
+        let all_ok = report(vec![Ok(()), Ok(())]);
+        assert!(all_ok.all_succeeded());
+        assert!(!all_ok.diverged());
+
+        let mixed = report(vec![Ok(()), Err(DroidError::Timeout(Duration::ZERO))]);
+        assert!(mixed.diverged());
+        let failed: Vec<_> = mixed.failures().map(|o| o.serial.as_str()).collect();
+        assert_eq!(failed, ["device-1"]);
+
+        let all_failed = report(vec![
+            Err(DroidError::Timeout(Duration::ZERO)),
+            Err(DroidError::Timeout(Duration::ZERO)),
+        ]);
+        assert!(!all_failed.diverged());
+        assert!(all_failed.into_result().is_err());
+    }
+}
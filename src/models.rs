@@ -1,5 +1,6 @@
 use crate::common::point::Point;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 /// Represents the target of an operation, which can be a precise coordinate
 /// or an image that needs to be located on the screen.
@@ -46,6 +47,165 @@ pub enum KeyCode {
     Enter = 66,
 }
 
+/// A direction on the screen, used by scroll gestures.
+///
+/// The direction describes the movement of the finger: `Up` drags the content
+/// upwards and reveals what is below it, like flicking to the next video in a feed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// The rotation of the display from its natural orientation, clockwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    /// The natural orientation, portrait on phones.
+    Rotation0,
+    Rotation90,
+    Rotation180,
+    Rotation270,
+}
+
+impl Rotation {
+    /// Converts Android's `Surface.ROTATION_*` value.
+    pub fn from_surface(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(Rotation::Rotation0),
+            1 => Some(Rotation::Rotation90),
+            2 => Some(Rotation::Rotation180),
+            3 => Some(Rotation::Rotation270),
+            _ => None,
+        }
+    }
+
+    /// Whether width and height are swapped compared to the natural orientation.
+    pub fn is_sideways(self) -> bool {
+        matches!(self, Rotation::Rotation90 | Rotation::Rotation270)
+    }
+}
+
+/// Static facts about the connected device, queried once and cached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceInfo {
+    /// The Android API level (`ro.build.version.sdk`).
+    pub sdk_level: u32,
+    /// The display width in pixels, in the natural orientation.
+    pub screen_width: u32,
+    /// The display height in pixels, in the natural orientation.
+    pub screen_height: u32,
+}
+
+/// How screenshots are captured from the device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CaptureStrategy {
+    /// A PNG-encoded framebuffer, decoded on the host. Works everywhere, but
+    /// encoding and decoding the PNG dominates the capture time.
+    #[default]
+    Png,
+    /// Uncompressed `screencap` output. Transfers more data, but skips PNG
+    /// encoding and decoding entirely. Requires a binary-safe shell (Android 7+).
+    Raw,
+}
+
+/// How touch gestures are injected into the device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InputInjection {
+    /// Use the `input` shell tool. Works everywhere, but every command starts
+    /// a new app process on the device.
+    #[default]
+    Input,
+    /// Write kernel input events to the touchscreen with `sendevent` when the
+    /// shell user is permitted to, falling back to `input` otherwise.
+    Sendevent,
+}
+
+/// What to do when the device disappears from the ADB server, e.g. after a
+/// USB glitch or a reboot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    /// How long to wait for the device to come back before failing with
+    /// `DroidError::DeviceDisconnected`. Zero fails immediately.
+    pub timeout: Duration,
+    /// Pause after the device is back online before it is used again, giving
+    /// adbd and the system services time to settle.
+    pub settle: Duration,
+}
+
+impl ReconnectPolicy {
+    /// Never waits for a lost device.
+    pub fn never() -> Self {
+        Self {
+            timeout: Duration::ZERO,
+            settle: Duration::ZERO,
+        }
+    }
+
+    /// Waits up to `timeout` for a lost device to come back.
+    pub fn wait(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            ..Self::default()
+        }
+    }
+
+    /// Sets the pause after the device is back online.
+    pub fn settle(mut self, settle: Duration) -> Self {
+        self.settle = settle;
+        self
+    }
+}
+
+impl Default for ReconnectPolicy {
+    /// Waits up to 30 seconds, then settles for 1 second.
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            settle: Duration::from_secs(1),
+        }
+    }
+}
+
+/// The phase of a touch pointer, as accepted by `input motionevent`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MotionAction {
+    Down,
+    Move,
+    Up,
+}
+
+impl MotionAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MotionAction::Down => "DOWN",
+            MotionAction::Move => "MOVE",
+            MotionAction::Up => "UP",
+        }
+    }
+}
+
+/// A single low-level touch event in a gesture.
+///
+/// `delay` is how long the pointer stays at `point` before the next event is sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MotionEvent {
+    pub action: MotionAction,
+    pub point: Point,
+    pub delay: Duration,
+}
+
+impl MotionEvent {
+    pub fn new(action: MotionAction, point: Point, delay: Duration) -> Self {
+        Self {
+            action,
+            point,
+            delay,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum AppPackages {
     Tiktok,
@@ -71,4 +231,10 @@ impl AppPackages {
     }
 }
 
+impl AsRef<str> for AppPackages {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
 pub const TIKTOK_LIKE_POINT: Point = Point { x: 656, y: 710 };
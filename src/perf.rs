@@ -0,0 +1,530 @@
+use std::fmt::Write as _;
+use std::net::SocketAddrV4;
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use adb_client::{ADBDeviceExt, ADBServer, ADBServerDevice};
+
+use crate::device::quote;
+use crate::error::{DroidError, Result};
+
+/// Options for a performance sampler started with `Droid::start_sampler()`.
+#[derive(Debug, Clone, Copy)]
+pub struct SamplerOptions {
+    /// The time waited after collecting a sample before collecting the next.
+    pub interval: Duration,
+    /// Collect memory usage from `dumpsys meminfo`.
+    pub memory: bool,
+    /// Collect CPU usage from `/proc`.
+    pub cpu: bool,
+    /// Collect frame counts from `dumpsys gfxinfo`.
+    pub frames: bool,
+}
+
+impl Default for SamplerOptions {
+    /// - Interval: 1 second
+    /// - Memory, CPU and frames: collected
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+            memory: true,
+            cpu: true,
+            frames: true,
+        }
+    }
+}
+
+impl SamplerOptions {
+    /// Sets the sampling interval. Collecting a sample takes a few hundred
+    /// milliseconds itself, which adds to the interval.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn memory(mut self, enabled: bool) -> Self {
+        self.memory = enabled;
+        self
+    }
+
+    pub fn cpu(mut self, enabled: bool) -> Self {
+        self.cpu = enabled;
+        self
+    }
+
+    pub fn frames(mut self, enabled: bool) -> Self {
+        self.frames = enabled;
+        self
+    }
+}
+
+/// Memory usage of an app, in kilobytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// Proportional set size: private memory plus a share of shared memory.
+    pub total_pss_kb: u64,
+    pub java_heap_kb: Option<u64>,
+    pub native_heap_kb: Option<u64>,
+}
+
+/// Frames counted by the app's renderer since it started or the stats were reset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameCounts {
+    pub total: u64,
+    pub janky: u64,
+}
+
+/// One sample of an app's resource usage. A value is `None` if it was not
+/// collected or the app was not running.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PerfSample {
+    pub timestamp: SystemTime,
+    /// Time since the sampler started.
+    pub elapsed: Duration,
+    pub memory: Option<MemoryUsage>,
+    /// CPU time used by the app's main process since the previous sample, in
+    /// percent of one core.
+    pub cpu_percent: Option<f32>,
+    pub frames: Option<FrameCounts>,
+}
+
+/// The samples collected by a `PerfSampler`.
+#[derive(Debug, Clone)]
+pub struct PerfReport {
+    pub package: String,
+    pub samples: Vec<PerfSample>,
+}
+
+impl PerfReport {
+    /// Formats the samples as CSV with a header row; missing values are empty.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "timestamp_ms,elapsed_ms,total_pss_kb,java_heap_kb,native_heap_kb,cpu_percent,total_frames,janky_frames\n",
+        );
+        for sample in &self.samples {
+            let memory = sample.memory;
+            let frames = sample.frames;
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{},{},{}",
+                unix_millis(sample.timestamp),
+                sample.elapsed.as_millis(),
+                field(memory.map(|m| m.total_pss_kb)),
+                field(memory.and_then(|m| m.java_heap_kb)),
+                field(memory.and_then(|m| m.native_heap_kb)),
+                field(sample.cpu_percent.map(|cpu| format!("{:.1}", cpu))),
+                field(frames.map(|f| f.total)),
+                field(frames.map(|f| f.janky)),
+            );
+        }
+        csv
+    }
+
+    /// Formats the report as a JSON object with the package and an array of
+    /// samples; missing values are `null`.
+    pub fn to_json(&self) -> String {
+        let null = |value: Option<String>| value.unwrap_or_else(|| "null".to_string());
+        let samples: Vec<String> = self
+            .samples
+            .iter()
+            .map(|sample| {
+                let memory = sample.memory;
+                let frames = sample.frames;
+                format!(
+                    "{{\"timestamp_ms\":{},\"elapsed_ms\":{},\"total_pss_kb\":{},\"java_heap_kb\":{},\"native_heap_kb\":{},\"cpu_percent\":{},\"total_frames\":{},\"janky_frames\":{}}}",
+                    unix_millis(sample.timestamp),
+                    sample.elapsed.as_millis(),
+                    null(memory.map(|m| m.total_pss_kb.to_string())),
+                    null(memory.and_then(|m| m.java_heap_kb).map(|kb| kb.to_string())),
+                    null(memory.and_then(|m| m.native_heap_kb).map(|kb| kb.to_string())),
+                    null(sample.cpu_percent.map(|cpu| format!("{:.1}", cpu))),
+                    null(frames.map(|f| f.total.to_string())),
+                    null(frames.map(|f| f.janky.to_string())),
+                )
+            })
+            .collect();
+        // Package names only contain letters, digits, `_` and `.`, so need no escaping.
+        format!(
+            "{{\"package\":\"{}\",\"samples\":[{}]}}",
+            self.package,
+            samples.join(",")
+        )
+    }
+
+    pub fn write_csv<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::write(path, self.to_csv())?;
+        Ok(())
+    }
+
+    pub fn write_json<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::write(path, self.to_json())?;
+        Ok(())
+    }
+}
+
+/// Samples the resource usage of an app on a background thread.
+///
+/// The sampler uses its own ADB connection, so the script keeps running
+/// while samples are collected. Samples are kept in memory until `stop()`.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rust_droid::{Droid, DroidConfig, perf::SamplerOptions};
+/// let mut droid = Droid::new(DroidConfig::default())?;
+/// let sampler = droid.start_sampler("com.example.shop", SamplerOptions::default())?;
+/// // ... run the scenario ...
+/// let report = sampler.stop();
+/// report.write_csv("shop_perf.csv")?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct PerfSampler {
+    stop: Option<Sender<()>>,
+    worker: Option<JoinHandle<Vec<PerfSample>>>,
+    package: String,
+}
+
+impl PerfSampler {
+    pub(crate) fn start(
+        adb_addr: SocketAddrV4,
+        serial: &str,
+        package: &str,
+        options: SamplerOptions,
+    ) -> Result<Self> {
+        let mut device = ADBServer::new(adb_addr)
+            .get_device_by_name(serial)
+            .map_err(|e| DroidError::AdbError(e.to_string()))?;
+        let (stop, stopped) = mpsc::channel::<()>();
+        let worker_package = package.to_string();
+        let worker = thread::Builder::new()
+            .name("droid-sampler".to_string())
+            .spawn(move || {
+                let start = Instant::now();
+                let mut previous_cpu = None;
+                let mut samples = Vec::new();
+                loop {
+                    let sample = collect(
+                        &mut device,
+                        &worker_package,
+                        &options,
+                        start,
+                        &mut previous_cpu,
+                    );
+                    samples.push(sample);
+                    match stopped.recv_timeout(options.interval) {
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+                log::debug!(
+                    "Sampler of {} collected {} samples",
+                    worker_package,
+                    samples.len()
+                );
+                samples
+            })?;
+
+        log::info!("Sampling {} every {:?}", package, options.interval);
+        Ok(Self {
+            stop: Some(stop),
+            worker: Some(worker),
+            package: package.to_string(),
+        })
+    }
+
+    /// Whether the background sampling is still running.
+    pub fn is_running(&self) -> bool {
+        self.worker
+            .as_ref()
+            .is_some_and(|worker| !worker.is_finished())
+    }
+
+    /// Stops sampling and returns the collected samples.
+    ///
+    /// This waits for a sample that is being collected to finish.
+    pub fn stop(mut self) -> PerfReport {
+        self.stop.take();
+        let samples = self
+            .worker
+            .take()
+            .and_then(|worker| worker.join().ok())
+            .unwrap_or_default();
+        PerfReport {
+            package: self.package.clone(),
+            samples,
+        }
+    }
+}
+
+impl Drop for PerfSampler {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Process and system CPU time in clock ticks at the previous sample.
+#[derive(Debug, Clone, Copy)]
+struct CpuTicks {
+    process: u64,
+    total: u64,
+    cores: u32,
+}
+
+fn collect(
+    device: &mut ADBServerDevice,
+    package: &str,
+    options: &SamplerOptions,
+    start: Instant,
+    previous_cpu: &mut Option<CpuTicks>,
+) -> PerfSample {
+    let mut sample = PerfSample {
+        timestamp: SystemTime::now(),
+        elapsed: start.elapsed(),
+        memory: None,
+        cpu_percent: None,
+        frames: None,
+    };
+    let package = quote(package);
+    let mut shell = |command: String| {
+        let mut output = Vec::new();
+        match device.shell_command(&[&command], &mut output) {
+            Ok(()) => Some(String::from_utf8_lossy(&output).into_owned()),
+            Err(e) => {
+                log::warn!("Sampling '{}' failed: {}", command, e);
+                None
+            }
+        }
+    };
+
+    if options.cpu {
+        let ticks = shell(format!(
+            "for pid in $(pidof {}); do cat /proc/$pid/stat; done; grep ^cpu /proc/stat",
+            package
+        ))
+        .and_then(|output| parse_cpu_ticks(&output));
+        if let (Some(before), Some(now)) = (*previous_cpu, ticks) {
+            sample.cpu_percent = cpu_percent(before, now);
+        }
+        *previous_cpu = ticks;
+    }
+    if options.memory {
+        sample.memory =
+            shell(format!("dumpsys meminfo {}", package)).and_then(|output| parse_meminfo(&output));
+    }
+    if options.frames {
+        sample.frames = shell(format!("dumpsys gfxinfo {} framestats", package))
+            .and_then(|output| parse_frame_counts(&output));
+    }
+    sample
+}
+
+/// Parses `/proc/<pid>/stat` lines followed by the `cpu` lines of `/proc/stat`.
+///
+/// ```text
+/// 4242 (com.example.shop) S 612 612 0 0 -1 1077952832 50611 0 ... 1530 412 0 0 ...
+/// cpu  210942 7562 163410 3953122 3398 0 4071 0 0 0
+/// cpu0 34815 1276 34162 486810 488 0 2150 0 0 0
+/// ```
+fn parse_cpu_ticks(output: &str) -> Option<CpuTicks> {
+    let mut process = None;
+    let mut total = None;
+    let mut cores = 0;
+    for line in output.lines() {
+        if let Some(rest) = line.strip_prefix("cpu ") {
+            total = Some(
+                rest.split_whitespace()
+                    .filter_map(|v| v.parse::<u64>().ok())
+                    .sum(),
+            );
+        } else if line.starts_with("cpu") {
+            cores += 1;
+        } else if let Some((_, rest)) = line.rsplit_once(") ") {
+            // utime and stime are fields 14 and 15; the rest starts at field 3.
+            let fields: Vec<&str> = rest.split_whitespace().collect();
+            let utime: u64 = fields.get(11)?.parse().ok()?;
+            let stime: u64 = fields.get(12)?.parse().ok()?;
+            *process.get_or_insert(0) += utime + stime;
+        }
+    }
+    Some(CpuTicks {
+        process: process?,
+        total: total?,
+        cores: cores.max(1),
+    })
+}
+
+fn cpu_percent(before: CpuTicks, now: CpuTicks) -> Option<f32> {
+    // A restarted process starts counting from zero again.
+    let process = now.process.checked_sub(before.process)?;
+    let total = now.total.checked_sub(before.total)?;
+    if total == 0 {
+        return None;
+    }
+    Some(process as f32 * 100.0 * now.cores as f32 / total as f32)
+}
+
+/// Parses `dumpsys meminfo <package>`.
+///
+/// ```text
+///  App Summary
+///                        Pss(KB)                        Rss(KB)
+///                         ------                         ------
+///            Java Heap:    12524                          27340
+///          Native Heap:    20364                          21648
+///   ...
+///                TOTAL PSS:   101513            TOTAL RSS:   186164       TOTAL SWAP PSS:       63
+/// ```
+///
+/// Older versions print a `TOTAL` row in the table instead of `TOTAL PSS:`.
+fn parse_meminfo(output: &str) -> Option<MemoryUsage> {
+    let first_number = |text: &str| {
+        text.split_whitespace()
+            .find_map(|value| value.parse::<u64>().ok())
+    };
+    let mut total_pss_kb = None;
+    let mut java_heap_kb = None;
+    let mut native_heap_kb = None;
+    for line in output.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("TOTAL PSS:") {
+            total_pss_kb = first_number(rest);
+        } else if let Some(rest) = line.strip_prefix("TOTAL ") {
+            total_pss_kb = total_pss_kb.or_else(|| first_number(rest));
+        } else if let Some(rest) = line.strip_prefix("Java Heap:") {
+            java_heap_kb = java_heap_kb.or_else(|| first_number(rest));
+        } else if let Some(rest) = line.strip_prefix("Native Heap:") {
+            native_heap_kb = native_heap_kb.or_else(|| first_number(rest));
+        }
+    }
+    Some(MemoryUsage {
+        total_pss_kb: total_pss_kb?,
+        java_heap_kb,
+        native_heap_kb,
+    })
+}
+
+/// Parses the summary of `dumpsys gfxinfo <package>`.
+///
+/// ```text
+/// Total frames rendered: 1873
+/// Janky frames: 94 (5.02%)
+/// ```
+pub(crate) fn parse_frame_counts(output: &str) -> Option<FrameCounts> {
+    let count = |prefix: &str| {
+        output.lines().find_map(|line| {
+            let rest = line.trim().strip_prefix(prefix)?;
+            rest.split_whitespace().next()?.parse().ok()
+        })
+    };
+    Some(FrameCounts {
+        total: count("Total frames rendered:")?,
+        janky: count("Janky frames:")?,
+    })
+}
+
+fn field<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+fn unix_millis(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpu_ticks_of_all_processes() {
+        let output = "\
+4242 (com.example.shop) S 612 612 0 0 -1 1077952832 50611 0 12 0 1530 412 0 0 20 0
+4260 (com.example.shop:remote service) S 612 612 0 0 -1 1077952832 811 0 0 0 100 8 0 0 20 0
+cpu  210942 7562 163410 3953122 3398 0 4071 0 0 0
+cpu0 34815 1276 34162 486810 488 0 2150 0 0 0
+cpu1 34815 1276 34162 486810 488 0 2150 0 0 0
+";
+        let ticks = parse_cpu_ticks(output).unwrap();
+        assert_eq!(ticks.process, 1530 + 412 + 100 + 8);
+        assert_eq!(ticks.total, 210942 + 7562 + 163410 + 3953122 + 3398 + 4071);
+        assert_eq!(ticks.cores, 2);
+
+        let later = CpuTicks {
+            process: ticks.process + 50,
+            total: ticks.total + 200,
+            ..ticks
+        };
+        assert_eq!(cpu_percent(ticks, later), Some(50.0));
+        assert_eq!(cpu_percent(later, ticks), None);
+    }
+
+    #[test]
+    fn cpu_ticks_without_process() {
+        let output = "cpu  210942 7562 163410 3953122 3398 0 4071 0 0 0\n";
+        assert!(parse_cpu_ticks(output).is_none());
+    }
+
+    #[test]
+    fn meminfo_summary() {
+        let output = "\
+ App Summary
+                       Pss(KB)                        Rss(KB)
+                        ------                         ------
+           Java Heap:    12524                          27340
+         Native Heap:    20364                          21648
+                Code:    25172                          71248
+               TOTAL PSS:   101513            TOTAL RSS:   186164       TOTAL SWAP PSS:       63
+";
+        assert_eq!(
+            parse_meminfo(output),
+            Some(MemoryUsage {
+                total_pss_kb: 101513,
+                java_heap_kb: Some(12524),
+                native_heap_kb: Some(20364),
+            })
+        );
+    }
+
+    #[test]
+    fn meminfo_total_row() {
+        let output = "\
+                   Pss  Private  Private  SwapPss     Heap     Heap     Heap
+                 Total    Dirty    Clean    Dirty     Size    Alloc     Free
+                ------   ------   ------   ------   ------   ------   ------
+  Native Heap     9820     9764        0        0    16384    13112     3271
+  Dalvik Heap     4370     4300        0        0    12402     6201     6201
+        TOTAL    48811    21532     9868        0    28786    19313     9472
+";
+        assert_eq!(
+            parse_meminfo(output),
+            Some(MemoryUsage {
+                total_pss_kb: 48811,
+                java_heap_kb: None,
+                native_heap_kb: None,
+            })
+        );
+        assert_eq!(parse_meminfo("No process found for: com.example\n"), None);
+    }
+
+    #[test]
+    fn frame_counts() {
+        let output = "\
+Stats since: 1234567890ns
+Total frames rendered: 1873
+Janky frames: 94 (5.02%)
+";
+        assert_eq!(
+            parse_frame_counts(output),
+            Some(FrameCounts {
+                total: 1873,
+                janky: 94,
+            })
+        );
+        assert_eq!(parse_frame_counts("Total frames rendered: 12\n"), None);
+    }
+}
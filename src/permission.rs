@@ -0,0 +1,582 @@
+use std::fmt;
+
+use crate::device::{DeviceController, UiNode};
+use crate::error::{DroidError, Result};
+
+/// Packages that show the runtime permission dialog, depending on the
+/// Android version and vendor.
+const DIALOG_PACKAGES: [&str; 3] = [
+    "com.android.permissioncontroller",
+    "com.google.android.permissioncontroller",
+    "com.android.packageinstaller",
+];
+
+/// The platform's runtime permission groups. A dialog asks for one group at
+/// a time, however many of its permissions the app requested.
+const PERMISSION_GROUPS: [(&str, &[&str]); 15] = [
+    ("CALENDAR", &["READ_CALENDAR", "WRITE_CALENDAR"]),
+    (
+        "CALL_LOG",
+        &["READ_CALL_LOG", "WRITE_CALL_LOG", "PROCESS_OUTGOING_CALLS"],
+    ),
+    ("CAMERA", &["CAMERA"]),
+    (
+        "CONTACTS",
+        &["READ_CONTACTS", "WRITE_CONTACTS", "GET_ACCOUNTS"],
+    ),
+    (
+        "LOCATION",
+        &[
+            "ACCESS_FINE_LOCATION",
+            "ACCESS_COARSE_LOCATION",
+            "ACCESS_BACKGROUND_LOCATION",
+        ],
+    ),
+    ("MICROPHONE", &["RECORD_AUDIO"]),
+    (
+        "PHONE",
+        &[
+            "READ_PHONE_STATE",
+            "READ_PHONE_NUMBERS",
+            "CALL_PHONE",
+            "ANSWER_PHONE_CALLS",
+            "ADD_VOICEMAIL",
+            "USE_SIP",
+            "ACCEPT_HANDOVER",
+        ],
+    ),
+    ("SENSORS", &["BODY_SENSORS", "BODY_SENSORS_BACKGROUND"]),
+    (
+        "SMS",
+        &[
+            "SEND_SMS",
+            "RECEIVE_SMS",
+            "READ_SMS",
+            "RECEIVE_WAP_PUSH",
+            "RECEIVE_MMS",
+        ],
+    ),
+    (
+        "STORAGE",
+        &[
+            "READ_EXTERNAL_STORAGE",
+            "WRITE_EXTERNAL_STORAGE",
+            "ACCESS_MEDIA_LOCATION",
+        ],
+    ),
+    ("ACTIVITY_RECOGNITION", &["ACTIVITY_RECOGNITION"]),
+    (
+        "NEARBY_DEVICES",
+        &[
+            "BLUETOOTH_SCAN",
+            "BLUETOOTH_CONNECT",
+            "BLUETOOTH_ADVERTISE",
+            "NEARBY_WIFI_DEVICES",
+            "UWB_RANGING",
+        ],
+    ),
+    ("NOTIFICATIONS", &["POST_NOTIFICATIONS"]),
+    (
+        "READ_MEDIA_VISUAL",
+        &[
+            "READ_MEDIA_IMAGES",
+            "READ_MEDIA_VIDEO",
+            "READ_MEDIA_VISUAL_USER_SELECTED",
+        ],
+    ),
+    ("READ_MEDIA_AURAL", &["READ_MEDIA_AUDIO"]),
+];
+
+/// Returns the group `permission` is asked for in, e.g. `LOCATION` for
+/// `android.permission.ACCESS_FINE_LOCATION`. A permission outside the
+/// platform groups, e.g. one defined by an app, forms a group of its own.
+fn permission_group(permission: &str) -> &str {
+    let Some(name) = permission.strip_prefix("android.permission.") else {
+        return permission;
+    };
+    PERMISSION_GROUPS
+        .iter()
+        .find(|(_, members)| members.contains(&name))
+        .map_or(permission, |(group, _)| group)
+}
+
+/// Phrases of the platform's English dialog messages that name the group
+/// asked for, e.g. "Allow Maps to access this device's location?".
+const MESSAGE_HINTS: [(&str, &str); 16] = [
+    ("calendar", "CALENDAR"),
+    ("call logs", "CALL_LOG"),
+    ("take pictures", "CAMERA"),
+    ("contacts", "CONTACTS"),
+    ("location", "LOCATION"),
+    ("record audio", "MICROPHONE"),
+    ("phone calls", "PHONE"),
+    ("sensor data", "SENSORS"),
+    ("sms messages", "SMS"),
+    ("photos, media", "STORAGE"),
+    ("photos and media", "STORAGE"),
+    ("physical activity", "ACTIVITY_RECOGNITION"),
+    ("nearby devices", "NEARBY_DEVICES"),
+    ("notifications", "NOTIFICATIONS"),
+    ("photos and videos", "READ_MEDIA_VISUAL"),
+    ("music and audio", "READ_MEDIA_AURAL"),
+];
+
+/// Dialog views that only appear for one group, e.g. the precise/approximate
+/// selector of location dialogs.
+const VIEW_HINTS: [(&str, &str); 1] = [("permission_location_accuracy", "LOCATION")];
+
+/// Returns the groups the dialog's `message` and view `ids` point to.
+///
+/// Messages are localized, so this finds nothing on devices in other
+/// languages; app names in the message can also add false hints.
+fn hinted_groups(message: &str, ids: &[String]) -> Vec<&'static str> {
+    let message = message.to_lowercase();
+    let by_message = MESSAGE_HINTS
+        .iter()
+        .filter(|(phrase, _)| message.contains(phrase));
+    let by_view = VIEW_HINTS
+        .iter()
+        .filter(|(prefix, _)| ids.iter().any(|id| id.starts_with(prefix)));
+    let mut groups: Vec<&str> = by_message.chain(by_view).map(|(_, group)| *group).collect();
+    groups.sort_unstable();
+    groups.dedup();
+    groups
+}
+
+/// Returns the groups an open dialog may be asking for: those of `pending`
+/// that `hinted` agrees with, all of `pending` if the hints name none of
+/// them, or the hints alone if nothing is pending.
+fn candidate_groups<'a>(pending: &'a [String], hinted: &[&'a str]) -> Vec<&'a str> {
+    let mut groups: Vec<&str> = pending.iter().map(|p| permission_group(p)).collect();
+    groups.sort_unstable();
+    groups.dedup();
+    if groups.is_empty() {
+        return hinted.to_vec();
+    }
+    let agreed: Vec<&str> = groups
+        .iter()
+        .copied()
+        .filter(|group| hinted.contains(group))
+        .collect();
+    if agreed.is_empty() { groups } else { agreed }
+}
+
+/// How to answer a runtime permission dialog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermissionChoice {
+    /// "Allow", or the closest option the dialog offers, e.g. "While using
+    /// the app" for location on Android 11+.
+    Allow,
+    /// "While using the app", or plain "Allow" for permissions that have no
+    /// foreground-only option.
+    AllowWhileUsing,
+    /// "Don't allow", or "Deny & don't ask again" when that is the only way to deny.
+    Deny,
+}
+
+impl PermissionChoice {
+    /// Orders choices from `Allow` to `Deny`.
+    fn restrictiveness(self) -> u8 {
+        match self {
+            PermissionChoice::Allow => 0,
+            PermissionChoice::AllowWhileUsing => 1,
+            PermissionChoice::Deny => 2,
+        }
+    }
+
+    /// The dialog button ids to try, in order of preference.
+    fn buttons(self) -> &'static [&'static str] {
+        match self {
+            PermissionChoice::Allow => &[
+                "permission_allow_button",
+                "permission_allow_always_button",
+                "permission_allow_foreground_only_button",
+                "permission_allow_one_time_button",
+            ],
+            PermissionChoice::AllowWhileUsing => &[
+                "permission_allow_foreground_only_button",
+                "permission_allow_button",
+                "permission_allow_one_time_button",
+            ],
+            PermissionChoice::Deny => &[
+                "permission_deny_button",
+                "permission_deny_and_dont_ask_again_button",
+            ],
+        }
+    }
+}
+
+impl fmt::Display for PermissionChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PermissionChoice::Allow => write!(f, "allow"),
+            PermissionChoice::AllowWhileUsing => write!(f, "allow while using"),
+            PermissionChoice::Deny => write!(f, "deny"),
+        }
+    }
+}
+
+/// Decides how runtime permission dialogs are answered.
+///
+/// A rule for the permission being requested wins over a rule for the
+/// requesting package, which wins over the default.
+///
+/// The dialog names its permission only in its localized message, so the
+/// permission group is inferred from the requester's runtime permissions
+/// that are neither granted nor fixed, narrowed down by the message and the
+/// dialog's views where they are recognised. If that leaves several groups,
+/// or none while permission rules are set, the most restrictive choice any
+/// of the possible groups would get is used, so a rule is never bypassed.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rust_droid::{Droid, DroidConfig};
+/// # use rust_droid::permission::{PermissionChoice, PermissionPolicy};
+/// let policy = PermissionPolicy::new(PermissionChoice::Allow)
+///     .permission("android.permission.ACCESS_FINE_LOCATION", PermissionChoice::AllowWhileUsing)
+///     .package("com.example.tracker", PermissionChoice::Deny);
+///
+/// let mut droid = Droid::new(DroidConfig::default())?;
+/// // Answer a dialog that is open right now...
+/// droid.handle_permission_dialog(&policy)?;
+/// // ...or answer dialogs whenever a target or wait is blocked by one.
+/// droid.set_permission_guard(Some(policy));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct PermissionPolicy {
+    pub default: PermissionChoice,
+    pub packages: Vec<(String, PermissionChoice)>,
+    pub permissions: Vec<(String, PermissionChoice)>,
+}
+
+impl Default for PermissionPolicy {
+    /// Allows everything.
+    fn default() -> Self {
+        Self::new(PermissionChoice::Allow)
+    }
+}
+
+impl PermissionPolicy {
+    pub fn new(default: PermissionChoice) -> Self {
+        Self {
+            default,
+            packages: Vec::new(),
+            permissions: Vec::new(),
+        }
+    }
+
+    /// Answers dialogs opened by `package` with `choice`.
+    pub fn package(mut self, package: &str, choice: PermissionChoice) -> Self {
+        self.packages.push((package.to_string(), choice));
+        self
+    }
+
+    /// Answers dialogs asking for `permission`, e.g.
+    /// `android.permission.CAMERA`, with `choice`.
+    ///
+    /// The rule covers the permission's whole group, since that is what the
+    /// dialog asks for: a rule for `ACCESS_FINE_LOCATION` also answers a
+    /// dialog for `ACCESS_COARSE_LOCATION`.
+    pub fn permission(mut self, permission: &str, choice: PermissionChoice) -> Self {
+        self.permissions.push((permission.to_string(), choice));
+        self
+    }
+
+    /// Picks the choice for a dialog opened by `package` while `pending`
+    /// permissions of it are neither granted nor fixed. `message` is the
+    /// dialog's question, which may tell which of them it asks for.
+    pub fn choice_for(
+        &self,
+        package: Option<&str>,
+        pending: &[String],
+        message: &str,
+    ) -> PermissionChoice {
+        let hinted = hinted_groups(message, &[]);
+        self.choose(package, &candidate_groups(pending, &hinted))
+    }
+
+    /// Picks the most restrictive choice any of `groups` would get.
+    fn choose(&self, package: Option<&str>, groups: &[&str]) -> PermissionChoice {
+        let fallback = self
+            .packages
+            .iter()
+            .find(|(name, _)| Some(name.as_str()) == package)
+            .map_or(self.default, |(_, choice)| *choice);
+        let rule = |group: &str| {
+            self.permissions
+                .iter()
+                .find(|(name, _)| permission_group(name) == group)
+                .map(|(_, choice)| *choice)
+        };
+        let choices: Vec<PermissionChoice> = if groups.is_empty() {
+            // Nothing tells which group the dialog asks for.
+            self.permissions
+                .iter()
+                .map(|(_, choice)| *choice)
+                .chain([fallback])
+                .collect()
+        } else {
+            groups
+                .iter()
+                .map(|group| rule(group).unwrap_or(fallback))
+                .collect()
+        };
+        choices
+            .into_iter()
+            .max_by_key(|choice| choice.restrictiveness())
+            .unwrap_or(fallback)
+    }
+}
+
+/// What `Droid::handle_permission_dialog` did.
+#[derive(Debug, Clone)]
+pub struct PermissionDecision {
+    /// The app that requested the permission, if it could be determined.
+    pub package: Option<String>,
+    /// The app's runtime permissions that were neither granted nor fixed
+    /// when the dialog was answered; the dialog asks for one group of them.
+    pub pending: Vec<String>,
+    /// The dialog's question, e.g. "Allow Camera to take pictures and record video?".
+    pub message: String,
+    pub choice: PermissionChoice,
+    /// The id of the button that was tapped.
+    pub button: String,
+}
+
+/// Answers the runtime permission dialog on screen according to `policy`.
+///
+/// Returns `None` without touching anything if no dialog is open.
+pub(crate) fn handle_dialog(
+    controller: &mut DeviceController,
+    policy: &PermissionPolicy,
+) -> Result<Option<PermissionDecision>> {
+    // Checking the focused window is much cheaper than dumping the UI.
+    let Some(window) = controller.focused_window()? else {
+        return Ok(None);
+    };
+    if !DIALOG_PACKAGES.iter().any(|p| window.starts_with(p)) {
+        return Ok(None);
+    }
+
+    let nodes = controller.dump_ui()?;
+    let dialog: Vec<&UiNode> = nodes
+        .iter()
+        .filter(|node| DIALOG_PACKAGES.contains(&node.package.as_str()))
+        .collect();
+    let button_id = |node: &UiNode| {
+        node.resource_id
+            .split_once(":id/")
+            .map(|(_, id)| id.to_string())
+            .unwrap_or_default()
+    };
+    let buttons: Vec<(String, &UiNode)> = dialog
+        .iter()
+        .map(|node| (button_id(node), *node))
+        .filter(|(id, _)| id.starts_with("permission_") && id.ends_with("_button"))
+        .collect();
+    if buttons.is_empty() {
+        return Ok(None);
+    }
+
+    let message = dialog
+        .iter()
+        .find(|node| button_id(node) == "permission_message")
+        .map(|node| node.text.clone())
+        .unwrap_or_default();
+    let package = controller.permission_requester()?;
+    let pending = match &package {
+        Some(package) if !policy.permissions.is_empty() => {
+            controller.pending_permissions(package)?
+        }
+        _ => Vec::new(),
+    };
+    let ids: Vec<String> = dialog.iter().map(|node| button_id(node)).collect();
+    let hinted = hinted_groups(&message, &ids);
+    let choice = policy.choose(package.as_deref(), &candidate_groups(&pending, &hinted));
+
+    let Some((button, node)) = choice
+        .buttons()
+        .iter()
+        .find_map(|id| buttons.iter().find(|(button, _)| button == id))
+    else {
+        return Err(DroidError::PermissionChoiceUnavailable {
+            choice: choice.to_string(),
+            buttons: buttons.into_iter().map(|(id, _)| id).collect(),
+        });
+    };
+
+    log::info!(
+        "Answering permission dialog of {:?} ('{}') with {}",
+        package,
+        message,
+        choice
+    );
+    controller.tap(node.bounds.center())?;
+    Ok(Some(PermissionDecision {
+        package,
+        pending,
+        message,
+        choice,
+        button: button.clone(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn package_rule_wins_over_default() {
+        let policy = PermissionPolicy::new(PermissionChoice::Allow)
+            .package("com.example.tracker", PermissionChoice::Deny);
+        assert_eq!(
+            policy.choice_for(Some("com.example.tracker"), &[], ""),
+            PermissionChoice::Deny
+        );
+        assert_eq!(
+            policy.choice_for(Some("com.example.other"), &[], ""),
+            PermissionChoice::Allow
+        );
+        assert_eq!(policy.choice_for(None, &[], ""), PermissionChoice::Allow);
+    }
+
+    fn pending(permissions: &[&str]) -> Vec<String> {
+        permissions
+            .iter()
+            .map(|name| format!("android.permission.{}", name))
+            .collect()
+    }
+
+    #[test]
+    fn permission_rule_covers_its_group() {
+        let policy = PermissionPolicy::new(PermissionChoice::Allow)
+            .permission(
+                "android.permission.ACCESS_FINE_LOCATION",
+                PermissionChoice::AllowWhileUsing,
+            )
+            .package("com.example.maps", PermissionChoice::Deny);
+        let location = pending(&["ACCESS_FINE_LOCATION", "ACCESS_COARSE_LOCATION"]);
+        assert_eq!(
+            policy.choice_for(Some("com.example.maps"), &location, ""),
+            PermissionChoice::AllowWhileUsing
+        );
+        let coarse = pending(&["ACCESS_COARSE_LOCATION"]);
+        assert_eq!(
+            policy.choice_for(None, &coarse, ""),
+            PermissionChoice::AllowWhileUsing
+        );
+        let camera = pending(&["CAMERA"]);
+        assert_eq!(
+            policy.choice_for(Some("com.example.maps"), &camera, ""),
+            PermissionChoice::Deny
+        );
+    }
+
+    #[test]
+    fn message_picks_the_group_among_several_pending() {
+        let policy = PermissionPolicy::new(PermissionChoice::Allow)
+            .permission("android.permission.CAMERA", PermissionChoice::Deny)
+            .permission(
+                "android.permission.ACCESS_FINE_LOCATION",
+                PermissionChoice::AllowWhileUsing,
+            );
+        let several = pending(&["CAMERA", "RECORD_AUDIO", "ACCESS_FINE_LOCATION"]);
+        let app = Some("com.example.app");
+        assert_eq!(
+            policy.choice_for(
+                app,
+                &several,
+                "Allow App to take pictures and record video?"
+            ),
+            PermissionChoice::Deny
+        );
+        assert_eq!(
+            policy.choice_for(app, &several, "Allow App to access this device's location?"),
+            PermissionChoice::AllowWhileUsing
+        );
+        assert_eq!(
+            policy.choice_for(app, &several, "Allow App to record audio?"),
+            PermissionChoice::Allow
+        );
+    }
+
+    #[test]
+    fn ambiguous_groups_get_the_most_restrictive_choice() {
+        let policy = PermissionPolicy::new(PermissionChoice::Allow)
+            .permission("android.permission.CAMERA", PermissionChoice::Deny)
+            .permission(
+                "android.permission.ACCESS_FINE_LOCATION",
+                PermissionChoice::AllowWhileUsing,
+            );
+        let app = Some("com.example.app");
+        // A localized message names no group.
+        let message = "¿Permitir que App acceda a la cámara?";
+        assert_eq!(
+            policy.choice_for(app, &pending(&["CAMERA", "RECORD_AUDIO"]), message),
+            PermissionChoice::Deny
+        );
+        assert_eq!(
+            policy.choice_for(
+                app,
+                &pending(&["ACCESS_FINE_LOCATION", "RECORD_AUDIO"]),
+                message
+            ),
+            PermissionChoice::AllowWhileUsing
+        );
+        // Unknown pending permissions: any rule could apply.
+        assert_eq!(policy.choice_for(app, &[], message), PermissionChoice::Deny);
+        assert_eq!(
+            policy.choice_for(app, &pending(&["CAMERA"]), message),
+            PermissionChoice::Deny
+        );
+    }
+
+    #[test]
+    fn ambiguity_keeps_a_stricter_package_rule() {
+        let policy = PermissionPolicy::new(PermissionChoice::Allow)
+            .permission("android.permission.CAMERA", PermissionChoice::Allow)
+            .package("com.example.app", PermissionChoice::Deny);
+        let several = pending(&["CAMERA", "RECORD_AUDIO"]);
+        assert_eq!(
+            policy.choice_for(Some("com.example.app"), &several, ""),
+            PermissionChoice::Deny
+        );
+    }
+
+    #[test]
+    fn hints_from_message_and_views() {
+        assert_eq!(
+            hinted_groups("Allow Maps to access this device's location?", &[]),
+            ["LOCATION"]
+        );
+        assert_eq!(
+            hinted_groups("", &["permission_location_accuracy_radio_fine".to_string()]),
+            ["LOCATION"]
+        );
+        assert!(hinted_groups("Permitir acceso", &[]).is_empty());
+
+        let several = pending(&["CAMERA", "RECORD_AUDIO"]);
+        assert_eq!(candidate_groups(&several, &["CAMERA"]), ["CAMERA"]);
+        // Hints that match nothing pending are ignored.
+        assert_eq!(
+            candidate_groups(&several, &["LOCATION"]),
+            ["CAMERA", "MICROPHONE"]
+        );
+        assert_eq!(candidate_groups(&[], &["LOCATION"]), ["LOCATION"]);
+    }
+
+    #[test]
+    fn groups() {
+        assert_eq!(
+            permission_group("android.permission.READ_MEDIA_VIDEO"),
+            "READ_MEDIA_VISUAL"
+        );
+        assert_eq!(permission_group("android.permission.CAMERA"), "CAMERA");
+        assert_eq!(
+            permission_group("com.example.permission.SYNC"),
+            "com.example.permission.SYNC"
+        );
+    }
+}
@@ -0,0 +1,273 @@
+use std::any::Any;
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::error::{DroidError, Result};
+use crate::selector::{self, DeviceSelector};
+use crate::{Droid, DroidConfig};
+
+/// A line recorded by a script while it runs on one device of a pool.
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    /// Time since the script started on this device.
+    pub elapsed: Duration,
+    pub level: log::Level,
+    pub message: String,
+}
+
+/// A per-device log handed to scripts run by a `DevicePool`.
+///
+/// Entries are kept with the device's result and also forwarded to the
+/// `log` crate, prefixed with the device serial.
+pub struct RunLog {
+    serial: String,
+    start: Instant,
+    entries: Vec<LogEntry>,
+}
+
+impl RunLog {
+    fn new(serial: &str) -> Self {
+        Self {
+            serial: serial.to_string(),
+            start: Instant::now(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Records a message at the given level.
+    pub fn log(&mut self, level: log::Level, message: impl Into<String>) {
+        let message = message.into();
+        log::log!(level, "[{}] {}", self.serial, message);
+        self.entries.push(LogEntry {
+            elapsed: self.start.elapsed(),
+            level,
+            message,
+        });
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.log(log::Level::Info, message);
+    }
+
+    pub fn warn(&mut self, message: impl Into<String>) {
+        self.log(log::Level::Warn, message);
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.log(log::Level::Error, message);
+    }
+}
+
+/// The outcome of running a script on one device of a pool.
+#[derive(Debug)]
+pub struct DeviceRun<T> {
+    pub serial: String,
+    pub result: Result<T>,
+    /// Time from connecting to the device until the script returned.
+    pub elapsed: Duration,
+    pub log: Vec<LogEntry>,
+}
+
+/// Runs the same script on several devices in parallel.
+///
+/// Devices are enumerated from the ADB server, optionally filtered by serial
+/// or a `DeviceSelector`, and each gets its own `Droid` on a worker thread.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rust_droid::{DroidConfig, models::KeyCode, pool::DevicePool};
+/// let runs = DevicePool::new(DroidConfig::default())
+///     .property("ro.product.manufacturer", "Google")
+///     .max_concurrency(4)
+///     .run(|droid, log| {
+///         droid.keyevent(KeyCode::Home).execute()?;
+///         log.info("pressed home");
+///         Ok(())
+///     })?;
+/// for run in &runs {
+///     println!("{}: {:?}", run.serial, run.result);
+/// }
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct DevicePool {
+    config: DroidConfig,
+    serials: Option<Vec<String>>,
+    selector: DeviceSelector,
+    max_concurrency: Option<usize>,
+}
+
+impl DevicePool {
+    /// Creates a pool whose `Droid`s share `config`, except for the device serial.
+    ///
+    /// The config's device selector becomes the pool's selector.
+    pub fn new(config: DroidConfig) -> Self {
+        let selector = config.device_selector.clone();
+        Self {
+            config,
+            serials: None,
+            selector,
+            max_concurrency: None,
+        }
+    }
+
+    /// Only uses devices with one of the given serials.
+    pub fn serials<I, S>(mut self, serials: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.serials = Some(serials.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Only uses devices matching `selector`, replacing any previous one.
+    pub fn selector(mut self, selector: DeviceSelector) -> Self {
+        self.selector = selector;
+        self
+    }
+
+    /// Only uses devices whose system property `key` equals `value`.
+    ///
+    /// Can be called several times; all properties must match.
+    pub fn property(mut self, key: &str, value: &str) -> Self {
+        self.selector = self.selector.property(key, value);
+        self
+    }
+
+    /// Limits how many devices run the script at the same time.
+    ///
+    /// Default is one thread per device.
+    pub fn max_concurrency(mut self, count: usize) -> Self {
+        self.max_concurrency = Some(count.max(1));
+        self
+    }
+
+    /// Returns the serials of the devices that pass the filters.
+    pub fn devices(&self) -> Result<Vec<String>> {
+        let devices = selector::discover(self.config.adb_server_addr, &self.selector)?;
+
+        let mut selected = Vec::new();
+        for device in devices {
+            if self
+                .serials
+                .as_ref()
+                .is_some_and(|serials| !serials.contains(&device.serial))
+            {
+                continue;
+            }
+            if !self.selector.matches(&device) {
+                log::info!("Skipping {}", device);
+                continue;
+            }
+            selected.push(device.serial);
+        }
+
+        log::info!("Device pool selected {:?}", selected);
+        Ok(selected)
+    }
+
+    /// The config for one device of the pool.
+    fn config_for(&self, serial: &str) -> DroidConfig {
+        self.config
+            .clone()
+            .select(DeviceSelector::default())
+            .serial(serial.to_string())
+    }
+
+    /// Runs `script` on every selected device and collects the results.
+    ///
+    /// Results are returned in enumeration order. A failing or panicking
+    /// script only affects its own device's result.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error only if the devices cannot be enumerated.
+    pub fn run<T, F>(&self, script: F) -> Result<Vec<DeviceRun<T>>>
+    where
+        T: Send,
+        F: Fn(&mut Droid, &mut RunLog) -> Result<T> + Sync,
+    {
+        let serials = self.devices()?;
+        let total = serials.len();
+        if total == 0 {
+            return Ok(Vec::new());
+        }
+
+        let workers = self.max_concurrency.unwrap_or(total).min(total);
+        let queue = Mutex::new(serials.into_iter().enumerate().collect::<VecDeque<_>>());
+        let results = Mutex::new(Vec::with_capacity(total));
+
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| {
+                    loop {
+                        let next = queue
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .pop_front();
+                        let Some((index, serial)) = next else {
+                            break;
+                        };
+                        let run = self.run_on(serial, &script);
+                        results
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .push((index, run));
+                    }
+                });
+            }
+        });
+
+        let mut results = results.into_inner().unwrap_or_else(PoisonError::into_inner);
+        results.sort_by_key(|(index, _)| *index);
+        Ok(results.into_iter().map(|(_, run)| run).collect())
+    }
+
+    fn run_on<T, F>(&self, serial: String, script: &F) -> DeviceRun<T>
+    where
+        F: Fn(&mut Droid, &mut RunLog) -> Result<T>,
+    {
+        let start = Instant::now();
+        let mut log = RunLog::new(&serial);
+
+        let result = match Droid::new(self.config_for(&serial)) {
+            Ok(mut droid) => {
+                log.info("connected");
+                panic::catch_unwind(AssertUnwindSafe(|| script(&mut droid, &mut log)))
+                    .unwrap_or_else(|payload| {
+                        Err(DroidError::ScriptPanicked {
+                            serial: serial.clone(),
+                            message: panic_message(payload.as_ref()),
+                        })
+                    })
+            }
+            Err(e) => Err(e),
+        };
+
+        match &result {
+            Ok(_) => log.info(format!("finished in {:?}", start.elapsed())),
+            Err(e) => log.error(format!("failed after {:?}: {}", start.elapsed(), e)),
+        }
+
+        DeviceRun {
+            serial,
+            result,
+            elapsed: start.elapsed(),
+            log: log.entries,
+        }
+    }
+}
+
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic payload".to_string()
+    }
+}
@@ -0,0 +1,243 @@
+use std::thread;
+use std::time::Duration;
+
+use crate::error::{ErrorKind, Result};
+
+/// How often, and on which errors, an action is attempted again.
+///
+/// Set it for every action with `DroidConfig::retry` or for a single one with
+/// the builder's `retry` method. A retry repeats the whole action, including
+/// the search for image targets.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use rust_droid::{Droid, DroidConfig, Target, error::ErrorKind, retry::RetryPolicy};
+/// let policy = RetryPolicy::attempts(3)
+///     .delay(Duration::from_millis(200))
+///     .backoff(2.0)
+///     .retry_on(&[ErrorKind::Adb, ErrorKind::ImageNotFound]);
+/// let mut droid = Droid::new(DroidConfig::default().retry(policy))?;
+///
+/// let outcome = droid.touch(Target::from("ok_button.png")).execute()?;
+/// println!("tapped after {} attempts", outcome.attempts);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one. At least `1`.
+    pub max_attempts: u32,
+    /// Pause before the first retry.
+    pub delay: Duration,
+    /// Factor the pause grows by after every retry; `1.0` keeps it constant.
+    pub backoff: f32,
+    /// Upper bound for the pause between attempts.
+    pub max_delay: Duration,
+    /// The kinds of errors that are retried. Any other error fails the action
+    /// immediately.
+    pub retry_on: Vec<ErrorKind>,
+}
+
+impl Default for RetryPolicy {
+    /// Attempts every action once.
+    fn default() -> Self {
+        Self::attempts(1)
+    }
+}
+
+impl RetryPolicy {
+    /// Never retries.
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Attempts an action up to `count` times, pausing 500ms between attempts.
+    ///
+    /// ADB, I/O and image-not-found errors are retried.
+    pub fn attempts(count: u32) -> Self {
+        Self {
+            max_attempts: count.max(1),
+            delay: Duration::from_millis(500),
+            backoff: 1.0,
+            max_delay: Duration::from_secs(10),
+            retry_on: vec![ErrorKind::Adb, ErrorKind::Io, ErrorKind::ImageNotFound],
+        }
+    }
+
+    /// Sets the pause before the first retry.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Multiplies the pause by `factor` after every retry.
+    pub fn backoff(mut self, factor: f32) -> Self {
+        self.backoff = factor.max(1.0);
+        self
+    }
+
+    /// Caps the pause between attempts.
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Sets the kinds of errors that are retried.
+    pub fn retry_on(mut self, kinds: &[ErrorKind]) -> Self {
+        self.retry_on = kinds.to_vec();
+        self
+    }
+
+    /// The pause after the failed attempt number `attempt`, counting from 1.
+    fn delay_after(&self, attempt: u32) -> Duration {
+        let factor = self.backoff.powi(attempt.saturating_sub(1) as i32);
+        let seconds = (self.delay.as_secs_f32() * factor)
+            .min(self.max_delay.as_secs_f32())
+            .max(0.0);
+        Duration::from_secs_f32(seconds)
+    }
+
+    /// Runs `action` until it succeeds, fails with an error that is not
+    /// retried, or runs out of attempts.
+    ///
+    /// On failure the error of the last attempt is returned.
+    pub(crate) fn run<T>(
+        &self,
+        name: &str,
+        mut action: impl FnMut() -> Result<T>,
+    ) -> Result<(T, ActionOutcome)> {
+        let max_attempts = self.max_attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            match action() {
+                Ok(value) => {
+                    if attempt > 1 {
+                        log::info!("{} succeeded on attempt {}", name, attempt);
+                    }
+                    return Ok((
+                        value,
+                        ActionOutcome {
+                            attempts: attempt,
+                            reissues: 0,
+                        },
+                    ));
+                }
+                Err(e) if attempt < max_attempts && self.retry_on.contains(&e.kind()) => {
+                    let delay = self.delay_after(attempt);
+                    log::warn!(
+                        "{} failed on attempt {}/{}, retrying in {:?}: {}",
+                        name,
+                        attempt,
+                        max_attempts,
+                        delay,
+                        e
+                    );
+                    thread::sleep(delay);
+                    attempt += 1;
+                }
+                Err(e) => {
+                    if attempt > 1 {
+                        log::warn!("{} failed after {} attempts: {}", name, attempt, e);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+    }
+}
+
+/// The result of an action executed under a `RetryPolicy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActionOutcome {
+    /// How many times the action was attempted, including the successful one.
+    pub attempts: u32,
+    /// How many times the action was issued again because its expectation,
+    /// e.g. `expect_screen_change`, was not met.
+    pub reissues: u32,
+}
+
+impl ActionOutcome {
+    /// How many times the action was retried after an error.
+    pub fn retries(&self) -> u32 {
+        self.attempts.saturating_sub(self.reissues + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::DroidError;
+
+    fn quick(count: u32) -> RetryPolicy {
+        RetryPolicy::attempts(count).delay(Duration::ZERO)
+    }
+
+    #[test]
+    fn run_retries_until_success() {
+        let mut calls = 0;
+        let (value, outcome) = quick(3)
+            .run("test", || {
+                calls += 1;
+                if calls < 3 {
+                    Err(DroidError::AdbError("flaky".into()))
+                } else {
+                    Ok(calls)
+                }
+            })
+            .unwrap();
+        assert_eq!(value, 3);
+        assert_eq!(outcome.attempts, 3);
+        assert_eq!(outcome.retries(), 2);
+    }
+
+    #[test]
+    fn run_stops_after_max_attempts() {
+        let mut calls = 0;
+        let result: Result<((), ActionOutcome)> = quick(2).run("test", || {
+            calls += 1;
+            Err(DroidError::AdbError(format!("attempt {}", calls)))
+        });
+        assert_eq!(calls, 2);
+        assert!(matches!(result, Err(DroidError::AdbError(m)) if m == "attempt 2"));
+    }
+
+    #[test]
+    fn run_fails_fast_on_errors_not_retried() {
+        let mut calls = 0;
+        let result: Result<((), ActionOutcome)> =
+            quick(5).retry_on(&[ErrorKind::Adb]).run("test", || {
+                calls += 1;
+                Err(DroidError::Timeout(Duration::ZERO))
+            });
+        assert_eq!(calls, 1);
+        assert!(matches!(result, Err(DroidError::Timeout(_))));
+    }
+
+    #[test]
+    fn delay_grows_by_backoff_up_to_the_cap() {
+        let policy = RetryPolicy::attempts(5)
+            .delay(Duration::from_millis(100))
+            .backoff(2.0)
+            .max_delay(Duration::from_millis(300));
+        let millis = |attempt| policy.delay_after(attempt).as_millis();
+        assert_eq!(millis(1), 100);
+        assert_eq!(millis(2), 200);
+        assert_eq!(millis(3), 300);
+        assert_eq!(millis(10), 300);
+    }
+
+    #[test]
+    fn retries_exclude_reissues() {
+        let outcome = ActionOutcome {
+            attempts: 4,
+            reissues: 1,
+        };
+        assert_eq!(outcome.retries(), 2);
+        let first_try = ActionOutcome {
+            attempts: 1,
+            reissues: 0,
+        };
+        assert_eq!(first_try.retries(), 0);
+    }
+}
@@ -0,0 +1,517 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddrV4;
+
+use adb_client::{ADBDeviceExt, ADBServer, DeviceState};
+
+use crate::error::{DroidError, Result};
+
+/// The ADB connection state of a device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// Connected and authorized (`device` in `adb devices`).
+    Online,
+    Offline,
+    /// Connected, but the host key has not been accepted on the device.
+    Unauthorized,
+    /// Any other state reported by the ADB server, e.g. `recovery`.
+    Other(String),
+}
+
+impl From<&DeviceState> for ConnectionState {
+    fn from(state: &DeviceState) -> Self {
+        match state {
+            DeviceState::Device => ConnectionState::Online,
+            DeviceState::Offline => ConnectionState::Offline,
+            DeviceState::Unauthorized => ConnectionState::Unauthorized,
+            other => ConnectionState::Other(format!("{:?}", other).to_lowercase()),
+        }
+    }
+}
+
+impl fmt::Display for ConnectionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectionState::Online => write!(f, "online"),
+            ConnectionState::Offline => write!(f, "offline"),
+            ConnectionState::Unauthorized => write!(f, "unauthorized"),
+            ConnectionState::Other(state) => write!(f, "{}", state),
+        }
+    }
+}
+
+/// What is known about a device attached to the ADB server.
+///
+/// Properties are only read from online devices, and only when a selector
+/// asks for them; otherwise the fields are left empty.
+#[derive(Debug, Clone)]
+pub struct DeviceDescription {
+    pub serial: String,
+    pub state: ConnectionState,
+    /// `ro.product.model`
+    pub model: String,
+    /// `ro.product.manufacturer`
+    pub manufacturer: String,
+    /// `ro.build.version.sdk`
+    pub sdk_level: Option<u32>,
+    /// `ro.product.cpu.abilist`
+    pub abis: Vec<String>,
+    /// Whether the device is an emulator (`ro.kernel.qemu` or `ro.boot.qemu`).
+    pub emulator: bool,
+    /// Additional properties requested with `DeviceSelector::property`.
+    pub properties: HashMap<String, String>,
+}
+
+impl fmt::Display for DeviceDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}", self.serial, self.state)?;
+        if !self.model.is_empty() {
+            write!(f, ", {} {}", self.manufacturer, self.model)?;
+        }
+        if let Some(sdk) = self.sdk_level {
+            write!(f, ", SDK {}", sdk)?;
+        }
+        if self.emulator {
+            write!(f, ", emulator")?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Chooses a device by its properties rather than only its serial.
+///
+/// Every criterion that is set must match. Matching devices are tried in
+/// serial order, so the same selector picks the same device on every run.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rust_droid::{Droid, DroidConfig, selector::DeviceSelector};
+/// let selector = DeviceSelector::default().manufacturer("Google").min_sdk(33).emulator(false);
+/// let droid = Droid::new(DroidConfig::default().select(selector))?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct DeviceSelector {
+    pub serial: Option<String>,
+    pub model: Option<String>,
+    pub manufacturer: Option<String>,
+    pub min_sdk: Option<u32>,
+    pub max_sdk: Option<u32>,
+    pub emulator: Option<bool>,
+    pub abi: Option<String>,
+    pub properties: Vec<(String, String)>,
+    pub state: ConnectionState,
+}
+
+impl Default for DeviceSelector {
+    /// Matches any online device.
+    fn default() -> Self {
+        Self {
+            serial: None,
+            model: None,
+            manufacturer: None,
+            min_sdk: None,
+            max_sdk: None,
+            emulator: None,
+            abi: None,
+            properties: Vec::new(),
+            state: ConnectionState::Online,
+        }
+    }
+}
+
+impl DeviceSelector {
+    /// Matches the exact ADB serial.
+    pub fn serial(mut self, serial: &str) -> Self {
+        self.serial = Some(serial.to_string());
+        self
+    }
+
+    /// Matches `ro.product.model`, ignoring case.
+    pub fn model(mut self, model: &str) -> Self {
+        self.model = Some(model.to_string());
+        self
+    }
+
+    /// Matches `ro.product.manufacturer`, ignoring case.
+    pub fn manufacturer(mut self, manufacturer: &str) -> Self {
+        self.manufacturer = Some(manufacturer.to_string());
+        self
+    }
+
+    /// Requires at least this Android API level.
+    pub fn min_sdk(mut self, level: u32) -> Self {
+        self.min_sdk = Some(level);
+        self
+    }
+
+    /// Requires at most this Android API level.
+    pub fn max_sdk(mut self, level: u32) -> Self {
+        self.max_sdk = Some(level);
+        self
+    }
+
+    /// Requires an emulator (`true`) or a physical device (`false`).
+    pub fn emulator(mut self, emulator: bool) -> Self {
+        self.emulator = Some(emulator);
+        self
+    }
+
+    /// Requires the device to support this ABI, e.g. `arm64-v8a`.
+    pub fn abi(mut self, abi: &str) -> Self {
+        self.abi = Some(abi.to_string());
+        self
+    }
+
+    /// Requires the system property `key` to equal `value`.
+    pub fn property(mut self, key: &str, value: &str) -> Self {
+        self.properties.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Requires the given connection state. Default is `ConnectionState::Online`.
+    pub fn state(mut self, state: ConnectionState) -> Self {
+        self.state = state;
+        self
+    }
+
+    /// Whether any criterion needs properties read from the device.
+    fn needs_properties(&self) -> bool {
+        self.model.is_some()
+            || self.manufacturer.is_some()
+            || self.min_sdk.is_some()
+            || self.max_sdk.is_some()
+            || self.emulator.is_some()
+            || self.abi.is_some()
+            || !self.properties.is_empty()
+    }
+
+    /// Whether the serial and state criteria match, which needs no device access.
+    fn prefilter(&self, serial: &str, state: &ConnectionState) -> bool {
+        *state == self.state && self.serial.as_deref().is_none_or(|s| s == serial)
+    }
+
+    /// Whether `device` satisfies every criterion.
+    pub fn matches(&self, device: &DeviceDescription) -> bool {
+        let eq = |expected: &Option<String>, actual: &str| {
+            expected
+                .as_deref()
+                .is_none_or(|e| e.eq_ignore_ascii_case(actual))
+        };
+        self.prefilter(&device.serial, &device.state)
+            && eq(&self.model, &device.model)
+            && eq(&self.manufacturer, &device.manufacturer)
+            && self
+                .min_sdk
+                .is_none_or(|min| device.sdk_level.is_some_and(|sdk| sdk >= min))
+            && self
+                .max_sdk
+                .is_none_or(|max| device.sdk_level.is_some_and(|sdk| sdk <= max))
+            && self.emulator.is_none_or(|e| e == device.emulator)
+            && self
+                .abi
+                .as_ref()
+                .is_none_or(|abi| device.abis.iter().any(|a| a == abi))
+            && self
+                .properties
+                .iter()
+                .all(|(key, value)| device.properties.get(key) == Some(value))
+    }
+}
+
+impl fmt::Display for DeviceSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut criteria = vec![format!("state={}", self.state)];
+        if let Some(serial) = &self.serial {
+            criteria.push(format!("serial={}", serial));
+        }
+        if let Some(model) = &self.model {
+            criteria.push(format!("model={}", model));
+        }
+        if let Some(manufacturer) = &self.manufacturer {
+            criteria.push(format!("manufacturer={}", manufacturer));
+        }
+        if let Some(min) = self.min_sdk {
+            criteria.push(format!("sdk>={}", min));
+        }
+        if let Some(max) = self.max_sdk {
+            criteria.push(format!("sdk<={}", max));
+        }
+        if let Some(emulator) = self.emulator {
+            criteria.push(format!("emulator={}", emulator));
+        }
+        if let Some(abi) = &self.abi {
+            criteria.push(format!("abi={}", abi));
+        }
+        for (key, value) in &self.properties {
+            criteria.push(format!("{}={}", key, value));
+        }
+        write!(f, "{}", criteria.join(", "))
+    }
+}
+
+/// Lists the devices attached to the ADB server, sorted by serial.
+///
+/// Properties are read only from devices that pass the selector's serial and
+/// state criteria, and only if the selector needs them.
+pub fn discover(
+    adb_addr: SocketAddrV4,
+    selector: &DeviceSelector,
+) -> Result<Vec<DeviceDescription>> {
+    let mut server = ADBServer::new(adb_addr);
+    let mut devices = server
+        .devices()
+        .map_err(|e| DroidError::AdbError(e.to_string()))?;
+    devices.sort_by(|a, b| a.identifier.cmp(&b.identifier));
+
+    let mut descriptions = Vec::with_capacity(devices.len());
+    for device in devices {
+        let state = ConnectionState::from(&device.state);
+        let mut description = DeviceDescription {
+            serial: device.identifier,
+            state,
+            model: String::new(),
+            manufacturer: String::new(),
+            sdk_level: None,
+            abis: Vec::new(),
+            emulator: false,
+            properties: HashMap::new(),
+        };
+
+        if selector.needs_properties()
+            && description.state == ConnectionState::Online
+            && selector.prefilter(&description.serial, &description.state)
+        {
+            let result = read_properties(&mut server, &mut description, selector);
+            if let Err(e) = result {
+                log::warn!(
+                    "Could not read properties of '{}': {}",
+                    description.serial,
+                    e
+                );
+            }
+        }
+        descriptions.push(description);
+    }
+    Ok(descriptions)
+}
+
+/// Returns the first device matching `selector`.
+///
+/// # Errors
+///
+/// Returns `DroidError::DeviceNotFound` if no device is attached, and
+/// `DroidError::NoMatchingDevice`, listing every attached device, if none
+/// matches the selector, including when none has the selector's serial.
+pub fn select(adb_addr: SocketAddrV4, selector: &DeviceSelector) -> Result<DeviceDescription> {
+    let devices = discover(adb_addr, selector)?;
+    if devices.is_empty() {
+        return Err(DroidError::DeviceNotFound);
+    }
+    match devices.iter().find(|d| selector.matches(d)) {
+        Some(device) => Ok(device.clone()),
+        None => Err(DroidError::NoMatchingDevice {
+            selector: selector.to_string(),
+            available: devices.iter().map(ToString::to_string).collect(),
+        }),
+    }
+}
+
+fn read_properties(
+    server: &mut ADBServer,
+    description: &mut DeviceDescription,
+    selector: &DeviceSelector,
+) -> Result<()> {
+    let mut keys = vec![
+        "ro.product.model",
+        "ro.product.manufacturer",
+        "ro.build.version.sdk",
+        "ro.product.cpu.abilist",
+        "ro.kernel.qemu",
+        "ro.boot.qemu",
+    ];
+    keys.extend(selector.properties.iter().map(|(key, _)| key.as_str()));
+
+    // One getprop per line, in a single shell round trip.
+    let command = keys
+        .iter()
+        .map(|key| format!("getprop {}", key))
+        .collect::<Vec<_>>()
+        .join(";");
+    let mut device = server
+        .get_device_by_name(&description.serial)
+        .map_err(|e| DroidError::AdbError(e.to_string()))?;
+    let mut output = Vec::new();
+    device
+        .shell_command(&[&command], &mut output)
+        .map_err(|e| DroidError::AdbError(e.to_string()))?;
+
+    apply_properties(
+        description,
+        &keys,
+        &String::from_utf8_lossy(&output),
+        selector,
+    );
+    Ok(())
+}
+
+/// Fills `description` from the output of one `getprop` per key in `keys`.
+fn apply_properties(
+    description: &mut DeviceDescription,
+    keys: &[&str],
+    output: &str,
+    selector: &DeviceSelector,
+) {
+    let values: HashMap<&str, &str> = keys
+        .iter()
+        .copied()
+        .zip(output.lines().map(str::trim))
+        .collect();
+    let value = |key: &str| values.get(key).copied().unwrap_or_default();
+
+    description.model = value("ro.product.model").to_string();
+    description.manufacturer = value("ro.product.manufacturer").to_string();
+    description.sdk_level = value("ro.build.version.sdk").parse().ok();
+    description.abis = value("ro.product.cpu.abilist")
+        .split(',')
+        .filter(|abi| !abi.is_empty())
+        .map(str::to_string)
+        .collect();
+    description.emulator = value("ro.kernel.qemu") == "1" || value("ro.boot.qemu") == "1";
+    for (key, _) in &selector.properties {
+        description
+            .properties
+            .insert(key.clone(), value(key).to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel() -> DeviceDescription {
+        DeviceDescription {
+            serial: "R58M123456".to_string(),
+            state: ConnectionState::Online,
+            model: "Pixel 7".to_string(),
+            manufacturer: "Google".to_string(),
+            sdk_level: Some(34),
+            abis: vec!["arm64-v8a".to_string()],
+            emulator: false,
+            properties: HashMap::from([("ro.build.type".to_string(), "user".to_string())]),
+        }
+    }
+
+    #[test]
+    fn matches_every_criterion() {
+        let device = pixel();
+        assert!(DeviceSelector::default().matches(&device));
+        assert!(
+            DeviceSelector::default()
+                .serial("R58M123456")
+                .model("pixel 7")
+                .manufacturer("GOOGLE")
+                .min_sdk(34)
+                .max_sdk(34)
+                .emulator(false)
+                .abi("arm64-v8a")
+                .property("ro.build.type", "user")
+                .matches(&device)
+        );
+
+        assert!(
+            !DeviceSelector::default()
+                .serial("emulator-5554")
+                .matches(&device)
+        );
+        assert!(!DeviceSelector::default().min_sdk(35).matches(&device));
+        assert!(!DeviceSelector::default().max_sdk(33).matches(&device));
+        assert!(!DeviceSelector::default().emulator(true).matches(&device));
+        assert!(!DeviceSelector::default().abi("x86_64").matches(&device));
+        assert!(
+            !DeviceSelector::default()
+                .property("ro.build.type", "userdebug")
+                .matches(&device)
+        );
+        assert!(
+            !DeviceSelector::default()
+                .state(ConnectionState::Offline)
+                .matches(&device)
+        );
+    }
+
+    #[test]
+    fn sdk_bounds_need_a_known_level() {
+        let device = DeviceDescription {
+            sdk_level: None,
+            ..pixel()
+        };
+        assert!(!DeviceSelector::default().min_sdk(21).matches(&device));
+    }
+
+    #[test]
+    fn display_lists_set_criteria() {
+        assert_eq!(DeviceSelector::default().to_string(), "state=online");
+        let selector = DeviceSelector::default()
+            .manufacturer("Google")
+            .min_sdk(33)
+            .emulator(false)
+            .property("ro.build.type", "user");
+        assert_eq!(
+            selector.to_string(),
+            "state=online, manufacturer=Google, sdk>=33, emulator=false, ro.build.type=user"
+        );
+    }
+
+    #[test]
+    fn applies_getprop_output_in_key_order() {
+        let selector = DeviceSelector::default().property("ro.build.type", "user");
+        let keys = [
+            "ro.product.model",
+            "ro.product.manufacturer",
+            "ro.build.version.sdk",
+            "ro.product.cpu.abilist",
+            "ro.kernel.qemu",
+            "ro.boot.qemu",
+            "ro.build.type",
+        ];
+        let output = "sdk_gphone64_x86_64\r\nGoogle\n34\nx86_64,arm64-v8a\n\n1\nuserdebug\n";
+        let mut description = DeviceDescription {
+            properties: HashMap::new(),
+            ..pixel()
+        };
+        apply_properties(&mut description, &keys, output, &selector);
+
+        assert_eq!(description.model, "sdk_gphone64_x86_64");
+        assert_eq!(description.manufacturer, "Google");
+        assert_eq!(description.sdk_level, Some(34));
+        assert_eq!(description.abis, ["x86_64", "arm64-v8a"]);
+        assert!(description.emulator);
+        assert_eq!(description.properties["ro.build.type"], "userdebug");
+    }
+
+    #[test]
+    fn missing_getprop_lines_leave_fields_empty() {
+        let keys = [
+            "ro.product.model",
+            "ro.build.version.sdk",
+            "ro.product.cpu.abilist",
+        ];
+        let mut description = DeviceDescription {
+            properties: HashMap::new(),
+            ..pixel()
+        };
+        apply_properties(
+            &mut description,
+            &keys,
+            "Pixel 7\n",
+            &DeviceSelector::default(),
+        );
+
+        assert_eq!(description.model, "Pixel 7");
+        assert_eq!(description.sdk_level, None);
+        assert!(description.abis.is_empty());
+        assert!(!description.emulator);
+    }
+}
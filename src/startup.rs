@@ -0,0 +1,267 @@
+use std::time::{Duration, Instant};
+
+use crate::common::relative_rect::RelativeRect;
+use crate::common::stats::percentile;
+use crate::error::{DroidError, Result};
+use crate::intent::{Intent, LaunchResult};
+use crate::models::KeyCode;
+use crate::{Droid, Target};
+
+/// What state the app is in before each launch of a startup measurement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StartupMode {
+    /// The app is force-stopped, so the process is created from scratch.
+    #[default]
+    Cold,
+    /// The process keeps running, but the activity is finished with Back.
+    ///
+    /// On Android 12+ Back only moves a launcher activity to the background,
+    /// which makes the launch hot; check `LaunchResult::launch_state`.
+    Warm,
+    /// The app is sent to the background with Home.
+    Hot,
+}
+
+/// Summary statistics of one kind of startup time across runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StartupStats {
+    pub min: Duration,
+    pub median: Duration,
+    /// 90% of the runs were at least this fast (nearest rank).
+    pub p90: Duration,
+    pub max: Duration,
+    /// The number of runs that reported this time.
+    pub samples: usize,
+}
+
+impl StartupStats {
+    /// Computes the statistics, or `None` if there are no samples.
+    pub fn from_samples(samples: &[Duration]) -> Option<Self> {
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        Some(Self {
+            min: *sorted.first()?,
+            median: percentile(&sorted, 50)?,
+            p90: percentile(&sorted, 90)?,
+            max: *sorted.last()?,
+            samples: sorted.len(),
+        })
+    }
+}
+
+/// One launch of a startup measurement.
+#[derive(Debug, Clone)]
+pub struct StartupRun {
+    pub launch: LaunchResult,
+    /// Time from the start request until the `visible` target was found.
+    pub visible_after: Option<Duration>,
+}
+
+/// The result of `StartupBuilder::execute()`.
+#[derive(Debug, Clone)]
+pub struct StartupReport {
+    pub mode: StartupMode,
+    pub runs: Vec<StartupRun>,
+    /// `TotalTime` reported by `am start -W`.
+    pub total_time: Option<StartupStats>,
+    /// `WaitTime` reported by `am start -W`.
+    pub wait_time: Option<StartupStats>,
+    /// Time until the `visible` target was found.
+    pub visible: Option<StartupStats>,
+}
+
+/// Builds and executes a startup time measurement.
+///
+/// Every run prepares the app according to the `StartupMode`, launches the
+/// intent with `am start -W` and, if a `visible` target is set, captures
+/// screenshots back to back until the target is found. That time is measured
+/// from the start request, so it is only as precise as one capture.
+///
+/// This struct is created by the `Droid::measure_startup()` method.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rust_droid::{Droid, DroidConfig, Target, intent::Intent};
+/// let mut droid = Droid::new(DroidConfig::default())?;
+/// let report = droid
+///     .measure_startup(Intent::new().component("com.example.shop/.MainActivity"))
+///     .runs(10)
+///     .visible(Target::from("home_feed.png"))
+///     .execute()?;
+/// if let Some(total) = report.total_time {
+///     println!("median {:?}, p90 {:?}", total.median, total.p90);
+/// }
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct StartupBuilder<'a> {
+    droid: &'a mut Droid,
+    intent: Intent,
+    mode: StartupMode,
+    runs: u32,
+    drop_caches: bool,
+    visible: Option<Target>,
+    threshold: Option<f32>,
+    search_rect: Option<RelativeRect>,
+    timeout: Duration,
+}
+
+impl<'a> StartupBuilder<'a> {
+    pub fn new(droid: &'a mut Droid, intent: Intent) -> Self {
+        let timeout = droid.config.default_timeout;
+        Self {
+            droid,
+            intent,
+            mode: StartupMode::Cold,
+            runs: 5,
+            drop_caches: false,
+            visible: None,
+            threshold: None,
+            search_rect: None,
+            timeout,
+        }
+    }
+
+    pub fn mode(mut self, mode: StartupMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Sets how many times the app is launched. Defaults to 5.
+    pub fn runs(mut self, runs: u32) -> Self {
+        self.runs = runs.max(1);
+        self
+    }
+
+    /// Drops the kernel's page cache before each cold start, so the app's
+    /// code is read from storage again. This needs a rooted device.
+    pub fn drop_caches(mut self, enabled: bool) -> Self {
+        self.drop_caches = enabled;
+        self
+    }
+
+    /// Also measures the time until `target` is on screen.
+    pub fn visible(mut self, target: Target) -> Self {
+        self.visible = Some(target);
+        self
+    }
+
+    pub fn threshold(mut self, value: f32) -> Self {
+        self.threshold = Some(value);
+        self
+    }
+
+    pub fn search_in(mut self, rect: RelativeRect) -> Self {
+        self.search_rect = Some(rect);
+        self
+    }
+
+    /// Sets how long to look for the `visible` target in each run.
+    pub fn timeout(mut self, duration: Duration) -> Self {
+        self.timeout = duration;
+        self
+    }
+
+    pub fn execute(mut self) -> Result<StartupReport> {
+        let package = self
+            .intent
+            .target_package()
+            .ok_or_else(|| {
+                DroidError::InvalidTarget(
+                    "a startup measurement needs an intent with a component or package".into(),
+                )
+            })?
+            .to_string();
+        log::info!(
+            "Measuring {:?} startup of {} over {} runs",
+            self.mode,
+            package,
+            self.runs
+        );
+
+        if self.mode != StartupMode::Cold {
+            // Warm and hot starts need a running process to begin with.
+            self.droid.start_activity(&self.intent)?;
+            self.settle();
+        }
+
+        let visible = self.visible.take();
+        let mut runs = Vec::new();
+        for run in 1..=self.runs {
+            self.prepare(&package)?;
+            let start = Instant::now();
+            let launch = self.droid.start_activity(&self.intent)?;
+            let visible_after = match &visible {
+                Some(target) => Some(self.wait_visible(target, start)?),
+                None => None,
+            };
+            log::info!(
+                "Run {}: {:?} start, total {:?}, visible after {:?}",
+                run,
+                launch.launch_state,
+                launch.total_time,
+                visible_after
+            );
+            runs.push(StartupRun {
+                launch,
+                visible_after,
+            });
+            self.settle();
+        }
+
+        let stats = |time: fn(&StartupRun) -> Option<Duration>| {
+            StartupStats::from_samples(&runs.iter().filter_map(time).collect::<Vec<_>>())
+        };
+        Ok(StartupReport {
+            mode: self.mode,
+            total_time: stats(|run| run.launch.total_time),
+            wait_time: stats(|run| run.launch.wait_time),
+            visible: stats(|run| run.visible_after),
+            runs,
+        })
+    }
+
+    /// Brings the app into the state the mode starts from.
+    fn prepare(&mut self, package: &str) -> Result<()> {
+        match self.mode {
+            StartupMode::Cold => {
+                self.droid.controller.force_stop(package)?;
+                if self.drop_caches {
+                    self.droid.controller.drop_caches()?;
+                }
+            }
+            StartupMode::Warm => {
+                self.droid.keyevent(KeyCode::Back).execute()?;
+            }
+            StartupMode::Hot => {
+                self.droid.keyevent(KeyCode::Home).execute()?;
+            }
+        }
+        self.settle();
+        Ok(())
+    }
+
+    /// Captures screens back to back until `target` is found.
+    fn wait_visible(&mut self, target: &Target, start: Instant) -> Result<Duration> {
+        let threshold = self
+            .threshold
+            .unwrap_or(self.droid.config.default_confidence);
+        loop {
+            let (screen, scale) = self.droid.capture()?;
+            match self
+                .droid
+                .resolve_target_on(target, threshold, self.search_rect, &screen, scale)
+            {
+                Ok(_) => return Ok(start.elapsed()),
+                Err(DroidError::ImageNotFound(_)) if start.elapsed() < self.timeout => {}
+                Err(DroidError::ImageNotFound(_)) => return Err(DroidError::Timeout(self.timeout)),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Lets the device finish the previous transition.
+    fn settle(&self) {
+        std::thread::sleep(self.droid.config.default_interval);
+    }
+}
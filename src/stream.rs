@@ -0,0 +1,482 @@
+use std::io::{self, Write};
+use std::net::SocketAddrV4;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use adb_client::{ADBDeviceExt, ADBServer};
+use image::{DynamicImage, RgbImage};
+
+use crate::error::{DroidError, Result};
+
+/// How long dropping a stream waits for the worker after stopping `screenrecord`.
+const STOP_TIMEOUT: Duration = Duration::from_secs(2);
+/// Pause before `screenrecord` is started again after it exited.
+const RESTART_DELAY: Duration = Duration::from_millis(200);
+
+/// Options for a continuous screen capture started with `Droid::start_stream()`.
+#[derive(Debug, Clone, Copy)]
+pub struct StreamOptions {
+    /// The size of the streamed frames relative to the screen (0.0 to 1.0).
+    ///
+    /// Frames are sent uncompressed, so halving the scale quarters the
+    /// bandwidth and roughly quadruples the achievable frame rate.
+    pub scale: f32,
+}
+
+impl Default for StreamOptions {
+    /// - Scale: 0.5
+    fn default() -> Self {
+        Self { scale: 0.5 }
+    }
+}
+
+impl StreamOptions {
+    /// Sets the frame scale. The value will be clamped between 0.1 and 1.0.
+    pub fn scale(mut self, scale: f32) -> Self {
+        self.scale = scale.clamp(0.1, 1.0);
+        self
+    }
+}
+
+/// A single frame received from a `ScreenStream`.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    /// When the frame was fully received on the host.
+    pub timestamp: Instant,
+    /// The frame contents, at `scale` times the screen resolution.
+    pub image: DynamicImage,
+    /// The factor the frame was scaled by relative to the screen.
+    pub scale: f32,
+}
+
+/// A continuous capture of the device screen, decoded on a background thread.
+///
+/// Frames are produced by `screenrecord --output-format=raw-frames`, which
+/// emits an uncompressed RGB frame every time the screen content changes.
+/// Only the newest frame is kept: a consumer that falls behind skips the
+/// frames in between. Iterating over the stream blocks until the next frame
+/// arrives.
+///
+/// Frames keep the orientation the stream was started in; after a rotation
+/// `screenrecord` letterboxes the display into them. `Droid` restarts its
+/// stream when it notices the display rotated.
+pub struct ScreenStream {
+    shared: Arc<Shared>,
+    /// The screen size in pixels when the stream was started.
+    screen: (u32, u32),
+    options: StreamOptions,
+    /// The sequence number of the last frame returned.
+    seen: u64,
+    stop: Arc<AtomicBool>,
+    /// The pid of the running `screenrecord`, or 0 before it reported it.
+    pid: Arc<AtomicU32>,
+    adb_addr: SocketAddrV4,
+    serial: String,
+    worker: Option<JoinHandle<()>>,
+}
+
+/// The newest frame, shared between the worker and the consumer.
+#[derive(Default)]
+struct Shared {
+    slot: Mutex<Slot>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct Slot {
+    frame: Option<Frame>,
+    /// Incremented for every frame; 0 until the first one arrives.
+    sequence: u64,
+    /// Set once the worker exited.
+    closed: bool,
+    /// Why the worker exited, if it was not stopped.
+    error: Option<String>,
+}
+
+impl Slot {
+    /// Returns the current frame and marks it as seen.
+    fn take(&self, seen: &mut u64) -> Option<Frame> {
+        *seen = self.sequence;
+        self.frame.clone()
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Slot> {
+        self.slot.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn publish(&self, frame: Frame) {
+        let mut slot = self.lock();
+        slot.frame = Some(frame);
+        slot.sequence += 1;
+        self.changed.notify_all();
+    }
+
+    fn close(&self, error: Option<String>) {
+        let mut slot = self.lock();
+        slot.closed = true;
+        slot.error = error;
+        self.changed.notify_all();
+    }
+}
+
+impl ScreenStream {
+    pub(crate) fn start(
+        adb_addr: SocketAddrV4,
+        serial: &str,
+        screen: (u32, u32),
+        options: StreamOptions,
+    ) -> Result<Self> {
+        // Encoders require even dimensions.
+        let width = ((screen.0 as f32 * options.scale) as u32) & !1;
+        let height = ((screen.1 as f32 * options.scale) as u32) & !1;
+        let scale = width as f32 / screen.0 as f32;
+        // The shell prints its pid before it becomes `screenrecord`, so the
+        // recording can be stopped even while the screen is static.
+        let command = format!(
+            "echo $$; exec screenrecord --output-format=raw-frames --size {}x{} -",
+            width, height
+        );
+
+        let shared = Arc::new(Shared::default());
+        let stop = Arc::new(AtomicBool::new(false));
+        let pid = Arc::new(AtomicU32::new(0));
+        let mut device = ADBServer::new(adb_addr)
+            .get_device_by_name(serial)
+            .map_err(|e| DroidError::AdbError(e.to_string()))?;
+
+        let worker_shared = Arc::clone(&shared);
+        let worker_stop = Arc::clone(&stop);
+        let worker_pid = Arc::clone(&pid);
+        let worker = thread::Builder::new()
+            .name("droid-stream".to_string())
+            .spawn(move || {
+                // `screenrecord` exits at its time limit; restart it until stopped.
+                let mut error = None;
+                while !worker_stop.load(Ordering::Relaxed) {
+                    let mut writer = FrameWriter {
+                        width,
+                        height,
+                        scale,
+                        header: true,
+                        pending: Vec::new(),
+                        frames: 0,
+                        failed: false,
+                        shared: Arc::clone(&worker_shared),
+                        stop: Arc::clone(&worker_stop),
+                        pid: Arc::clone(&worker_pid),
+                    };
+                    let result = device.shell_command(&[&command], &mut writer);
+                    if worker_stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    if let Err(e) = result {
+                        log::warn!("Screen stream ended: {}", e);
+                        error = Some(e.to_string());
+                        break;
+                    }
+                    // E.g. a device whose `screenrecord` lacks raw frames
+                    // prints its usage and exits right away.
+                    if writer.frames == 0 {
+                        let output = String::from_utf8_lossy(&writer.pending).trim().to_string();
+                        log::warn!("screenrecord exited without a frame: {}", output);
+                        error = Some(format!("screenrecord exited without a frame: {}", output));
+                        break;
+                    }
+                    thread::sleep(RESTART_DELAY);
+                }
+                worker_shared.close(error);
+                log::debug!("Screen stream worker exited");
+            })?;
+
+        log::info!("Streaming screen at {}x{}", width, height);
+        Ok(Self {
+            shared,
+            screen,
+            options,
+            seen: 0,
+            stop,
+            pid,
+            adb_addr,
+            serial: serial.to_string(),
+            worker: Some(worker),
+        })
+    }
+
+    /// Waits up to `timeout` for a frame newer than the last one returned.
+    pub fn next_timeout(&mut self, timeout: Duration) -> Option<Frame> {
+        let deadline = Instant::now() + timeout;
+        let mut slot = self.shared.lock();
+        while slot.sequence <= self.seen && !slot.closed {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return None;
+            }
+            slot = self
+                .shared
+                .changed
+                .wait_timeout(slot, remaining)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+        slot.take(&mut self.seen)
+    }
+
+    /// Returns the most recent frame.
+    ///
+    /// Because frames are only sent when the screen changes, the previous
+    /// frame is returned again while the screen is static. Before the first
+    /// frame arrives, this waits up to `timeout` for it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DroidError::AdbError` with the reason if the capture failed,
+    /// and `DroidError::Timeout` if no frame arrived in time.
+    pub fn latest(&mut self, timeout: Duration) -> Result<Frame> {
+        let slot = self.shared.lock();
+        if let Some(error) = &slot.error {
+            return Err(DroidError::AdbError(format!(
+                "Screen stream failed: {}",
+                error
+            )));
+        }
+        if slot.frame.is_some() {
+            return slot
+                .take(&mut self.seen)
+                .ok_or(DroidError::Timeout(timeout));
+        }
+        drop(slot);
+        match self.next_timeout(timeout) {
+            Some(frame) => Ok(frame),
+            None => match self.error() {
+                Some(error) => Err(DroidError::AdbError(format!(
+                    "Screen stream failed: {}",
+                    error
+                ))),
+                None => Err(DroidError::Timeout(timeout)),
+            },
+        }
+    }
+
+    /// Why the capture stopped, if it failed rather than being stopped.
+    pub fn error(&self) -> Option<String> {
+        self.shared.lock().error.clone()
+    }
+
+    /// The screen size in pixels, in the orientation the stream was started in.
+    pub fn screen_size(&self) -> (u32, u32) {
+        self.screen
+    }
+
+    /// The options the stream was started with.
+    pub fn options(&self) -> StreamOptions {
+        self.options
+    }
+
+    /// Whether the background capture is still running.
+    pub fn is_running(&self) -> bool {
+        self.worker
+            .as_ref()
+            .is_some_and(|worker| !worker.is_finished())
+    }
+
+    /// Stops `screenrecord` on the device, which ends the worker's read.
+    fn kill_recorder(&self) {
+        let pid = self.pid.load(Ordering::Relaxed);
+        if pid == 0 {
+            return;
+        }
+        let result = ADBServer::new(self.adb_addr)
+            .get_device_by_name(&self.serial)
+            .and_then(|mut device| {
+                device.shell_command(&["kill", &pid.to_string()], &mut io::sink())
+            });
+        if let Err(e) = result {
+            log::debug!("Could not stop screenrecord ({}): {}", pid, e);
+        }
+    }
+}
+
+impl Iterator for ScreenStream {
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Frame> {
+        let mut slot = self.shared.lock();
+        while slot.sequence <= self.seen && !slot.closed {
+            slot = self
+                .shared
+                .changed
+                .wait(slot)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        if slot.sequence <= self.seen {
+            return None;
+        }
+        slot.take(&mut self.seen)
+    }
+}
+
+impl Drop for ScreenStream {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.kill_recorder();
+        let Some(worker) = self.worker.take() else {
+            return;
+        };
+        let deadline = Instant::now() + STOP_TIMEOUT;
+        while !worker.is_finished() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(20));
+        }
+        if worker.is_finished() {
+            let _ = worker.join();
+        } else {
+            log::warn!(
+                "Screen stream worker did not stop within {:?}",
+                STOP_TIMEOUT
+            );
+        }
+    }
+}
+
+/// Splits the raw RGB byte stream into frames and publishes them.
+struct FrameWriter {
+    width: u32,
+    height: u32,
+    scale: f32,
+    /// Whether the pid line before the frames is still expected.
+    header: bool,
+    pending: Vec<u8>,
+    /// The number of frames published by this run of `screenrecord`.
+    frames: u64,
+    /// Set when the output did not start with a pid.
+    failed: bool,
+    shared: Arc<Shared>,
+    stop: Arc<AtomicBool>,
+    pid: Arc<AtomicU32>,
+}
+
+impl Write for FrameWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.stop.load(Ordering::Relaxed) {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "stream stopped"));
+        }
+
+        self.pending.extend_from_slice(buf);
+        if self.header {
+            let Some(end) = self.pending.iter().position(|&b| b == b'\n') else {
+                return Ok(buf.len());
+            };
+            // Without a pid the shell printed an error instead; keep all of
+            // it so the worker can report it.
+            let line = String::from_utf8_lossy(&self.pending[..end])
+                .trim()
+                .to_string();
+            let Ok(pid) = line.parse() else {
+                self.failed = true;
+                self.header = false;
+                return Ok(buf.len());
+            };
+            self.pending.drain(..=end);
+            self.pid.store(pid, Ordering::Relaxed);
+            self.header = false;
+        }
+        if self.failed {
+            return Ok(buf.len());
+        }
+
+        let frame_len = (self.width * self.height * 3) as usize;
+        while self.pending.len() >= frame_len {
+            let data: Vec<u8> = self.pending.drain(..frame_len).collect();
+            let Some(image) = RgbImage::from_raw(self.width, self.height, data) else {
+                continue;
+            };
+            self.shared.publish(Frame {
+                timestamp: Instant::now(),
+                image: DynamicImage::ImageRgb8(image),
+                scale: self.scale,
+            });
+            self.frames += 1;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn writer(shared: &Arc<Shared>, pid: &Arc<AtomicU32>) -> FrameWriter {
+        FrameWriter {
+            width: 2,
+            height: 1,
+            scale: 0.5,
+            header: true,
+            pending: Vec::new(),
+            frames: 0,
+            failed: false,
+            shared: Arc::clone(shared),
+            stop: Arc::new(AtomicBool::new(false)),
+            pid: Arc::clone(pid),
+        }
+    }
+
+    #[test]
+    fn splits_frames_after_the_pid() {
+        let shared = Arc::new(Shared::default());
+        let pid = Arc::new(AtomicU32::new(0));
+        let mut writer = writer(&shared, &pid);
+
+        // The pid line arrives in two pieces, the first frame with it.
+        writer.write_all(b"42").unwrap();
+        assert_eq!(pid.load(Ordering::Relaxed), 0);
+        writer
+            .write_all(b"17\n\x01\x02\x03\x04\x05\x06\x07")
+            .unwrap();
+        assert_eq!(pid.load(Ordering::Relaxed), 4217);
+        assert_eq!(writer.frames, 1);
+        assert_eq!(writer.pending, [7]);
+
+        writer.write_all(&[8, 9, 10, 11, 12]).unwrap();
+        assert_eq!(writer.frames, 2);
+        let slot = shared.lock();
+        assert_eq!(slot.sequence, 2);
+        let frame = slot.frame.as_ref().unwrap();
+        assert_eq!(frame.scale, 0.5);
+        assert_eq!(frame.image.to_rgb8().into_raw(), [7, 8, 9, 10, 11, 12]);
+    }
+
+    #[test]
+    fn keeps_error_output_instead_of_a_pid() {
+        let shared = Arc::new(Shared::default());
+        let pid = Arc::new(AtomicU32::new(0));
+        let mut writer = writer(&shared, &pid);
+
+        writer
+            .write_all(b"/system/bin/sh: screenrecord: not found\n")
+            .unwrap();
+        writer.write_all(b"more").unwrap();
+        assert_eq!(pid.load(Ordering::Relaxed), 0);
+        assert_eq!(writer.frames, 0);
+        assert_eq!(
+            writer.pending,
+            b"/system/bin/sh: screenrecord: not found\nmore"
+        );
+        assert_eq!(shared.lock().sequence, 0);
+    }
+
+    #[test]
+    fn stopped_writer_refuses_data() {
+        let shared = Arc::new(Shared::default());
+        let pid = Arc::new(AtomicU32::new(0));
+        let mut writer = writer(&shared, &pid);
+        writer.stop.store(true, Ordering::Relaxed);
+        assert!(writer.write(b"4217\n").is_err());
+    }
+}
@@ -0,0 +1,546 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::Droid;
+use crate::error::{DroidError, Result};
+
+/// What a path on the device is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    File,
+    Directory,
+    Symlink,
+    /// A device node, socket or pipe.
+    Other,
+}
+
+impl FileKind {
+    /// Reads the file type bits of a `st_mode`.
+    pub fn from_mode(mode: u32) -> Self {
+        match mode & 0o170000 {
+            0o100000 => FileKind::File,
+            0o040000 => FileKind::Directory,
+            0o120000 => FileKind::Symlink,
+            _ => FileKind::Other,
+        }
+    }
+}
+
+/// A file or directory on the device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteFile {
+    pub path: String,
+    pub kind: FileKind,
+    /// The size in bytes; not meaningful for directories.
+    pub size: u64,
+    pub modified: SystemTime,
+    /// The `st_mode`, including permission bits such as `0o644`.
+    pub mode: u32,
+}
+
+/// The state of a running transfer, passed to the progress callback.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferProgress {
+    /// The file being copied, as a path on the device.
+    pub path: String,
+    /// Bytes of the current file copied so far.
+    pub file_bytes: u64,
+    pub file_size: u64,
+    /// Bytes of all files to copy copied so far.
+    pub bytes: u64,
+    pub total_bytes: u64,
+    /// The number of files copied completely.
+    pub files: usize,
+    /// The number of files to copy, after skipping unchanged ones.
+    pub total_files: usize,
+}
+
+/// What a transfer did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TransferSummary {
+    pub files_copied: usize,
+    /// Files skipped by `sync(true)` because they were unchanged.
+    pub files_skipped: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Way {
+    Push,
+    Pull,
+}
+
+/// A file to copy, with its size and modification time at the source.
+struct Job {
+    local: PathBuf,
+    remote: String,
+    size: u64,
+    modified: SystemTime,
+}
+
+type ProgressCallback<'a> = Box<dyn FnMut(&TransferProgress) + 'a>;
+
+/// Builds and executes a copy of a file or directory tree between the host
+/// and the device.
+///
+/// Directories are copied recursively. Like `adb push` and `adb pull`, a
+/// file copied onto an existing directory is placed inside it.
+///
+/// This struct is created by the `Droid::push()` and `Droid::pull()` methods.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rust_droid::{Droid, DroidConfig};
+/// let mut droid = Droid::new(DroidConfig::default())?;
+/// let summary = droid
+///     .push("fixtures/photos", "/sdcard/Pictures/fixtures")
+///     .sync(true)
+///     .on_progress(|p| println!("{} {}/{} bytes", p.path, p.bytes, p.total_bytes))
+///     .execute()?;
+/// println!("{} copied, {} unchanged", summary.files_copied, summary.files_skipped);
+///
+/// droid.pull("/sdcard/Download/report.pdf", "out/").execute()?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct TransferBuilder<'a> {
+    droid: &'a mut Droid,
+    way: Way,
+    local: PathBuf,
+    remote: String,
+    sync: bool,
+    preserve_times: bool,
+    progress: Option<ProgressCallback<'a>>,
+}
+
+impl<'a> TransferBuilder<'a> {
+    pub(crate) fn push(droid: &'a mut Droid, local: PathBuf, remote: &str) -> Self {
+        Self::new(droid, Way::Push, local, remote)
+    }
+
+    pub(crate) fn pull(droid: &'a mut Droid, remote: &str, local: PathBuf) -> Self {
+        Self::new(droid, Way::Pull, local, remote)
+    }
+
+    fn new(droid: &'a mut Droid, way: Way, local: PathBuf, remote: &str) -> Self {
+        Self {
+            droid,
+            way,
+            local,
+            remote: remote.to_string(),
+            sync: false,
+            preserve_times: true,
+            progress: None,
+        }
+    }
+
+    /// Copies only files whose size or modification time differ from the
+    /// destination. Needs `preserve_times(true)` to be effective.
+    pub fn sync(mut self, enabled: bool) -> Self {
+        self.sync = enabled;
+        self
+    }
+
+    /// Gives copies the modification time of their source. Enabled by default.
+    pub fn preserve_times(mut self, enabled: bool) -> Self {
+        self.preserve_times = enabled;
+        self
+    }
+
+    /// Calls `callback` as data is copied.
+    ///
+    /// If a file has to be copied again after the device reconnected, its
+    /// progress starts over.
+    pub fn on_progress<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&TransferProgress) + 'a,
+    {
+        self.progress = Some(Box::new(callback));
+        self
+    }
+
+    pub fn execute(mut self) -> Result<TransferSummary> {
+        let jobs = match self.way {
+            Way::Push => self.push_jobs()?,
+            Way::Pull => self.pull_jobs()?,
+        };
+        let found = jobs.len();
+        let jobs: Vec<Job> = if self.sync {
+            let existing = self.destination_state()?;
+            jobs.into_iter()
+                .filter(|job| !is_unchanged(job, &existing, self.way))
+                .collect()
+        } else {
+            jobs
+        };
+        log::info!(
+            "{:?} {} files between {:?} and {} ({} unchanged)",
+            self.way,
+            jobs.len(),
+            self.local,
+            self.remote,
+            found - jobs.len()
+        );
+
+        let mut progress = TransferProgress {
+            path: String::new(),
+            file_bytes: 0,
+            file_size: 0,
+            bytes: 0,
+            total_bytes: jobs.iter().map(|job| job.size).sum(),
+            files: 0,
+            total_files: jobs.len(),
+        };
+        let controller = &mut self.droid.controller;
+        for job in &jobs {
+            progress.path = job.remote.clone();
+            progress.file_size = job.size;
+            let before = progress.bytes;
+            let mut report = |file_bytes: u64| {
+                progress.file_bytes = file_bytes;
+                progress.bytes = before + file_bytes;
+                if let Some(callback) = &mut self.progress {
+                    callback(&progress);
+                }
+            };
+            match self.way {
+                Way::Push => {
+                    controller.push_file_with_progress(&job.local, &job.remote, &mut report)?;
+                    if self.preserve_times {
+                        controller.set_modified(&job.remote, job.modified)?;
+                    }
+                }
+                Way::Pull => {
+                    if let Some(parent) = job.local.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    controller.pull_file(&job.remote, &job.local, &mut report)?;
+                    if self.preserve_times {
+                        fs::File::options()
+                            .write(true)
+                            .open(&job.local)?
+                            .set_modified(job.modified)?;
+                    }
+                }
+            }
+            progress.bytes = before + job.size;
+            progress.files += 1;
+        }
+
+        Ok(TransferSummary {
+            files_copied: jobs.len(),
+            files_skipped: found - jobs.len(),
+            bytes: progress.bytes,
+        })
+    }
+
+    fn push_jobs(&mut self) -> Result<Vec<Job>> {
+        let metadata = fs::metadata(&self.local)?;
+        let remote_root = self.remote.trim_end_matches('/').to_string();
+        if metadata.is_file() {
+            let remote = match self.droid.controller.stat(&remote_root)? {
+                Some(file) if file.kind == FileKind::Directory => {
+                    format!("{}/{}", remote_root, file_name(&self.local))
+                }
+                _ => remote_root,
+            };
+            self.remote = remote.clone();
+            return Ok(vec![Job {
+                local: self.local.clone(),
+                remote,
+                size: metadata.len(),
+                modified: metadata.modified()?,
+            }]);
+        }
+
+        let mut jobs = Vec::new();
+        let mut directories = vec![remote_root.clone()];
+        walk(&self.local, &mut |path, metadata| {
+            let relative = path
+                .strip_prefix(&self.local)
+                .unwrap_or(path)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let remote = format!("{}/{}", remote_root, relative);
+            if metadata.is_dir() {
+                directories.push(remote);
+            } else {
+                jobs.push(Job {
+                    local: path.to_path_buf(),
+                    remote,
+                    size: metadata.len(),
+                    modified: metadata.modified()?,
+                });
+            }
+            Ok(())
+        })?;
+        // Files create their parents on the device; this keeps empty directories.
+        self.droid.controller.make_dirs(&directories)?;
+        Ok(jobs)
+    }
+
+    fn pull_jobs(&mut self) -> Result<Vec<Job>> {
+        let remote_root = self.remote.trim_end_matches('/');
+        let Some(root) = self.droid.controller.stat(remote_root)? else {
+            return Err(DroidError::FileOperationFailed {
+                action: "pull".to_string(),
+                path: self.remote.clone(),
+                output: "No such file or directory".to_string(),
+            });
+        };
+        if root.kind != FileKind::Directory {
+            // A trailing separator names a directory that may not exist yet.
+            let into_dir = self
+                .local
+                .as_os_str()
+                .to_string_lossy()
+                .ends_with(['/', '\\']);
+            let local = if self.local.is_dir() || into_dir {
+                self.local.join(file_name(Path::new(remote_root)))
+            } else {
+                self.local.clone()
+            };
+            self.local = local.clone();
+            return Ok(vec![Job {
+                local,
+                remote: remote_root.to_string(),
+                size: root.size,
+                modified: root.modified,
+            }]);
+        }
+
+        fs::create_dir_all(&self.local)?;
+        let prefix = format!("{}/", remote_root);
+        let files = self.droid.controller.list_files(remote_root, true)?;
+        let mut jobs = Vec::new();
+        for file in files {
+            let Some(relative) = file.path.strip_prefix(&prefix) else {
+                continue;
+            };
+            let local = self.local.join(relative);
+            if file.kind == FileKind::Directory {
+                fs::create_dir_all(&local)?;
+            } else if file.kind == FileKind::File {
+                jobs.push(Job {
+                    local,
+                    remote: file.path,
+                    size: file.size,
+                    modified: file.modified,
+                });
+            }
+        }
+        Ok(jobs)
+    }
+
+    /// Returns the size and modification time of the existing destination files.
+    fn destination_state(&mut self) -> Result<HashMap<String, (u64, SystemTime)>> {
+        let mut state = HashMap::new();
+        match self.way {
+            Way::Push => {
+                let root = self.remote.trim_end_matches('/');
+                match self.droid.controller.stat(root)? {
+                    Some(file) if file.kind == FileKind::Directory => {
+                        for file in self.droid.controller.list_files(root, true)? {
+                            state.insert(file.path, (file.size, file.modified));
+                        }
+                    }
+                    Some(file) => {
+                        state.insert(file.path, (file.size, file.modified));
+                    }
+                    None => {}
+                }
+            }
+            Way::Pull => {
+                let mut insert = |path: &Path, metadata: &fs::Metadata| -> Result<()> {
+                    if metadata.is_file() {
+                        let key = path.to_string_lossy().into_owned();
+                        state.insert(key, (metadata.len(), metadata.modified()?));
+                    }
+                    Ok(())
+                };
+                match fs::metadata(&self.local) {
+                    Ok(metadata) if metadata.is_dir() => walk(&self.local, &mut insert)?,
+                    Ok(metadata) => insert(&self.local, &metadata)?,
+                    Err(_) => {}
+                }
+            }
+        }
+        Ok(state)
+    }
+}
+
+/// Whether the destination of `job` has the same size and modification
+/// time, to the second, as its source.
+fn is_unchanged(job: &Job, existing: &HashMap<String, (u64, SystemTime)>, way: Way) -> bool {
+    let key = match way {
+        Way::Push => job.remote.clone(),
+        Way::Pull => job.local.to_string_lossy().into_owned(),
+    };
+    let seconds = |time: SystemTime| {
+        time.duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+    };
+    existing.get(&key).is_some_and(|&(size, modified)| {
+        size == job.size && seconds(modified) == seconds(job.modified)
+    })
+}
+
+/// Calls `visit` for every file and directory below `root`, parents first.
+fn walk(root: &Path, visit: &mut dyn FnMut(&Path, &fs::Metadata) -> Result<()>) -> Result<()> {
+    for entry in fs::read_dir(root)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        visit(&entry.path(), &metadata)?;
+        if metadata.is_dir() {
+            walk(&entry.path(), visit)?;
+        }
+    }
+    Ok(())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Parses `stat -c '%f %s %Y %n'` lines: the mode in hex, size, modification
+/// time and path.
+///
+/// ```text
+/// 81a4 52311 1697630472 /sdcard/Download/report.pdf
+/// 41f9 3452 1697630410 /sdcard/Download/old
+/// ```
+pub(crate) fn parse_stat_lines(output: &str) -> Vec<RemoteFile> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(4, ' ');
+            let mode = u32::from_str_radix(fields.next()?, 16).ok()?;
+            let size = fields.next()?.parse().ok()?;
+            let modified: u64 = fields.next()?.parse().ok()?;
+            let path = fields.next()?.to_string();
+            Some(RemoteFile {
+                path,
+                kind: FileKind::from_mode(mode),
+                size,
+                modified: UNIX_EPOCH + Duration::from_secs(modified),
+                mode,
+            })
+        })
+        .collect()
+}
+
+/// Reports the number of bytes read or written so far.
+pub(crate) struct Counting<'a, T> {
+    pub inner: T,
+    pub count: u64,
+    pub report: &'a mut dyn FnMut(u64),
+}
+
+impl<T: Read> Read for Counting<'_, T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count += read as u64;
+        (self.report)(self.count);
+        Ok(read)
+    }
+}
+
+impl<T: Write> Write for Counting<'_, T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written as u64;
+        (self.report)(self.count);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_kinds_from_mode() {
+        assert_eq!(FileKind::from_mode(0o100644), FileKind::File);
+        assert_eq!(FileKind::from_mode(0o040771), FileKind::Directory);
+        assert_eq!(FileKind::from_mode(0o120777), FileKind::Symlink);
+        assert_eq!(FileKind::from_mode(0o020666), FileKind::Other);
+        assert_eq!(FileKind::from_mode(0o140777), FileKind::Other);
+    }
+
+    #[test]
+    fn parses_stat_lines() {
+        let output = "81a4 52311 1697630472 /sdcard/Download/report.pdf\n\
+                      41f9 3452 1697630410 /sdcard/Download/old scans\n\
+                      stat: '/sdcard/Download/gone': No such file or directory\n";
+        let files = parse_stat_lines(output);
+        assert_eq!(
+            files,
+            vec![
+                RemoteFile {
+                    path: "/sdcard/Download/report.pdf".to_string(),
+                    kind: FileKind::File,
+                    size: 52311,
+                    modified: UNIX_EPOCH + Duration::from_secs(1697630472),
+                    mode: 0o100644,
+                },
+                RemoteFile {
+                    path: "/sdcard/Download/old scans".to_string(),
+                    kind: FileKind::Directory,
+                    size: 3452,
+                    modified: UNIX_EPOCH + Duration::from_secs(1697630410),
+                    mode: 0o040771,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unchanged_by_size_and_second() {
+        let modified = UNIX_EPOCH + Duration::from_millis(1_697_630_472_250);
+        let job = Job {
+            local: PathBuf::from("out/report.pdf"),
+            remote: "/sdcard/Download/report.pdf".to_string(),
+            size: 52311,
+            modified,
+        };
+        let at = |size, millis| {
+            HashMap::from([(
+                "/sdcard/Download/report.pdf".to_string(),
+                (size, UNIX_EPOCH + Duration::from_millis(millis)),
+            )])
+        };
+
+        assert!(is_unchanged(&job, &at(52311, 1_697_630_472_900), Way::Push));
+        assert!(!is_unchanged(
+            &job,
+            &at(52311, 1_697_630_473_000),
+            Way::Push
+        ));
+        assert!(!is_unchanged(
+            &job,
+            &at(52310, 1_697_630_472_250),
+            Way::Push
+        ));
+        // Pulled files are looked up by their local path.
+        assert!(!is_unchanged(
+            &job,
+            &at(52311, 1_697_630_472_250),
+            Way::Pull
+        ));
+        let local = HashMap::from([("out/report.pdf".to_string(), (52311, modified))]);
+        assert!(is_unchanged(&job, &local, Way::Pull));
+        assert!(!is_unchanged(&job, &HashMap::new(), Way::Push));
+    }
+}
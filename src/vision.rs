@@ -80,3 +80,81 @@ pub fn find_template(
         Err(DroidError::ImageNotFound(needle_path.to_path_buf()))
     }
 }
+
+/// Measures how different two screen captures are.
+///
+/// Returns the mean absolute grayscale difference as a fraction, from `0.0` for
+/// identical images to `1.0` for fully inverted ones. Images of different sizes
+/// are treated as completely different. If `region` is set, only that part of
+/// both images is compared.
+pub fn image_difference(a: &DynamicImage, b: &DynamicImage, region: Option<DroidRect>) -> f32 {
+    if a.dimensions() != b.dimensions() {
+        return 1.0;
+    }
+
+    let (width, height) = a.dimensions();
+    let rect = region.unwrap_or(DroidRect::new(0, 0, width, height));
+    let x = rect.x.min(width);
+    let y = rect.y.min(height);
+    let w = rect.width.min(width - x);
+    let h = rect.height.min(height - y);
+    if w == 0 || h == 0 {
+        return 0.0;
+    }
+
+    let a_gray = a.to_luma8().view(x, y, w, h).to_image();
+    let b_gray = b.to_luma8().view(x, y, w, h).to_image();
+    let total: u64 = a_gray
+        .pixels()
+        .zip(b_gray.pixels())
+        .map(|(pa, pb)| u64::from(pa.0[0].abs_diff(pb.0[0])))
+        .sum();
+
+    let difference = total as f64 / (f64::from(w) * f64::from(h) * 255.0);
+    log::trace!("Image difference in {:?}: {:.5}", rect, difference);
+    difference as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    fn gray(width: u32, height: u32, value: u8) -> DynamicImage {
+        DynamicImage::ImageLuma8(GrayImage::from_pixel(width, height, Luma([value])))
+    }
+
+    #[test]
+    fn identical_and_inverted_images() {
+        let black = gray(4, 4, 0);
+        assert_eq!(image_difference(&black, &black, None), 0.0);
+        assert_eq!(image_difference(&black, &gray(4, 4, 255), None), 1.0);
+        assert!((image_difference(&black, &gray(4, 4, 51), None) - 0.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn different_sizes_are_completely_different() {
+        assert_eq!(image_difference(&gray(4, 4, 0), &gray(4, 5, 0), None), 1.0);
+    }
+
+    #[test]
+    fn region_limits_the_comparison() {
+        let a = gray(4, 4, 0);
+        let mut b = GrayImage::from_pixel(4, 4, Luma([0]));
+        b.put_pixel(3, 3, Luma([255]));
+        let b = DynamicImage::ImageLuma8(b);
+
+        assert!((image_difference(&a, &b, None) - 1.0 / 16.0).abs() < 1e-6);
+        assert_eq!(
+            image_difference(&a, &b, Some(DroidRect::new(0, 0, 2, 2))),
+            0.0
+        );
+        // A region reaching past the image is clamped to it.
+        let clamped = image_difference(&a, &b, Some(DroidRect::new(2, 2, 10, 10)));
+        assert!((clamped - 0.25).abs() < 1e-6);
+        assert_eq!(
+            image_difference(&a, &b, Some(DroidRect::new(9, 9, 2, 2))),
+            0.0
+        );
+    }
+}
@@ -0,0 +1,418 @@
+use std::fmt;
+use std::time::Instant;
+
+use image::DynamicImage;
+
+use crate::common::point::Point;
+use crate::common::relative_rect::RelativeRect;
+use crate::models::KeyCode;
+use crate::{Droid, DroidError, Result, Target};
+
+/// Custom watcher code, given the position the target was found at.
+type CustomHandler = Box<dyn FnMut(&mut Droid, Point) -> Result<()> + Send>;
+
+/// What a watcher does when its target shows up.
+pub(crate) enum Handler {
+    /// Taps the watcher's target where it was found.
+    TapTarget,
+    /// Taps another target, e.g. the "Later" button of a rating dialog.
+    Tap(Target),
+    /// Presses a key, e.g. `KeyCode::Back`.
+    Key(KeyCode),
+    /// Runs custom code.
+    Custom(CustomHandler),
+}
+
+impl fmt::Debug for Handler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Handler::TapTarget => write!(f, "TapTarget"),
+            Handler::Tap(target) => f.debug_tuple("Tap").field(target).finish(),
+            Handler::Key(key) => f.debug_tuple("Key").field(key).finish(),
+            Handler::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
+}
+
+/// How often a watcher has fired.
+#[derive(Debug, Clone)]
+pub struct WatcherStats {
+    pub name: String,
+    pub enabled: bool,
+    /// How many times the handler ran successfully.
+    pub fired: u32,
+    pub last_fired: Option<Instant>,
+}
+
+#[derive(Debug)]
+struct Watcher {
+    /// Tells a watcher apart from a later one registered under the same name.
+    id: u64,
+    name: String,
+    target: Target,
+    handler: Handler,
+    threshold: Option<f32>,
+    search_rect: Option<RelativeRect>,
+    enabled: bool,
+    fired: u32,
+    last_fired: Option<Instant>,
+}
+
+/// The watchers registered on a `Droid`.
+#[derive(Debug, Default)]
+pub(crate) struct Watchers {
+    entries: Vec<Watcher>,
+    next_id: u64,
+    /// Set while a handler runs, so handlers never trigger watchers themselves.
+    running: bool,
+}
+
+impl Watchers {
+    pub fn is_active(&self) -> bool {
+        !self.running && self.entries.iter().any(|w| w.enabled)
+    }
+
+    fn add(&mut self, mut watcher: Watcher) {
+        self.remove(&watcher.name);
+        watcher.id = self.next_id;
+        self.next_id += 1;
+        self.entries.push(watcher);
+    }
+
+    /// Lends out the handler of the watcher at `index` while it runs.
+    fn lend(&mut self, index: usize) -> Handler {
+        self.running = true;
+        // The placeholder is never run: no watcher fires while `running`.
+        std::mem::replace(&mut self.entries[index].handler, Handler::TapTarget)
+    }
+
+    /// Gives `handler` back to the watcher `id` and counts a successful run.
+    ///
+    /// Returns where the watcher is now, or `None` if the handler removed or
+    /// replaced it, in which case the handler is dropped.
+    fn give_back(&mut self, id: u64, handler: Handler, handled: bool) -> Option<usize> {
+        self.running = false;
+        let position = self.entries.iter().position(|w| w.id == id)?;
+        let watcher = &mut self.entries[position];
+        watcher.handler = handler;
+        if handled {
+            watcher.fired += 1;
+            watcher.last_fired = Some(Instant::now());
+        }
+        Some(position)
+    }
+
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        match self.entries.iter_mut().find(|w| w.name == name) {
+            Some(watcher) => {
+                watcher.enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|w| w.name != name);
+        self.entries.len() != before
+    }
+
+    pub fn stats(&self) -> Vec<WatcherStats> {
+        self.entries
+            .iter()
+            .map(|w| WatcherStats {
+                name: w.name.clone(),
+                enabled: w.enabled,
+                fired: w.fired,
+                last_fired: w.last_fired,
+            })
+            .collect()
+    }
+
+    /// Runs the handler of the first enabled watcher of `droid` whose target
+    /// is on `screen`.
+    ///
+    /// Returns the name of the watcher that fired. Watchers are checked in
+    /// registration order, and at most one fires per call. Handlers may
+    /// register, remove, enable or disable watchers, including their own.
+    pub fn check(droid: &mut Droid, screen: &DynamicImage, scale: f32) -> Result<Option<String>> {
+        let mut index = 0;
+        while let Some(watcher) = droid.watchers.entries.get(index) {
+            if !watcher.enabled {
+                index += 1;
+                continue;
+            }
+            let threshold = watcher.threshold.unwrap_or(droid.config.default_confidence);
+            let point = match droid.resolve_target_on(
+                &watcher.target,
+                threshold,
+                watcher.search_rect,
+                screen,
+                scale,
+            ) {
+                Ok(point) => point,
+                Err(DroidError::ImageNotFound(_)) => {
+                    index += 1;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            let id = watcher.id;
+            let name = watcher.name.clone();
+            log::info!("Watcher '{}' matched at {:?}", name, point);
+            // The watchers stay in place while the handler runs, so its
+            // changes to them are kept.
+            let mut handler = droid.watchers.lend(index);
+            let handled = match &mut handler {
+                Handler::TapTarget => droid.touch(Target::Point(point)).execute().map(|_| ()),
+                Handler::Tap(target) => droid.touch(target.clone()).execute().map(|_| ()),
+                Handler::Key(key) => droid.keyevent(*key).execute().map(|_| ()),
+                Handler::Custom(handler) => handler(droid, point),
+            };
+            let position = droid.watchers.give_back(id, handler, handled.is_ok());
+            match handled {
+                Ok(()) => {
+                    // Give the dismissed dialog time to disappear.
+                    droid.sleep(droid.config.default_interval);
+                    return Ok(Some(name));
+                }
+                Err(e) => {
+                    log::warn!("Watcher '{}' failed to handle: {}", name, e);
+                    // Go on after the failed watcher, wherever it is now.
+                    index = position.map_or(index, |position| position + 1);
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Registers a watcher that handles an interruption, such as a popup.
+///
+/// Registered watchers are checked whenever an image target cannot be found,
+/// e.g. while touching or waiting for it. If a watcher's target is on
+/// screen, its handler runs and the search is repeated once.
+///
+/// This struct is created by the `Droid::watch()` method.
+///
+/// # Example
+///
+/// ```no_run
+/// # use rust_droid::{Droid, DroidConfig, Target, models::KeyCode};
+/// let mut droid = Droid::new(DroidConfig::default())?;
+/// droid
+///     .watch("rate_app", Target::from("rate_dialog.png"))
+///     .tap_on(Target::from("later_button.png"))
+///     .register()?;
+/// droid
+///     .watch("not_responding", Target::from("anr_dialog.png"))
+///     .press(KeyCode::Back)
+///     .register()?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct WatcherBuilder<'a> {
+    droid: &'a mut Droid,
+    name: String,
+    target: Target,
+    handler: Handler,
+    threshold: Option<f32>,
+    search_rect: Option<RelativeRect>,
+}
+
+impl<'a> WatcherBuilder<'a> {
+    pub fn new(droid: &'a mut Droid, name: &str, target: Target) -> Self {
+        Self {
+            droid,
+            name: name.to_string(),
+            target,
+            handler: Handler::TapTarget,
+            threshold: None,
+            search_rect: None,
+        }
+    }
+
+    /// Taps the watcher's target itself. This is the default.
+    pub fn tap(mut self) -> Self {
+        self.handler = Handler::TapTarget;
+        self
+    }
+
+    /// Taps another target when the watcher's target is found.
+    pub fn tap_on(mut self, target: Target) -> Self {
+        self.handler = Handler::Tap(target);
+        self
+    }
+
+    /// Presses a key when the watcher's target is found.
+    pub fn press(mut self, key_code: KeyCode) -> Self {
+        self.handler = Handler::Key(key_code);
+        self
+    }
+
+    /// Runs `handler` when the watcher's target is found.
+    ///
+    /// Watchers are not checked while a handler runs.
+    pub fn run<F>(mut self, handler: F) -> Self
+    where
+        F: FnMut(&mut Droid, Point) -> Result<()> + Send + 'static,
+    {
+        self.handler = Handler::Custom(Box::new(handler));
+        self
+    }
+
+    pub fn threshold(mut self, value: f32) -> Self {
+        self.threshold = Some(value);
+        self
+    }
+
+    pub fn search_in(mut self, rect: RelativeRect) -> Self {
+        self.search_rect = Some(rect);
+        self
+    }
+
+    /// Registers the watcher, replacing any watcher with the same name.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DroidError::InvalidTarget` if the target is a point, which
+    /// would match every screen.
+    pub fn register(self) -> Result<()> {
+        check_target(&self.name, &self.target)?;
+        log::debug!("Registered watcher '{}' for {:?}", self.name, self.target);
+        self.droid.watchers.add(Watcher {
+            id: 0,
+            name: self.name,
+            target: self.target,
+            handler: self.handler,
+            threshold: self.threshold,
+            search_rect: self.search_rect,
+            enabled: true,
+            fired: 0,
+            last_fired: None,
+        });
+        Ok(())
+    }
+}
+
+/// Rejects point targets, which are "found" on every screen.
+fn check_target(name: &str, target: &Target) -> Result<()> {
+    match target {
+        Target::Point(p) => Err(DroidError::InvalidTarget(format!(
+            "watcher '{}' needs an image target, not the point {:?}",
+            name, p
+        ))),
+        Target::Image(_) => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watcher(name: &str) -> Watcher {
+        Watcher {
+            id: 0,
+            name: name.to_string(),
+            target: Target::from(format!("{}.png", name).as_str()),
+            handler: Handler::Key(KeyCode::Back),
+            threshold: None,
+            search_rect: None,
+            enabled: true,
+            fired: 0,
+            last_fired: None,
+        }
+    }
+
+    fn names(watchers: &Watchers) -> Vec<&str> {
+        watchers.entries.iter().map(|w| w.name.as_str()).collect()
+    }
+
+    #[test]
+    fn registering_a_name_again_replaces_the_watcher() {
+        let mut watchers = Watchers::default();
+        watchers.add(watcher("rate"));
+        watchers.add(watcher("anr"));
+        watchers.add(watcher("rate"));
+        assert_eq!(names(&watchers), ["anr", "rate"]);
+        assert_ne!(watchers.entries[0].id, watchers.entries[1].id);
+    }
+
+    #[test]
+    fn enable_disable_and_remove() {
+        let mut watchers = Watchers::default();
+        watchers.add(watcher("rate"));
+        assert!(watchers.is_active());
+        assert!(watchers.set_enabled("rate", false));
+        assert!(!watchers.is_active());
+        assert!(!watchers.set_enabled("missing", true));
+        assert!(watchers.remove("rate"));
+        assert!(!watchers.remove("rate"));
+    }
+
+    #[test]
+    fn inactive_while_a_handler_runs() {
+        let mut watchers = Watchers::default();
+        watchers.add(watcher("rate"));
+        let id = watchers.entries[0].id;
+        let handler = watchers.lend(0);
+        assert!(!watchers.is_active());
+
+        assert_eq!(watchers.give_back(id, handler, true), Some(0));
+        assert!(watchers.is_active());
+        assert!(matches!(
+            watchers.entries[0].handler,
+            Handler::Key(KeyCode::Back)
+        ));
+        let stats = watchers.stats();
+        assert_eq!(stats[0].fired, 1);
+        assert!(stats[0].last_fired.is_some());
+    }
+
+    #[test]
+    fn keeps_changes_made_by_a_handler() {
+        let mut watchers = Watchers::default();
+        watchers.add(watcher("first"));
+        watchers.add(watcher("rate"));
+        let id = watchers.entries[1].id;
+        let handler = watchers.lend(1);
+
+        // The handler removes a watcher, adds one and disables itself.
+        watchers.remove("first");
+        watchers.add(watcher("later"));
+        watchers.set_enabled("rate", false);
+
+        assert_eq!(watchers.give_back(id, handler, false), Some(0));
+        assert_eq!(names(&watchers), ["rate", "later"]);
+        let stats = watchers.stats();
+        assert!(!stats[0].enabled);
+        assert_eq!(stats[0].fired, 0);
+    }
+
+    #[test]
+    fn drops_the_handler_of_a_replaced_watcher() {
+        let mut watchers = Watchers::default();
+        watchers.add(watcher("rate"));
+        let id = watchers.entries[0].id;
+        let handler = watchers.lend(0);
+
+        let mut replacement = watcher("rate");
+        replacement.handler = Handler::TapTarget;
+        watchers.add(replacement);
+
+        assert_eq!(watchers.give_back(id, handler, true), None);
+        assert!(matches!(watchers.entries[0].handler, Handler::TapTarget));
+        assert_eq!(watchers.stats()[0].fired, 0);
+    }
+
+    #[test]
+    fn point_targets_are_rejected() {
+        let point = Target::Point(Point::new(10, 20));
+        assert!(matches!(
+            check_target("tap", &point),
+            Err(DroidError::InvalidTarget(_))
+        ));
+        assert!(check_target("rate", &Target::from("rate.png")).is_ok());
+    }
+}